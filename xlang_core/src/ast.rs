@@ -1,4 +1,7 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use xlang_util::format::{NodeDisplay, TreeDisplay};
 
@@ -137,6 +140,20 @@ impl ParamaterList {
     pub fn iter_items(&self) -> impl Iterator<Item = &Param> + '_ {
         self.items.iter_items()
     }
+
+    /// Destructuring parameters, keyed by the name their argument is bound to
+    pub fn patterns(&self) -> Vec<(String, Pattern)> {
+        self.iter_items()
+            .filter_map(|param| Some((param.binding()?, param.pattern.clone()?)))
+            .collect()
+    }
+
+    /// Default values of parameters, keyed by the name of the parameter
+    pub fn defaults(&self) -> Vec<(String, Expression)> {
+        self.iter_items()
+            .filter_map(|param| Some((param.binding()?, param.default.clone()?)))
+            .collect()
+    }
 }
 
 impl NodeDisplay for ParamaterList {
@@ -163,13 +180,21 @@ impl TreeDisplay for ParamaterList {
 pub struct Param {
     pub ty: Option<Type>,
     pub name: Option<SpannedToken>,
+    /// `Type {a, b}`, destructuring the argument instead of naming it
+    pub pattern: Option<Pattern>,
+    /// `Type name = value`, the value of a record field that isn't given when instantiating it
+    pub default: Option<Expression>,
 }
 
 impl AstNode for Param {
     fn get_range(&self) -> Range {
-        match (&self.name, &self.ty) {
-            (Some(name), None) => Range::from(*name.span()),
-            (Some(name), Some(value)) => Range::from((name, &value.get_range())),
+        match (&self.name, &self.pattern, &self.ty) {
+            (Some(name), _, None) => Range::from(*name.span()),
+            (Some(name), _, Some(value)) => Range::from((name, &value.get_range())),
+            (None, Some(pattern), None) => pattern.get_range(),
+            (None, Some(pattern), Some(value)) => {
+                Range::from((&value.get_range(), &pattern.get_range()))
+            }
             _ => Range::default(),
         }
     }
@@ -182,6 +207,23 @@ impl Param {
             _ => panic!(),
         }
     }
+
+    /// Name the argument is bound to. Destructuring parameters are bound to the text of their
+    /// pattern, which can't be referred to as an identifier.
+    pub fn binding(&self) -> Option<String> {
+        match (&self.name, &self.pattern) {
+            (Some(name), _) => Some(name.as_str().to_string()),
+            (None, Some(pattern)) => {
+                let names: Vec<_> = pattern
+                    .bindings()
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect();
+                Some(format!("{{{}}}", names.join(", ")))
+            }
+            _ => None,
+        }
+    }
 }
 
 impl NodeDisplay for Param {
@@ -192,11 +234,11 @@ impl NodeDisplay for Param {
 
 impl TreeDisplay for Param {
     fn num_children(&self) -> usize {
-        addup!(self.ty, self.name)
+        addup!(self.ty, self.name, self.pattern, self.default)
     }
 
     fn child_at(&self, index: usize) -> Option<&dyn TreeDisplay> {
-        switchon!(index, &self.ty, &self.name);
+        switchon!(index, &self.ty, &self.name, &self.pattern, &self.default);
         None
     }
 }
@@ -243,6 +285,18 @@ impl TreeDisplay for ArgList {
     }
 }
 
+/// Identifies a node of the AST. Ids are unique among everything parsed, and a clone of a node
+/// keeps its id, so it's the same wherever the node is evaluated from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn next() -> NodeId {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        NodeId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 #[derive(Clone)]
 pub enum Type {
     Integer {
@@ -254,7 +308,28 @@ pub enum Type {
         width: u8,
         token: SpannedToken,
     },
-    Ident(SpannedToken),
+    /// `bool`
+    Boolean(SpannedToken),
+    /// `string`
+    String(SpannedToken),
+    /// A record or other named type, along with an id so what it resolves to can be cached
+    Ident(SpannedToken, NodeId),
+    Optional {
+        ty: Box<Type>,
+        question: SpannedToken,
+    },
+    /// `[T]`, or `[T; n]` for arrays of a fixed length
+    Array {
+        ty: Box<Type>,
+        len: Option<SpannedToken>,
+        /// Range of the brackets
+        range: Range,
+    },
+    /// `&T`
+    Ref {
+        amp: SpannedToken,
+        ty: Box<Type>,
+    },
 }
 
 impl PartialEq for Type {
@@ -275,10 +350,31 @@ impl PartialEq for Type {
             (Self::Float { width: l_width, .. }, Self::Float { width: r_width, .. }) => {
                 l_width == r_width
             }
+            (Self::Boolean(_), Self::Boolean(_)) | (Self::String(_), Self::String(_)) => true,
             (
-                Self::Ident(SpannedToken(_, Token::Ident(a))),
-                Self::Ident(SpannedToken(_, Token::Ident(b))),
+                Self::Ident(SpannedToken(_, Token::Ident(a)), _),
+                Self::Ident(SpannedToken(_, Token::Ident(b)), _),
             ) => a == b,
+            (Self::Optional { ty: l_ty, .. }, Self::Optional { ty: r_ty, .. }) => l_ty == r_ty,
+            (
+                Self::Array {
+                    ty: l_ty,
+                    len: l_len,
+                    ..
+                },
+                Self::Array {
+                    ty: r_ty,
+                    len: r_len,
+                    ..
+                },
+            ) => {
+                let len = |len: &Option<SpannedToken>| match len {
+                    Some(SpannedToken(_, Token::Integer(len))) => Some(*len),
+                    _ => None,
+                };
+                l_ty == r_ty && len(l_len) == len(r_len)
+            }
+            (Self::Ref { ty: l_ty, .. }, Self::Ref { ty: r_ty, .. }) => l_ty == r_ty,
             _ => false,
         }
     }
@@ -289,7 +385,11 @@ impl AstNode for Type {
         match self {
             Self::Integer { token, .. } => token.span().into(),
             Self::Float { token, .. } => token.span().into(),
-            Self::Ident(ident) => ident.span().into(),
+            Self::Boolean(token) | Self::String(token) => token.span().into(),
+            Self::Ident(ident, _) => ident.span().into(),
+            Self::Optional { ty, question } => Range::from((&ty.get_range(), *question.span())),
+            Self::Array { range, .. } => *range,
+            Self::Ref { amp, ty } => Range::from((*amp.span(), &ty.get_range())),
         }
     }
 }
@@ -308,7 +408,25 @@ impl NodeDisplay for Type {
                 signed: false,
                 ..
             } => write!(f, "u{width}"),
-            Self::Ident(ident) => <SpannedToken as NodeDisplay>::fmt(ident, f),
+            Self::Boolean(_) => f.write_str("bool"),
+            Self::String(_) => f.write_str("string"),
+            Self::Ident(ident, _) => <SpannedToken as NodeDisplay>::fmt(ident, f),
+            Self::Optional { ty, .. } => {
+                <Type as NodeDisplay>::fmt(ty, f)?;
+                f.write_str("?")
+            }
+            Self::Array { ty, len, .. } => {
+                f.write_str("[")?;
+                <Type as NodeDisplay>::fmt(ty, f)?;
+                if let Some(SpannedToken(_, Token::Integer(len))) = len {
+                    write!(f, "; {len}")?;
+                }
+                f.write_str("]")
+            }
+            Self::Ref { ty, .. } => {
+                f.write_str("&")?;
+                <Type as NodeDisplay>::fmt(ty, f)
+            }
         }
     }
 }
@@ -332,7 +450,69 @@ impl TreeDisplay for Type {
 #[derive(Clone)]
 pub enum ParsedTemplate {
     String(SpannedToken),
-    Template(Box<Expression>, SpannedToken, SpannedToken),
+    Template(
+        Box<Expression>,
+        SpannedToken,
+        SpannedToken,
+        Option<FormatSpec>,
+    ),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+/// `[align][0][width][.precision]` following an interpolated expression, like the `04` in
+/// `{x:04}` or the `.2` in `{pi:.2}`. Alignment is one of `<`, `>` or `^`.
+#[derive(Clone)]
+pub struct FormatSpec {
+    pub align: Option<Alignment>,
+    /// Pad with zeros after the sign instead of with spaces
+    pub zero: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub token: SpannedToken,
+}
+
+impl FormatSpec {
+    /// Parses the text of `token`, or `None` if it isn't a valid specifier
+    pub fn parse(token: SpannedToken) -> Option<FormatSpec> {
+        let text = token.as_str();
+        let (align, text) = match text.chars().next() {
+            Some('<') => (Some(Alignment::Left), &text[1..]),
+            Some('>') => (Some(Alignment::Right), &text[1..]),
+            Some('^') => (Some(Alignment::Center), &text[1..]),
+            _ => (None, text),
+        };
+        let (zero, text) = match text.strip_prefix('0') {
+            Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => (true, rest),
+            _ => (false, text),
+        };
+        let (width, text) = match text.find(|c: char| !c.is_ascii_digit()) {
+            Some(0) => (None, text),
+            Some(end) => (Some(text[..end].parse().ok()?), &text[end..]),
+            None if text.is_empty() => (None, text),
+            None => (Some(text.parse().ok()?), ""),
+        };
+        let precision = match text.strip_prefix('.') {
+            Some(digits) if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) => {
+                Some(digits.parse().ok()?)
+            }
+            _ if text.is_empty() => None,
+            _ => return None,
+        };
+
+        Some(FormatSpec {
+            align,
+            zero,
+            width,
+            precision,
+            token,
+        })
+    }
 }
 
 impl NodeDisplay for ParsedTemplate {
@@ -380,29 +560,248 @@ pub enum Expression {
         right: Option<Box<Expression>>,
         op_token: Option<SpannedToken>,
     },
-    Integer(u64, Option<Unit>, SpannedToken),
-    Float(f64, Option<Unit>, SpannedToken),
+    /// A number with an optional unit, and a type suffix like `5u8` or `1.5f32`
+    Integer(u64, Option<Unit>, Option<Type>, SpannedToken),
+    Float(f64, Option<Unit>, Option<Type>, SpannedToken),
     Ident(SpannedToken),
     String(ParsedTemplateString, SpannedToken),
     FunctionCall {
         expr: Box<Expression>,
         args: ArgList,
     },
-    Tuple(Vec<Expression>),
+    /// `(a, b)`, with the label of each element if it was given one like `(x: a, y: b)`
+    Tuple(Vec<Expression>, Vec<Option<SpannedToken>>),
+    /// Expressions separated by semicolons like `(a; b; c)`, evaluating to the last one
+    Sequence(Vec<Expression>),
     Array {
         values: PunctuationList<Expression>,
         range: Range,
     },
+    Index {
+        expr: Box<Expression>,
+        index: Option<Box<Expression>>,
+        /// Range of the square brackets
+        range: Range,
+    },
     Function {
+        /// `pure` keyword before the parameters
+        pure: Option<SpannedToken>,
         parameters: ParamaterList,
         arrow: SpannedToken,
         return_parameters: ParamaterList,
+        /// `requires` keyword and the precondition checked before the body runs
+        requires: Option<(SpannedToken, Box<Expression>)>,
         comma: Option<SpannedToken>,
         body: Option<Box<Statement>>,
     },
     Record {
         parameters: ParamaterList,
     },
+    /// Record instance like `{ a: 1, b: a + 1 }`, where each field is a declaration
+    RecordLiteral {
+        /// `Name { .. }`, instantiating the record `Name` instead of an anonymous one
+        name: Option<SpannedToken>,
+        fields: PunctuationList<Statement>,
+        /// Range of the braces
+        range: Range,
+    },
+    /// `if let pattern = expr { body }`, where the body only runs if the pattern matches and
+    /// bindings from the pattern are only visible in the body
+    IfLet {
+        if_token: SpannedToken,
+        pattern: Pattern,
+        expr: Box<Expression>,
+        body: Box<Statement>,
+        /// Range of the braces
+        range: Range,
+    },
+    /// `if cond { body } elif cond { body } else { body }`, yielding the value of the body of the
+    /// first branch whose condition is true
+    If {
+        if_token: SpannedToken,
+        /// Each condition along with its body
+        branches: Vec<(Expression, Statement)>,
+        else_body: Option<Box<Statement>>,
+        /// Range of the braces of the last body
+        range: Range,
+    },
+    /// `for pattern in expr { body }`, where the body runs for each element of an array or
+    /// range with the bindings from the pattern
+    For {
+        for_token: SpannedToken,
+        pattern: Pattern,
+        expr: Box<Expression>,
+        body: Box<Statement>,
+        /// Range of the braces
+        range: Range,
+    },
+    /// `repeat count { body }`, which runs the body `count` times
+    Repeat {
+        repeat_token: SpannedToken,
+        count: Box<Expression>,
+        body: Box<Statement>,
+        /// Range of the braces
+        range: Range,
+    },
+    /// `while cond { body }`, which runs the body for as long as the condition is true
+    While {
+        while_token: SpannedToken,
+        condition: Box<Expression>,
+        body: Box<Statement>,
+        /// Range of the braces
+        range: Range,
+    },
+    /// `with expr as binding { body }`, where the body runs with `binding` bound to the resource
+    /// and `binding.close()` is called once it's done
+    With {
+        with_token: SpannedToken,
+        expr: Box<Expression>,
+        binding: SpannedToken,
+        body: Box<Statement>,
+        /// Range of the braces
+        range: Range,
+    },
+    /// `match expr { pattern => body, ... }`, evaluating the body of the first arm whose pattern
+    /// matches the value
+    Match {
+        match_token: SpannedToken,
+        expr: Box<Expression>,
+        arms: PunctuationList<MatchArm>,
+        /// Range of the braces
+        range: Range,
+    },
+    /// `defer expr`, where `expr` is run when the enclosing scope exits
+    Defer {
+        defer_token: SpannedToken,
+        expr: Box<Expression>,
+    },
+    /// `return` or `return expr`, leaving the enclosing function. The value, if any, is given to
+    /// the function's only return parameter.
+    Return {
+        return_token: SpannedToken,
+        expr: Option<Box<Expression>>,
+    },
+}
+
+#[derive(Clone)]
+pub enum Pattern {
+    /// `Some(binding)`, matching an optional that isn't null
+    Some {
+        some: SpannedToken,
+        binding: SpannedToken,
+    },
+    /// A single binding, matching any value
+    Ident(SpannedToken),
+    /// `(a, b)`, matching a tuple with one value for each binding
+    Tuple {
+        bindings: Vec<SpannedToken>,
+        /// Range of the parentheses
+        range: Range,
+    },
+    /// `{a, b}`, matching a record instance with a field named after each binding
+    Record {
+        bindings: Vec<SpannedToken>,
+        /// Range of the braces
+        range: Range,
+    },
+    /// `1 | 2 | 3`, matching a value equal to any of the literals
+    Literals(Vec<Expression>),
+    /// `binding: Type`, matching a value of that type. Optionals that aren't null are narrowed
+    /// to their value.
+    Typed { binding: SpannedToken, ty: Type },
+}
+
+impl Pattern {
+    /// Names bound when the pattern matches
+    pub fn bindings(&self) -> Vec<&SpannedToken> {
+        match self {
+            Self::Some { binding, .. } | Self::Ident(binding) | Self::Typed { binding, .. } => {
+                vec![binding]
+            }
+            Self::Tuple { bindings, .. } | Self::Record { bindings, .. } => {
+                bindings.iter().collect()
+            }
+            Self::Literals(_) => Vec::new(),
+        }
+    }
+}
+
+impl AstNode for Pattern {
+    fn get_range(&self) -> Range {
+        match self {
+            Self::Some { some, binding } => Range::from((*some.span(), *binding.span())),
+            Self::Ident(binding) => binding.span().into(),
+            Self::Tuple { range, .. } | Self::Record { range, .. } => *range,
+            Self::Literals(literals) => match (literals.first(), literals.last()) {
+                (Some(first), Some(last)) => Range::from((&first.get_range(), &last.get_range())),
+                _ => Range::default(),
+            },
+            Self::Typed { binding, ty } => Range::from((*binding.span(), &ty.get_range())),
+        }
+    }
+}
+
+impl NodeDisplay for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Some { binding, .. } => write!(f, "Some({})", binding.as_str()),
+            Self::Ident(binding) => f.write_str(binding.as_str()),
+            Self::Tuple { bindings, .. } => {
+                let names: Vec<_> = bindings.iter().map(|binding| binding.as_str()).collect();
+                write!(f, "({})", names.join(", "))
+            }
+            Self::Record { bindings, .. } => {
+                let names: Vec<_> = bindings.iter().map(|binding| binding.as_str()).collect();
+                write!(f, "{{{}}}", names.join(", "))
+            }
+            Self::Literals(_) => f.write_str("Literals"),
+            Self::Typed { binding, ty } => {
+                write!(f, "{}: ", binding.as_str())?;
+                NodeDisplay::fmt(ty, f)
+            }
+        }
+    }
+}
+
+impl TreeDisplay for Pattern {
+    fn num_children(&self) -> usize {
+        0
+    }
+
+    fn child_at(&self, _index: usize) -> Option<&dyn TreeDisplay> {
+        None
+    }
+}
+
+/// `pattern => body`
+#[derive(Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub arrow: SpannedToken,
+    pub body: Statement,
+}
+
+impl AstNode for MatchArm {
+    fn get_range(&self) -> Range {
+        Range::from((&self.pattern.get_range(), &self.body.get_range()))
+    }
+}
+
+impl NodeDisplay for MatchArm {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("Arm ")?;
+        NodeDisplay::fmt(&self.pattern, f)
+    }
+}
+
+impl TreeDisplay for MatchArm {
+    fn num_children(&self) -> usize {
+        1
+    }
+
+    fn child_at(&self, _index: usize) -> Option<&dyn TreeDisplay> {
+        Some(&self.body)
+    }
 }
 
 impl Expression {
@@ -451,15 +850,56 @@ impl AstNode for Expression {
             Self::BinaryExpression {
                 op_token: Some(op), ..
             } => Range::from(*op.span()),
-            Self::Tuple(s) => match (s.first(), s.last()) {
+            Self::Tuple(s, _) | Self::Sequence(s) => match (s.first(), s.last()) {
                 (Some(s), Some(e)) => Range::from((&s.get_range(), &e.get_range())),
                 _ => Range::default(),
             },
             Self::Array { range, .. } => *range,
-            Self::Integer(_, _, s) => s.0.into(),
-            Self::Float(_, _, s) => s.0.into(),
+            Self::Index { expr, range, .. } => Range::from((&expr.get_range(), range)),
+            Self::RecordLiteral {
+                name: Some(name),
+                range,
+                ..
+            } => Range::from((*name.span(), range)),
+            Self::RecordLiteral { range, .. } => *range,
+            Self::IfLet {
+                if_token, range, ..
+            } => Range::from((*if_token.span(), range)),
+            Self::If {
+                if_token, range, ..
+            } => Range::from((*if_token.span(), range)),
+            Self::For {
+                for_token, range, ..
+            } => Range::from((*for_token.span(), range)),
+            Self::Repeat {
+                repeat_token,
+                range,
+                ..
+            } => Range::from((*repeat_token.span(), range)),
+            Self::While {
+                while_token, range, ..
+            } => Range::from((*while_token.span(), range)),
+            Self::With {
+                with_token, range, ..
+            } => Range::from((*with_token.span(), range)),
+            Self::Match {
+                match_token, range, ..
+            } => Range::from((*match_token.span(), range)),
+            Self::Defer { defer_token, expr } => {
+                Range::from((&Range::from(*defer_token.span()), &expr.get_range()))
+            }
+            Self::Return {
+                return_token,
+                expr: Some(expr),
+            } => Range::from((&Range::from(*return_token.span()), &expr.get_range())),
+            Self::Return { return_token, .. } => (*return_token.span()).into(),
+            Self::Integer(_, _, Some(ty), s) | Self::Float(_, _, Some(ty), s) => {
+                Range::from((s.0, &ty.get_range()))
+            }
+            Self::Integer(_, _, None, s) => s.0.into(),
+            Self::Float(_, _, None, s) => s.0.into(),
             Self::Ident(s) => s.0.into(),
-            // Self::String(s) => s.0.into(),
+            Self::String(_, s) => s.0.into(),
             Self::FunctionCall { expr, args } => {
                 Range::from((&expr.get_range(), &args.get_range()))
             }
@@ -478,14 +918,35 @@ impl NodeDisplay for Expression {
                 ..
             } => write!(f, "BinExp {}", op.as_str()),
             Self::BinaryExpression { .. } => write!(f, "BinExp"),
-            Self::Integer(i, Some(u), _) => write!(f, "{i}{u}"),
-            Self::Float(i, Some(u), _) => write!(f, "{i}{u}"),
-            Self::Integer(i, None, _) => write!(f, "{i}"),
-            Self::Float(i, None, _) => write!(f, "{i}"),
+            Self::Integer(i, Some(u), _, _) => write!(f, "{i}{u}"),
+            Self::Float(i, Some(u), _, _) => write!(f, "{i}{u}"),
+            Self::Integer(i, None, Some(ty), _) => write!(f, "{i}{ty:?}"),
+            Self::Float(i, None, Some(ty), _) => write!(f, "{i}{ty:?}"),
+            Self::Integer(i, None, None, _) => write!(f, "{i}"),
+            Self::Float(i, None, None, _) => write!(f, "{i}"),
             Self::Ident(SpannedToken(_, Token::Ident(i))) => write!(f, "{i}"),
             Self::String(_pts, _) => write!(f, "\"{:?}\"", "kkjflsd"),
             Self::FunctionCall { .. } => write!(f, "FunctionCall"),
+            Self::Tuple(..) => f.write_str("Tuple"),
+            Self::Sequence(_) => f.write_str("Sequence"),
             Self::Array { .. } => f.write_str("Array"),
+            Self::Index { .. } => f.write_str("Index"),
+            Self::RecordLiteral { .. } => f.write_str("Record Literal"),
+            Self::IfLet { pattern, .. } => {
+                f.write_str("If Let ")?;
+                NodeDisplay::fmt(pattern, f)
+            }
+            Self::If { .. } => f.write_str("If"),
+            Self::For { pattern, .. } => {
+                f.write_str("For ")?;
+                NodeDisplay::fmt(pattern, f)
+            }
+            Self::Repeat { .. } => f.write_str("Repeat"),
+            Self::While { .. } => f.write_str("While"),
+            Self::With { binding, .. } => write!(f, "With {}", binding.as_str()),
+            Self::Match { .. } => f.write_str("Match"),
+            Self::Defer { .. } => f.write_str("Defer"),
+            Self::Return { .. } => f.write_str("Return"),
             _ => panic!(),
         }
     }
@@ -504,7 +965,23 @@ impl TreeDisplay for Expression {
             Self::Function { body: Some(_), .. } => 3,
             Self::Function { .. } => 2,
             Self::FunctionCall { .. } => 2,
+            Self::Tuple(values, _) | Self::Sequence(values) => values.len(),
             Self::Array { values, .. } => values.num_children(),
+            Self::RecordLiteral { fields, .. } => fields.num_children(),
+            Self::IfLet { .. }
+            | Self::For { .. }
+            | Self::Repeat { .. }
+            | Self::While { .. }
+            | Self::With { .. } => 2,
+            Self::Match { arms, .. } => arms.num_children() + 1,
+            Self::If {
+                branches,
+                else_body,
+                ..
+            } => branches.len() * 2 + else_body.is_some() as usize,
+            Self::Defer { .. } | Self::Return { expr: Some(_), .. } => 1,
+            Self::Index { index: Some(_), .. } => 2,
+            Self::Index { .. } => 1,
             Self::BinaryExpression {
                 left: Some(_),
                 right: Some(_),
@@ -545,7 +1022,50 @@ impl TreeDisplay for Expression {
                 1 => Some(args),
                 _ => None,
             },
+            Self::Tuple(values, _) | Self::Sequence(values) => {
+                values.get(index).map(|v| v as &dyn TreeDisplay)
+            }
             Self::Array { values, .. } => values.child_at(index),
+            Self::RecordLiteral { fields, .. } => fields.child_at(index),
+            Self::IfLet { expr, body, .. }
+            | Self::For { expr, body, .. }
+            | Self::Repeat {
+                count: expr, body, ..
+            }
+            | Self::While {
+                condition: expr,
+                body,
+                ..
+            }
+            | Self::With { expr, body, .. } => match index {
+                0 => Some(&**expr),
+                1 => Some(&**body),
+                _ => None,
+            },
+            Self::Match { expr, arms, .. } => match index {
+                0 => Some(&**expr),
+                index => arms.child_at(index - 1),
+            },
+            Self::If {
+                branches,
+                else_body,
+                ..
+            } => match branches.get(index / 2) {
+                Some((condition, _)) if index.is_multiple_of(2) => Some(condition),
+                Some((_, body)) => Some(body),
+                None => else_body.as_deref().map(|body| body as &dyn TreeDisplay),
+            },
+            Self::Defer { expr, .. }
+            | Self::Return {
+                expr: Some(expr), ..
+            } => Some(&**expr),
+            Self::Index {
+                expr, index: idx, ..
+            } => match index {
+                0 => Some(&**expr),
+                1 => idx.as_deref().map(|idx| idx as &dyn TreeDisplay),
+                _ => None,
+            },
             Self::BinaryExpression {
                 left: Some(l),
                 right: Some(r),
@@ -570,6 +1090,13 @@ pub enum Statement {
         colon: SpannedToken,
         expr: Option<Expression>,
     },
+    /// `const NAME = expr`, a binding folded at evaluation time that can't be reassigned
+    Const {
+        const_token: SpannedToken,
+        ident: SpannedToken,
+        equals: Option<SpannedToken>,
+        expr: Option<Expression>,
+    },
     UseStatement {
         token: Option<SpannedToken>,
         args: PunctuationList<SpannedToken>,
@@ -590,6 +1117,11 @@ impl AstNode for Statement {
                 _ => Range::from(*token.span()),
             },
             Self::List(list) => list.get_range(),
+            Self::Const {
+                const_token,
+                expr: Some(expr),
+                ..
+            } => Range::from((*const_token.span(), &expr.get_range())),
             _ => Range::default(),
         }
     }
@@ -605,6 +1137,7 @@ impl TreeDisplay for Statement {
     fn num_children(&self) -> usize {
         match self {
             Self::Decleration { .. } => 2,
+            Self::Const { expr, .. } => 1 + addup!(expr),
             Self::UseStatement { token, args } => addup!(token) + args.num_children(), // Self::Expression(_) => 1,
             Self::Expression(_) => 1,
             Self::List(list) => list.num_children(),
@@ -623,6 +1156,11 @@ impl TreeDisplay for Statement {
                 _ => None,
             },
             Self::Decleration { ident, .. } => Some(ident),
+            Self::Const { ident, expr, .. } => match index {
+                0 => Some(ident),
+                1 => expr.as_ref().map::<&dyn TreeDisplay, _>(|expr| expr),
+                _ => None,
+            },
             Self::UseStatement { token, args } => {
                 let ind = switchon!(index, token);
                 args.child_at(index - ind)
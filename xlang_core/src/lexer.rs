@@ -4,8 +4,11 @@ pub struct Lexer {}
 
 impl Lexer {
     pub fn lex(&self, input: &str) -> Vec<SpannedToken> {
+        // Indices are in bytes and always on character boundaries, while spans count characters
+        let char_end =
+            |index: usize| index + input[index..].chars().next().map_or(1, char::len_utf8);
         let mut start_index = 0;
-        let mut end_index = 1;
+        let mut end_index = char_end(0);
 
         let mut line_num = 0;
         let mut position = 0;
@@ -15,9 +18,27 @@ impl Lexer {
 
         while start_index < input.len() && end_index <= input.len() {
             let sub_str = &input[start_index..end_index];
-            let next = input.chars().nth(end_index);
+            let mut rest = input[end_index..].chars();
+            let next = rest.next();
+
+            // A number followed by a dot is an integer if it's a tuple index, so `t.0.1` isn't
+            // lexed as `t.(0.1)`, or if it starts a range like `1..3`
+            let integer_before_dot = !string
+                && next == Some('.')
+                && sub_str.chars().all(|c| c.is_ascii_digit())
+                && (rest.next() == Some('.')
+                    || matches!(
+                        tokens.last(),
+                        Some(SpannedToken(_, Token::Operator(Operator::Dot)))
+                    ));
+
+            let token = if integer_before_dot {
+                Some(Token::Integer(sub_str.parse().unwrap_or(0)))
+            } else {
+                self.try_lex(sub_str, next, string)
+            };
 
-            if let Some(token) = self.try_lex(sub_str, next, string) {
+            if let Some(token) = token {
                 match token {
                     Token::Operator(Operator::Quote) => string = true,
                     Token::String => {
@@ -37,8 +58,7 @@ impl Lexer {
                     }
                     Token::Whitespace => position += 1,
                     Token::Newline => {
-                        let _ce = end_index - 1;
-                        if &input[start_index..end_index + 1] == "\r\n" {
+                        if input[start_index..].starts_with("\r\n") {
                             end_index += 1;
                         }
 
@@ -51,13 +71,13 @@ impl Lexer {
                             Span {
                                 line_num,
                                 position,
-                                length: (end_index - start_index) as u32,
+                                length: sub_str.chars().count() as u32,
                                 token_index: tokens.len() as u32,
                             },
                         );
 
                         tokens.push(token);
-                        position += (end_index - start_index) as u32;
+                        position += sub_str.chars().count() as u32;
                     }
                     token => {
                         let token = SpannedToken::new(
@@ -65,20 +85,20 @@ impl Lexer {
                             Span {
                                 line_num,
                                 position,
-                                length: (end_index - start_index) as u32,
+                                length: sub_str.chars().count() as u32,
                                 token_index: tokens.len() as u32,
                             },
                         );
 
                         tokens.push(token);
-                        position += (end_index - start_index) as u32;
+                        position += sub_str.chars().count() as u32;
                     }
                 }
 
                 start_index = end_index;
-                end_index = start_index + 1;
+                end_index = char_end(start_index);
             } else {
-                end_index += 1;
+                end_index = char_end(end_index);
             }
         }
 
@@ -116,12 +136,23 @@ impl Lexer {
                 Some('"') => return Some(Token::Operator(Operator::Quote)),
 
                 Some(':') => return Some(Token::Operator(Operator::Colon)),
-                Some('.') => return Some(Token::Operator(Operator::Dot)),
+                Some('.') => match next {
+                    Some('.') => return None,
+                    _ => return Some(Token::Operator(Operator::Dot)),
+                },
                 Some(',') => return Some(Token::Operator(Operator::Comma)),
+                Some(';') => return Some(Token::Operator(Operator::Semicolon)),
+                Some('?') => match next {
+                    Some('.') => return None,
+                    _ => return Some(Token::Operator(Operator::Question)),
+                },
 
-                Some('+') => return Some(Token::Operator(Operator::Plus)),
+                Some('+') => match next {
+                    Some('+') => return None,
+                    _ => return Some(Token::Operator(Operator::Plus)),
+                },
                 Some('-') => match next {
-                    Some('>') => return None,
+                    Some('>' | '-') => return None,
                     _ => return Some(Token::Operator(Operator::Minus)),
                 },
                 Some('*') => match next {
@@ -129,7 +160,30 @@ impl Lexer {
                     _ => return Some(Token::Operator(Operator::Multiply)),
                 },
                 Some('/') => return Some(Token::Operator(Operator::Divide)),
-                Some('=') => return Some(Token::Operator(Operator::Equals)),
+                Some('%') => return Some(Token::Operator(Operator::Modulo)),
+                Some('&') => match next {
+                    Some('&') => return None,
+                    _ => return Some(Token::Operator(Operator::BitAnd)),
+                },
+                Some('|') => match next {
+                    Some('|' | '>') => return None,
+                    _ => return Some(Token::Operator(Operator::BitOr)),
+                },
+                Some('^') => return Some(Token::Operator(Operator::BitXor)),
+                Some('=') => match next {
+                    Some('=' | '>') => return None,
+                    _ => return Some(Token::Operator(Operator::Equals)),
+                },
+                Some('<') => match next {
+                    Some('=') => return None,
+                    _ => return Some(Token::Operator(Operator::LessThan)),
+                },
+                Some('>') => match next {
+                    Some('=') => return None,
+                    _ => return Some(Token::Operator(Operator::GreaterThan)),
+                },
+                Some('!') if next == Some('=') => return None,
+                Some('!') => return Some(Token::Operator(Operator::LogicalNot)),
 
                 Some('\r' | '\n') => return Some(Token::Newline),
                 Some(c) if c.is_whitespace() => return Some(Token::Whitespace),
@@ -137,9 +191,26 @@ impl Lexer {
             }
         }
 
+        if input == "..=" {
+            return Some(Token::Operator(Operator::RangeInclusive));
+        }
+
         match (input.chars().next(), input.chars().nth(1)) {
+            (Some('.'), Some('.')) if next == Some('=') => return None,
+            (Some('.'), Some('.')) => return Some(Token::Operator(Operator::Range)),
             (Some('*'), Some('*')) => return Some(Token::Operator(Operator::Exponent)),
+            (Some('+'), Some('+')) => return Some(Token::Operator(Operator::PlusPlus)),
+            (Some('-'), Some('-')) => return Some(Token::Operator(Operator::MinusMinus)),
             (Some('-'), Some('>')) => return Some(Token::Operator(Operator::Arrow)),
+            (Some('='), Some('>')) => return Some(Token::Operator(Operator::FatArrow)),
+            (Some('<'), Some('=')) => return Some(Token::Operator(Operator::LessThanEqual)),
+            (Some('>'), Some('=')) => return Some(Token::Operator(Operator::GreaterThanEqual)),
+            (Some('='), Some('=')) => return Some(Token::Operator(Operator::EqualsEquals)),
+            (Some('!'), Some('=')) => return Some(Token::Operator(Operator::NotEquals)),
+            (Some('|'), Some('>')) => return Some(Token::Operator(Operator::Pipe)),
+            (Some('?'), Some('.')) => return Some(Token::Operator(Operator::QuestionDot)),
+            (Some('&'), Some('&')) => return Some(Token::Operator(Operator::LogicalAnd)),
+            (Some('|'), Some('|')) => return Some(Token::Operator(Operator::LogicalOr)),
             _ => (),
         }
 
@@ -193,16 +264,14 @@ impl Lexer {
         let mut toks = Vec::new();
         let sindex = position + 1;
 
-        for i in 0..input.len() {
-            let c = input.chars().nth(i).unwrap();
-
+        for (i, c) in input.char_indices() {
             if c == '}' {
                 open -= 1;
             }
 
             if c == '{' && open == 0 {
                 let st = input[last_index..i].to_string();
-                let len = st.len();
+                let len = st.chars().count();
                 last_span = Span {
                     line_num: line,
                     position: position + len as u32,
@@ -226,7 +295,22 @@ impl Lexer {
                 iindex = i;
                 open += 1
             } else if c == '}' && open == 0 {
-                let st = &input[iindex + 1..i];
+                let inner = &input[iindex + 1..i];
+                let (st, spec) = match split_format_spec(inner) {
+                    Some(colon) => {
+                        let spec = SpannedToken::new(
+                            Token::Ident(inner[colon + 1..].to_string()),
+                            Span {
+                                line_num: line,
+                                position: position + inner[..colon].chars().count() as u32 + 2,
+                                length: inner[colon + 1..].chars().count() as u32,
+                                token_index,
+                            },
+                        );
+                        (&inner[..colon], Some(spec))
+                    }
+                    None => (inner, None),
+                };
                 let utoks = self.lex(st);
                 let tok_len = utoks.len();
                 let p = utoks
@@ -246,7 +330,7 @@ impl Lexer {
                     .collect::<Vec<_>>();
 
                 last_index = i + 1;
-                position += st.len() as u32 + 2;
+                position += inner.chars().count() as u32 + 2;
 
                 toks.push(Template::Template(
                     p,
@@ -260,13 +344,14 @@ impl Lexer {
                             token_index,
                         },
                     ),
+                    spec,
                 ));
             }
         }
 
         {
             let st = input[last_index..input.len()].to_string();
-            let len = st.len();
+            let len = st.chars().count();
 
             let token = SpannedToken::new(
                 Token::Ident(st),
@@ -283,7 +368,7 @@ impl Lexer {
 
         (
             Span {
-                length: input.len() as u32 + 2,
+                length: input.chars().count() as u32 + 2,
                 line_num: line,
                 position: sindex - 1,
                 token_index,
@@ -296,5 +381,28 @@ impl Lexer {
 #[derive(Clone, Debug)]
 pub enum Template {
     String(SpannedToken),
-    Template(Vec<SpannedToken>, SpannedToken, SpannedToken),
+    /// Tokens of the interpolated expression, the braces, and the format specifier following a
+    /// colon
+    Template(
+        Vec<SpannedToken>,
+        SpannedToken,
+        SpannedToken,
+        Option<SpannedToken>,
+    ),
+}
+
+/// Index of the colon separating an interpolated expression from its format specifier, like
+/// `x:04`. Colons inside brackets belong to the expression.
+fn split_format_spec(input: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut colon = None;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ':' if depth == 0 => colon = Some(i),
+            _ => (),
+        }
+    }
+    colon
 }
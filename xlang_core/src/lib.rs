@@ -432,6 +432,9 @@ impl<U: Clone> ModuleDescender<U> {
         match node {
             Statement::Decleration {
                 expr: Some(expr), ..
+            }
+            | Statement::Const {
+                expr: Some(expr), ..
             } => self.descend_expression(expr),
             Statement::Expression(e) => self.descend_expression(e),
             _ => (),
@@ -510,6 +513,9 @@ impl<U: Clone> MutModuleDescender<U> {
             match node {
                 Statement::Decleration {
                     expr: Some(expr), ..
+                }
+                | Statement::Const {
+                    expr: Some(expr), ..
                 } => self.descend_expression(expr),
                 Statement::Expression(e) => self.descend_expression(e),
                 _ => (),
@@ -521,6 +527,9 @@ impl<U: Clone> MutModuleDescender<U> {
             match node {
                 Statement::Decleration {
                     expr: Some(expr), ..
+                }
+                | Statement::Const {
+                    expr: Some(expr), ..
                 } => self.descend_expression(expr),
                 Statement::Expression(e) => self.descend_expression(e),
                 _ => (),
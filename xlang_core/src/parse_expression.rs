@@ -1,5 +1,8 @@
 use crate::{
-    ast::{Expression, ParsedTemplate, ParsedTemplateString, PunctuationList, Statement, Type},
+    ast::{
+        AstNode, Expression, FormatSpec, MatchArm, NodeId, ParsedTemplate, ParsedTemplateString,
+        Pattern, PunctuationList, Statement, Type,
+    },
     error::{ParseError, ParseErrorKind},
     lexer::Template,
     parser::Parser,
@@ -12,6 +15,36 @@ impl Parser {
 
         while let Some(t) = self.tokens.peek() {
             left = match t {
+                // Prefix `-x` and `+x` bind tighter than any binary operator but `**`, wherever
+                // they appear, so `2 * -3` is `2 * (-3)` and `-2 ** 2` is `-(2 ** 2)`
+                Token::Operator(Operator::Minus | Operator::Plus) if left.is_none() => {
+                    let op_token = self.tokens.next().cloned();
+                    let right =
+                        self.parse_expression(self.precedence_of_operator(&Operator::Multiply));
+
+                    Some(Expression::BinaryExpression {
+                        left: None,
+                        right: right.map(Box::new),
+                        op_token,
+                    })
+                }
+                // `Name { field: value }` instantiates the record `Name`
+                Token::Operator(Operator::OpenBrace)
+                    if matches!(left, Some(Expression::Ident(_)))
+                        && !*self.in_condition.read().unwrap() =>
+                {
+                    let Some(Expression::Ident(name)) = left else {
+                        unreachable!()
+                    };
+                    self.parse_record_literal(Some(name))
+                }
+                // The member on the right of `a.b[i]` isn't indexed, `a.b` is
+                Token::Operator(Operator::OpenSquare)
+                    if left.is_some()
+                        && last_prec == self.precedence_of_operator(&Operator::Dot) =>
+                {
+                    break
+                }
                 Token::Operator(o) => {
                     let prec = self.precedence_of_operator(o);
                     if prec <= last_prec || prec == 0 {
@@ -26,12 +59,32 @@ impl Parser {
                                 continue;
                             }
                         }
+                        (Operator::OpenSquare, Some(expr)) => {
+                            left = Some(self.parse_index(expr));
+                            continue;
+                        }
+                        // Postfix `x++` has no right side
+                        (Operator::PlusPlus | Operator::MinusMinus, Some(expr)) => {
+                            left = Some(Expression::BinaryExpression {
+                                left: Some(Box::new(expr)),
+                                right: None,
+                                op_token: self.tokens.next().cloned(),
+                            });
+                            continue;
+                        }
                         (_, l) => left = l,
                     };
 
                     let op_token = self.tokens.next().cloned();
 
-                    let right = self.parse_expression(prec);
+                    // Exponentiation is right associative, so `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+                    // Prefix `++x` takes the whole place after it, so `++a.b` is `++(a.b)`.
+                    let right =
+                        if let Operator::Exponent | Operator::PlusPlus | Operator::MinusMinus = o {
+                            self.parse_expression(prec - 1)
+                        } else {
+                            self.parse_expression(prec)
+                        };
 
                     Some(Expression::BinaryExpression {
                         left: left.map(Box::new),
@@ -47,15 +100,122 @@ impl Parser {
     }
 
     pub fn parse_primary_expression(&self) -> Option<Expression> {
-        if let Some(Token::Operator(Operator::OpenParen)) = self.tokens.peek() {
+        if let Some(Token::Operator(Operator::OpenSquare)) = self.tokens.peek() {
+            self.parse_array()
+        } else if let Some(Token::Operator(Operator::OpenBrace)) = self.tokens.peek() {
+            self.parse_record_literal(None)
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "pure") {
+            self.parse_pure_function()
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "if") {
+            self.parse_if()
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "for") {
+            self.parse_for()
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "repeat") {
+            let repeat_token = self.tokens.next().unwrap().clone();
+            let count = self.parse_condition()?;
+            let (body, range) = self.parse_block()?;
+
+            Some(Expression::Repeat {
+                repeat_token,
+                count: Box::new(count),
+                body: Box::new(Statement::List(body)),
+                range,
+            })
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "while") {
+            let while_token = self.tokens.next().unwrap().clone();
+            let condition = self.parse_condition()?;
+            let (body, range) = self.parse_block()?;
+
+            Some(Expression::While {
+                while_token,
+                condition: Box::new(condition),
+                body: Box::new(Statement::List(body)),
+                range,
+            })
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "match") {
+            self.parse_match()
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "with") {
+            self.parse_with()
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "defer") {
+            let defer_token = self.tokens.next().unwrap().clone();
+            let expr = self.parse_expression(0)?;
+
+            Some(Expression::Defer {
+                defer_token,
+                expr: Box::new(expr),
+            })
+        } else if matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "return") {
+            let return_token = self.tokens.next().unwrap().clone();
+            let expr = match self.tokens.peek() {
+                None
+                | Some(Token::Operator(
+                    Operator::Comma
+                    | Operator::Semicolon
+                    | Operator::CloseParen
+                    | Operator::CloseBrace,
+                )) => None,
+                _ => self.parse_expression(0).map(Box::new),
+            };
+
+            Some(Expression::Return { return_token, expr })
+        } else if let Some(Token::Operator(Operator::OpenParen)) = self.tokens.peek() {
             let state = self.save_state();
+            let error_count = self.get_errors().len();
             if let Some(func) = self.parse_function() {
                 return Some(func);
             }
             state.restore(&self.tokens);
+            // Errors from the failed function attempt don't apply to the expression
+            self.get_errors_mut().truncate(error_count);
 
             let _open = self.tokens.next().unwrap();
-            let expr = self.parse_expression(0);
+            let label = self.parse_tuple_label();
+            let expr = self.parse_nested_expression();
+
+            let sep = match self.tokens.peek() {
+                Some(Token::Operator(sep @ (Operator::Comma | Operator::Semicolon))) => {
+                    Some(sep.clone())
+                }
+                // A single labeled element is still a tuple
+                _ if label.is_some() => Some(Operator::Comma),
+                _ => None,
+            };
+            if let Some(sep) = sep {
+                let (mut values, mut labels) = (Vec::new(), Vec::new());
+                if let Some(expr) = expr {
+                    values.push(expr);
+                    labels.push(label);
+                }
+                while let Some(Token::Operator(op)) = self.tokens.peek() {
+                    if *op != sep {
+                        break;
+                    }
+                    self.tokens.next();
+                    let label = self.parse_tuple_label();
+                    match self.parse_nested_expression() {
+                        Some(expr) => {
+                            values.push(expr);
+                            labels.push(label);
+                        }
+                        None => break,
+                    }
+                }
+                let _close = self.tokens.next().unwrap(); // TODO: error
+
+                if sep == Operator::Semicolon {
+                    if let Some(label) = labels.into_iter().flatten().next() {
+                        self.add_error(ParseError {
+                            kind: ParseErrorKind::InvalidSyntax(
+                                "Only tuple elements can be labeled!".to_string(),
+                            ),
+                            range: label.get_range(),
+                        });
+                    }
+                    return Some(Expression::Sequence(values));
+                }
+                return Some(Expression::Tuple(values, labels));
+            }
+
             let _close = self.tokens.next().unwrap(); // TODO: error
 
             expr
@@ -64,6 +224,23 @@ impl Parser {
         }
     }
 
+    /// Parses the `label:` before a tuple element, if there is one
+    fn parse_tuple_label(&self) -> Option<SpannedToken> {
+        let state = self.save_state();
+        let label = match self.tokens.next() {
+            Some(tok @ SpannedToken(_, Token::Ident(_))) => tok.clone(),
+            _ => {
+                state.restore(&self.tokens);
+                return None;
+            }
+        };
+        if self.expect_operator(Operator::Colon).is_none() {
+            state.restore(&self.tokens);
+            return None;
+        }
+        Some(label)
+    }
+
     pub fn parse_function_call(&self, expression: Expression) -> (Expression, bool) {
         let Some(args) = self.parse_arguments() else {
             return (expression, false);
@@ -78,6 +255,498 @@ impl Parser {
         )
     }
 
+    pub fn parse_index(&self, expression: Expression) -> Expression {
+        let open = self.tokens.next().unwrap().clone();
+        let index = self.parse_expression(0);
+        let close = self.expect_operator(Operator::CloseSquare).cloned();
+
+        let end = match &close {
+            Some(close) => close.0,
+            None => {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax("Expected closing bracket!".to_string()),
+                    range: Range::default(),
+                });
+                open.0
+            }
+        };
+
+        Expression::Index {
+            expr: Box::new(expression),
+            index: index.map(Box::new),
+            range: Range { start: open.0, end },
+        }
+    }
+
+    pub fn parse_array(&self) -> Option<Expression> {
+        let open = self.tokens.next().unwrap().clone();
+
+        let mut values = PunctuationList::default();
+        while let Some(value) = self.parse_expression(0) {
+            let comma = self.expect_operator(Operator::Comma).cloned();
+            let last = comma.is_none();
+            values.push(value, comma);
+            if last {
+                break;
+            }
+        }
+
+        let close = self.expect_operator(Operator::CloseSquare).cloned();
+        let end = match &close {
+            Some(close) => close.0,
+            None => {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax("Expected closing bracket!".to_string()),
+                    range: Range::default(),
+                });
+                open.0
+            }
+        };
+
+        Some(Expression::Array {
+            values,
+            range: Range { start: open.0, end },
+        })
+    }
+
+    /// Parses the expression before a block, as in `if cond { .. }`. Records can't be
+    /// instantiated with `Name { .. }` in it unless it's in parentheses, since the brace would
+    /// start the block.
+    pub fn parse_condition(&self) -> Option<Expression> {
+        let outer = std::mem::replace(&mut *self.in_condition.write().unwrap(), true);
+        let expr = self.parse_expression(0);
+        *self.in_condition.write().unwrap() = outer;
+        expr
+    }
+
+    /// Parses an expression in parentheses, where records can be instantiated even inside a
+    /// condition
+    pub fn parse_nested_expression(&self) -> Option<Expression> {
+        let outer = std::mem::replace(&mut *self.in_condition.write().unwrap(), false);
+        let expr = self.parse_expression(0);
+        *self.in_condition.write().unwrap() = outer;
+        expr
+    }
+
+    pub fn parse_record_literal(&self, name: Option<SpannedToken>) -> Option<Expression> {
+        let open = self.tokens.next().unwrap().clone();
+
+        let mut fields = PunctuationList::default();
+        while let Some(field) = self.parse_decleration() {
+            let comma = self.expect_operator(Operator::Comma).cloned();
+            let last = comma.is_none();
+            fields.push(field, comma);
+            if last {
+                break;
+            }
+        }
+
+        let close = self.expect_operator(Operator::CloseBrace).cloned();
+        let end = match &close {
+            Some(close) => close.0,
+            None => {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax("Expected closing brace!".to_string()),
+                    range: Range::default(),
+                });
+                open.0
+            }
+        };
+
+        Some(Expression::RecordLiteral {
+            name,
+            fields,
+            range: Range { start: open.0, end },
+        })
+    }
+
+    pub fn parse_if(&self) -> Option<Expression> {
+        let if_token = self.tokens.next().unwrap().clone();
+
+        if !matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "let") {
+            return self.parse_if_chain(if_token);
+        }
+        self.tokens.next();
+
+        let pattern = self.parse_pattern()?;
+
+        if self.expect_operator(Operator::Equals).is_none() {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax("Expected `=` after pattern!".to_string()),
+                range: pattern.get_range(),
+            });
+            return None;
+        }
+
+        let expr = self.parse_condition()?;
+        let (body, range) = self.parse_block()?;
+
+        Some(Expression::IfLet {
+            if_token,
+            pattern,
+            expr: Box::new(expr),
+            body: Box::new(Statement::List(body)),
+            range,
+        })
+    }
+
+    /// Parses the rest of `if cond { body }` followed by any number of `elif cond { body }` and an
+    /// optional `else { body }`
+    fn parse_if_chain(&self, if_token: SpannedToken) -> Option<Expression> {
+        let mut branches = Vec::new();
+        let mut else_body = None;
+        let mut range;
+
+        loop {
+            let condition = self.parse_condition()?;
+            let (body, body_range) = self.parse_block()?;
+            branches.push((condition, Statement::List(body)));
+            range = body_range;
+
+            match self.tokens.peek() {
+                Some(Token::Ident(s)) if s == "elif" => {
+                    self.tokens.next();
+                }
+                Some(Token::Ident(s)) if s == "else" => {
+                    self.tokens.next();
+                    let (body, body_range) = self.parse_block()?;
+                    else_body = Some(Box::new(Statement::List(body)));
+                    range = body_range;
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        Some(Expression::If {
+            if_token,
+            branches,
+            else_body,
+            range,
+        })
+    }
+
+    pub fn parse_for(&self) -> Option<Expression> {
+        let for_token = self.tokens.next().unwrap().clone();
+        let pattern = self.parse_pattern()?;
+
+        if !matches!(self.tokens.next(), Some(SpannedToken(_, Token::Ident(s))) if s == "in") {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax("Expected `in` after pattern!".to_string()),
+                range: pattern.get_range(),
+            });
+            return None;
+        }
+
+        let expr = self.parse_condition()?;
+        let (body, range) = self.parse_block()?;
+
+        Some(Expression::For {
+            for_token,
+            pattern,
+            expr: Box::new(expr),
+            body: Box::new(Statement::List(body)),
+            range,
+        })
+    }
+
+    pub fn parse_with(&self) -> Option<Expression> {
+        let with_token = self.tokens.next().unwrap().clone();
+        let expr = self.parse_condition()?;
+
+        if !matches!(self.tokens.next(), Some(SpannedToken(_, Token::Ident(s))) if s == "as") {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax("Expected `as` after resource!".to_string()),
+                range: expr.get_range(),
+            });
+            return None;
+        }
+
+        let binding = match self.tokens.next() {
+            Some(tok @ SpannedToken(_, Token::Ident(_))) => tok.clone(),
+            tok => {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax(
+                        "Expected identifier after `as`!".to_string(),
+                    ),
+                    range: tok.map(|tok| tok.span().into()).unwrap_or_default(),
+                });
+                return None;
+            }
+        };
+
+        let (body, range) = self.parse_block()?;
+
+        Some(Expression::With {
+            with_token,
+            expr: Box::new(expr),
+            binding,
+            body: Box::new(Statement::List(body)),
+            range,
+        })
+    }
+
+    pub fn parse_match(&self) -> Option<Expression> {
+        let match_token = self.tokens.next().unwrap().clone();
+        let expr = self.parse_condition()?;
+
+        let Some(open) = self.expect_operator(Operator::OpenBrace).cloned() else {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax("Expected opening brace!".to_string()),
+                range: expr.get_range(),
+            });
+            return None;
+        };
+
+        let mut arms = PunctuationList::default();
+        while !matches!(
+            self.tokens.peek(),
+            Some(Token::Operator(Operator::CloseBrace)) | None
+        ) {
+            let pattern = self.parse_pattern()?;
+
+            let Some(arrow) = self.expect_operator(Operator::FatArrow).cloned() else {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax("Expected `=>` after pattern!".to_string()),
+                    range: pattern.get_range(),
+                });
+                return None;
+            };
+
+            let body = self.parse_statement()?;
+            let comma = self.expect_operator(Operator::Comma).cloned();
+            let last = comma.is_none();
+            arms.push(
+                MatchArm {
+                    pattern,
+                    arrow,
+                    body,
+                },
+                comma,
+            );
+            if last {
+                break;
+            }
+        }
+
+        let close = self.expect_operator(Operator::CloseBrace).cloned();
+        let end = match &close {
+            Some(close) => close.0,
+            None => {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax("Expected closing brace!".to_string()),
+                    range: Range::default(),
+                });
+                open.0
+            }
+        };
+
+        Some(Expression::Match {
+            match_token,
+            expr: Box::new(expr),
+            arms,
+            range: Range { start: open.0, end },
+        })
+    }
+
+    pub fn parse_pattern(&self) -> Option<Pattern> {
+        if let Some(Token::Operator(Operator::OpenParen)) = self.tokens.peek() {
+            return self.parse_tuple_pattern();
+        }
+        if let Some(Token::Operator(Operator::OpenBrace)) = self.tokens.peek() {
+            return self.parse_record_pattern();
+        }
+        if self.peek_literal() {
+            return self.parse_literals_pattern();
+        }
+
+        let some = match self.tokens.next() {
+            Some(tok @ SpannedToken(_, Token::Ident(s))) if s == "Some" => tok.clone(),
+            Some(tok @ SpannedToken(_, Token::Ident(_))) => {
+                let binding = tok.clone();
+                if self.expect_operator(Operator::Colon).is_some() {
+                    let ty = self.parse_type()?;
+                    return Some(Pattern::Typed { binding, ty });
+                }
+                return Some(Pattern::Ident(binding));
+            }
+            tok => {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax("Expected pattern!".to_string()),
+                    range: tok.map(|tok| tok.span().into()).unwrap_or_default(),
+                });
+                return None;
+            }
+        };
+
+        let binding =
+            self.expect_operator(Operator::OpenParen)
+                .and_then(|_| match self.tokens.next() {
+                    Some(tok @ SpannedToken(_, Token::Ident(_))) => Some(tok.clone()),
+                    _ => None,
+                });
+        let (Some(binding), Some(_)) = (binding, self.expect_operator(Operator::CloseParen)) else {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax(
+                    "Expected a binding like `Some(x)`!".to_string(),
+                ),
+                range: some.span().into(),
+            });
+            return None;
+        };
+
+        Some(Pattern::Some { some, binding })
+    }
+
+    /// Whether the next token starts a literal that can be used as a pattern
+    fn peek_literal(&self) -> bool {
+        match self.tokens.peek() {
+            Some(Token::Integer(_) | Token::Float(_) | Token::TemplateString(_)) => true,
+            Some(Token::Ident(s)) => s == "true" || s == "false",
+            _ => false,
+        }
+    }
+
+    /// Parses literals separated by `|`, like `1 | 2 | 3`
+    fn parse_literals_pattern(&self) -> Option<Pattern> {
+        let mut literals = vec![self.parse_primary_expression()?];
+        while self.expect_operator(Operator::BitOr).is_some() {
+            if !self.peek_literal() {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax(
+                        "Expected a literal after `|`!".to_string(),
+                    ),
+                    range: literals.last().map(|l| l.get_range()).unwrap_or_default(),
+                });
+                return None;
+            }
+            literals.push(self.parse_primary_expression()?);
+        }
+
+        Some(Pattern::Literals(literals))
+    }
+
+    /// Parses `(a, b, ...)`
+    fn parse_tuple_pattern(&self) -> Option<Pattern> {
+        let (bindings, range) = self.parse_pattern_bindings(Operator::CloseParen, "(a, b)")?;
+        Some(Pattern::Tuple { bindings, range })
+    }
+
+    /// Parses `{a, b, ...}`
+    pub fn parse_record_pattern(&self) -> Option<Pattern> {
+        let (bindings, range) = self.parse_pattern_bindings(Operator::CloseBrace, "{a, b}")?;
+        Some(Pattern::Record { bindings, range })
+    }
+
+    /// Parses identifiers separated by commas between the next token and `close`
+    fn parse_pattern_bindings(
+        &self,
+        close: Operator,
+        example: &str,
+    ) -> Option<(Vec<SpannedToken>, Range)> {
+        let open = self.tokens.next().unwrap().clone();
+
+        let mut bindings = Vec::new();
+        while let Some(Token::Ident(_)) = self.tokens.peek() {
+            bindings.push(self.tokens.next().unwrap().clone());
+            if self.expect_operator(Operator::Comma).is_none() {
+                break;
+            }
+        }
+
+        let Some(close) = self.expect_operator(close).cloned() else {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax(format!("Expected bindings like `{example}`!")),
+                range: open.span().into(),
+            });
+            return None;
+        };
+
+        Some((bindings, Range::from((*open.span(), *close.span()))))
+    }
+
+    fn parse_format_spec(&self, token: &SpannedToken) -> Option<FormatSpec> {
+        let spec = FormatSpec::parse(token.clone());
+        if spec.is_none() {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax(format!(
+                    "Invalid format specifier `{}`!",
+                    token.as_str()
+                )),
+                range: token.span().into(),
+            });
+        }
+        spec
+    }
+
+    /// Parses statements separated by commas inside braces
+    pub fn parse_block(&self) -> Option<(PunctuationList<Statement>, Range)> {
+        let Some(open) = self.expect_operator(Operator::OpenBrace).cloned() else {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax("Expected opening brace!".to_string()),
+                range: Range::default(),
+            });
+            return None;
+        };
+
+        let mut stmts = PunctuationList::default();
+        while let Some(stmt) = self.parse_statement() {
+            let comma = self.expect_operator(Operator::Comma).cloned();
+            let last = comma.is_none();
+            stmts.push(stmt, comma);
+            if last {
+                break;
+            }
+        }
+
+        let close = self.expect_operator(Operator::CloseBrace).cloned();
+        let end = match &close {
+            Some(close) => close.0,
+            None => {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax("Expected closing brace!".to_string()),
+                    range: Range::default(),
+                });
+                open.0
+            }
+        };
+
+        Some((stmts, Range { start: open.0, end }))
+    }
+
+    pub fn parse_pure_function(&self) -> Option<Expression> {
+        let pure_token = self.tokens.next().cloned();
+
+        match self.parse_function() {
+            Some(Expression::Function {
+                parameters,
+                arrow,
+                return_parameters,
+                requires,
+                comma,
+                body,
+                ..
+            }) => Some(Expression::Function {
+                pure: pure_token,
+                parameters,
+                arrow,
+                return_parameters,
+                requires,
+                comma,
+                body,
+            }),
+            expr => {
+                self.add_error(ParseError {
+                    kind: ParseErrorKind::InvalidSyntax(
+                        "Expected function after `pure`!".to_string(),
+                    ),
+                    range: Range::default(),
+                });
+                expr
+            }
+        }
+    }
+
     pub fn parse_function(&self) -> Option<Expression> {
         let parameters = self.parse_parameters();
 
@@ -87,19 +756,25 @@ impl Parser {
 
             match (parameters, return_parameters) {
                 (Some(parameters), Some(return_parameters)) => {
+                    let requires = self.parse_requires();
+
                     if let Some((comma, body)) = self.parse_function_body() {
                         return Some(Expression::Function {
+                            pure: None,
                             parameters,
                             arrow,
                             return_parameters,
+                            requires,
                             comma,
                             body: Some(Box::new(Statement::List(body))),
                         });
                     } else {
                         return Some(Expression::Function {
+                            pure: None,
                             parameters,
                             arrow,
                             return_parameters,
+                            requires,
                             comma: None,
                             body: None,
                         });
@@ -114,6 +789,26 @@ impl Parser {
         parameters.map(|parameters| Expression::Record { parameters })
     }
 
+    /// Parses `requires cond` after the return parameters of a function
+    fn parse_requires(&self) -> Option<(SpannedToken, Box<Expression>)> {
+        if !matches!(self.tokens.peek(), Some(Token::Ident(s)) if s == "requires") {
+            return None;
+        }
+
+        let requires = self.tokens.next().unwrap().clone();
+        let Some(cond) = self.parse_condition() else {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax(
+                    "Expected condition after `requires`!".to_string(),
+                ),
+                range: requires.span().into(),
+            });
+            return None;
+        };
+
+        Some((requires, Box::new(cond)))
+    }
+
     pub fn parse_function_body(
         &self,
     ) -> Option<(Option<SpannedToken>, PunctuationList<Statement>)> {
@@ -127,7 +822,10 @@ impl Parser {
             } else {
                 None
             };
-            if let Some(Token::Newline) = self.tokens.peek() {
+            // Without a comma after it, the last statement of the body ends with its line
+            if matches!(self.tokens.peek(), Some(Token::Newline))
+                || (comma.is_none() && self.tokens.at_new_line())
+            {
                 stmts.push(stmt, comma);
                 break;
             }
@@ -149,16 +847,28 @@ impl Parser {
 
     pub fn parse_literal(&self) -> Option<Expression> {
         match self.tokens.peek() {
-            Some(Token::Integer(i)) => Some(Expression::Integer(
-                *i,
-                None,
-                self.tokens.next().unwrap().clone(),
-            )),
-            Some(Token::Float(f)) => Some(Expression::Float(
-                *f,
-                None,
-                self.tokens.next().unwrap().clone(),
-            )),
+            Some(Token::Integer(i)) => {
+                let tok = self.tokens.next().unwrap().clone();
+                Some(Expression::Integer(
+                    *i,
+                    None,
+                    self.parse_type_suffix(&tok),
+                    tok,
+                ))
+            }
+            Some(Token::Float(f)) => {
+                let tok = self.tokens.next().unwrap().clone();
+                let suffix = self.parse_type_suffix(&tok);
+                if let Some(ty @ Type::Integer { .. }) = &suffix {
+                    self.add_error(ParseError {
+                        kind: ParseErrorKind::InvalidSyntax(format!(
+                            "Float literal can't have suffix `{ty:?}`!"
+                        )),
+                        range: ty.get_range(),
+                    });
+                }
+                Some(Expression::Float(*f, None, suffix, tok))
+            }
             Some(Token::Ident(_)) => Some(Expression::Ident(self.tokens.next().unwrap().clone())),
             Some(Token::TemplateString(ts)) => {
                 let tok = self.tokens.next().unwrap();
@@ -166,7 +876,7 @@ impl Parser {
                     .iter()
                     .filter_map(|t| match t {
                         Template::String(s) => Some(ParsedTemplate::String(s.clone())),
-                        Template::Template(t, o, c) => Some(ParsedTemplate::Template(
+                        Template::Template(t, o, c, spec) => Some(ParsedTemplate::Template(
                             Box::new({
                                 let parser = Parser::new(t.clone());
                                 let expr = parser.parse_expression(0)?;
@@ -178,6 +888,7 @@ impl Parser {
                             }),
                             o.clone(),
                             c.clone(),
+                            spec.as_ref().and_then(|spec| self.parse_format_spec(spec)),
                         )),
                     })
                     .collect();
@@ -187,8 +898,52 @@ impl Parser {
         }
     }
 
+    /// Parses a numeric type written directly after a number literal, like the `u8` in `255u8`
+    fn parse_type_suffix(&self, literal: &SpannedToken) -> Option<Type> {
+        let SpannedToken(span, token) = self.tokens.next()?.clone();
+        self.tokens.back();
+
+        let Token::Ident(id) = token else {
+            return None;
+        };
+        let adjacent = span.line_num == literal.0.line_num
+            && span.position == literal.0.position + literal.0.length;
+        let numeric = matches!(
+            id.as_str(),
+            "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "f32" | "f64"
+        );
+        if !adjacent || !numeric {
+            return None;
+        }
+
+        self.parse_base_type()
+    }
+
     pub fn parse_type(&self) -> Option<Type> {
+        let ty = self.parse_base_type()?;
+
+        // `T?` is an optional `T`
+        if let Some(Token::Operator(Operator::Question)) = self.tokens.peek() {
+            return Some(Type::Optional {
+                ty: Box::new(ty),
+                question: self.tokens.next().unwrap().clone(),
+            });
+        }
+
+        Some(ty)
+    }
+
+    fn parse_base_type(&self) -> Option<Type> {
         match self.tokens.peek() {
+            Some(Token::Operator(Operator::OpenSquare)) => self.parse_array_type(),
+            Some(Token::Operator(Operator::BitAnd)) => {
+                let amp = self.tokens.next().unwrap().clone();
+                let ty = self.parse_type()?;
+                Some(Type::Ref {
+                    amp,
+                    ty: Box::new(ty),
+                })
+            }
             Some(Token::Ident(id)) => match id.as_str() {
                 "i8" => Some(Type::Integer {
                     width: 8,
@@ -230,6 +985,8 @@ impl Parser {
                     signed: false,
                     token: self.tokens.next().unwrap().clone(),
                 }),
+                "bool" => Some(Type::Boolean(self.tokens.next().unwrap().clone())),
+                "string" => Some(Type::String(self.tokens.next().unwrap().clone())),
                 "f32" => Some(Type::Float {
                     width: 32,
                     token: self.tokens.next().unwrap().clone(),
@@ -238,22 +995,83 @@ impl Parser {
                     width: 64,
                     token: self.tokens.next().unwrap().clone(),
                 }),
-                _ => Some(Type::Ident(self.tokens.next().unwrap().clone())),
+                _ => Some(Type::Ident(
+                    self.tokens.next().unwrap().clone(),
+                    NodeId::next(),
+                )),
             },
             _ => None,
         }
     }
 
+    /// Parses `[T]` or `[T; n]`
+    fn parse_array_type(&self) -> Option<Type> {
+        let open = self.tokens.next().unwrap().clone();
+        let ty = self.parse_type()?;
+
+        let len = if self.expect_operator(Operator::Semicolon).is_some() {
+            match self.tokens.next() {
+                Some(tok @ SpannedToken(_, Token::Integer(_))) => Some(tok.clone()),
+                tok => {
+                    self.add_error(ParseError {
+                        kind: ParseErrorKind::InvalidSyntax("Expected array length!".to_string()),
+                        range: tok.map(|tok| tok.span().into()).unwrap_or_default(),
+                    });
+                    return None;
+                }
+            }
+        } else {
+            None
+        };
+
+        let Some(close) = self.expect_operator(Operator::CloseSquare).cloned() else {
+            self.add_error(ParseError {
+                kind: ParseErrorKind::InvalidSyntax("Expected closing bracket!".to_string()),
+                range: Range::from((*open.span(), &ty.get_range())),
+            });
+            return None;
+        };
+
+        Some(Type::Array {
+            ty: Box::new(ty),
+            len,
+            range: Range::from((*open.span(), *close.span())),
+        })
+    }
+
     pub fn precedence_of_operator(&self, operator: &Operator) -> u32 {
         match operator {
             Operator::Equals => 1,
-            Operator::Plus => 2,
-            Operator::Minus => 2,
-            Operator::Multiply => 3,
-            Operator::Divide => 3,
-            Operator::Exponent => 4,
-            Operator::Dot => 5,
-            Operator::OpenParen => 6,
+            Operator::Pipe => 2,
+            Operator::Range => 3,
+            Operator::RangeInclusive => 3,
+            Operator::LogicalOr => 4,
+            Operator::LogicalAnd => 5,
+            Operator::EqualsEquals => 6,
+            Operator::NotEquals => 6,
+            Operator::LessThan => 7,
+            Operator::GreaterThan => 7,
+            Operator::LessThanEqual => 7,
+            Operator::GreaterThanEqual => 7,
+            Operator::BitOr => 8,
+            Operator::BitXor => 9,
+            Operator::BitAnd => 10,
+            Operator::Plus => 11,
+            Operator::Minus => 11,
+            Operator::Multiply => 12,
+            Operator::Divide => 12,
+            Operator::Modulo => 12,
+            Operator::Exponent => 13,
+            // Only used as a prefix, binding tighter than binary operators besides `**`
+            Operator::LogicalNot => 13,
+            // Postfix applies to a member access, call or index before it, and binds tighter than
+            // any other operator
+            Operator::PlusPlus => 14,
+            Operator::MinusMinus => 14,
+            Operator::Dot => 14,
+            Operator::QuestionDot => 14,
+            Operator::OpenParen => 15,
+            Operator::OpenSquare => 15,
             _ => 0, // TODO: error
         }
     }
@@ -9,6 +9,9 @@ use crate::{
 pub struct Parser {
     pub(crate) tokens: TokenStream,
     pub(crate) errors: RwLock<Vec<ParseError>>,
+    /// Set while parsing the expression before a block, where `Name {` starts the block instead
+    /// of instantiating a record
+    pub(crate) in_condition: RwLock<bool>,
 }
 
 impl Parser {
@@ -16,6 +19,7 @@ impl Parser {
         Self {
             tokens: token_stream.into(),
             errors: RwLock::new(Vec::new()),
+            in_condition: RwLock::new(false),
         }
     }
 
@@ -54,6 +58,11 @@ impl Parser {
                     return Some(us);
                 }
             }
+            Some(Token::Ident(s)) if s == "const" => {
+                if let Some(cnst) = self.parse_const() {
+                    return Some(cnst);
+                }
+            }
             Some(Token::Ident(_)) => {
                 if let Some(decl) = self.parse_decleration() {
                     return Some(decl);
@@ -87,6 +96,28 @@ impl Parser {
         Some(Statement::Decleration { ident, colon, expr })
     }
 
+    pub fn parse_const(&self) -> Option<Statement> {
+        let state = self.save_state();
+        let const_token = self.tokens.next()?.clone();
+        let ident = match self.tokens.next() {
+            Some(tok @ SpannedToken(_, Token::Ident(_))) => tok.clone(),
+            _ => {
+                // `const` on its own is an ordinary identifier
+                state.restore(&self.tokens);
+                return None;
+            }
+        };
+        let equals = self.expect_operator(Operator::Equals).cloned();
+        let expr = self.parse_expression(0);
+
+        Some(Statement::Const {
+            const_token,
+            ident,
+            equals,
+            expr,
+        })
+    }
+
     pub fn parse_use(&self) -> Option<Statement> {
         let token = self.tokens.next();
         let mut args = PunctuationList::default();
@@ -176,13 +207,32 @@ impl Parser {
 
     fn parse_parameter(&self) -> Option<Param> {
         let ty = self.parse_type();
+        if let (Some(ty), Some(Token::Operator(Operator::OpenBrace))) = (&ty, self.tokens.peek()) {
+            return Some(Param {
+                ty: Some(ty.clone()),
+                name: None,
+                pattern: self.parse_record_pattern(),
+                default: None,
+            });
+        }
         let ident = self.expect(Token::Ident("".into()));
 
         match (ident, ty) {
-            (Some(ident), Some(ty)) => Some(Param {
-                ty: Some(ty),
-                name: Some(ident.clone()),
-            }),
+            (Some(ident), Some(ty)) => {
+                let default = match self.tokens.peek() {
+                    Some(Token::Operator(Operator::Equals)) => {
+                        self.tokens.next();
+                        self.parse_expression(0)
+                    }
+                    _ => None,
+                };
+                Some(Param {
+                    ty: Some(ty),
+                    name: Some(ident.clone()),
+                    pattern: None,
+                    default,
+                })
+            }
             (ident, ty) => {
                 self.add_error(ParseError {
                     kind: ParseErrorKind::InvalidSyntax("Unable to parse arg fields!".to_string()),
@@ -191,6 +241,8 @@ impl Parser {
                 Some(Param {
                     ty,
                     name: ident.cloned(),
+                    pattern: None,
+                    default: None,
                 })
             }
         }
@@ -204,7 +256,7 @@ impl Parser {
             _ => {
                 let mut args = PunctuationList::default();
 
-                while let Some(arg) = self.parse_expression(0) {
+                while let Some(arg) = self.parse_nested_expression() {
                     let comma = if let Some(Token::Operator(Operator::Comma)) = self.tokens.peek() {
                         self.tokens.next().cloned()
                     } else {
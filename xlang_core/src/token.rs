@@ -15,16 +15,40 @@ pub enum Operator {
     Quote,
 
     Dot,
+    QuestionDot,
+    Range,
+    RangeInclusive,
+    Pipe,
     Colon,
     Comma,
+    Semicolon,
     Arrow,
+    FatArrow,
+    Question,
 
     Plus,
     Minus,
+    PlusPlus,
+    MinusMinus,
     Multiply,
     Divide,
+    Modulo,
     Exponent,
+    BitAnd,
+    BitOr,
+    BitXor,
     Equals,
+
+    LessThan,
+    GreaterThan,
+    LessThanEqual,
+    GreaterThanEqual,
+    EqualsEquals,
+    NotEquals,
+
+    LogicalAnd,
+    LogicalOr,
+    LogicalNot,
 }
 
 impl Operator {
@@ -39,19 +63,59 @@ impl Operator {
             Self::Quote => "\"",
 
             Self::Dot => ".",
+            Self::QuestionDot => "?.",
+            Self::Range => "..",
+            Self::RangeInclusive => "..=",
+            Self::Pipe => "|>",
             Self::Colon => ":",
             Self::Comma => ",",
+            Self::Semicolon => ";",
             Self::Arrow => "->",
+            Self::FatArrow => "=>",
+            Self::Question => "?",
 
             Self::Plus => "+",
             Self::Minus => "-",
+            Self::PlusPlus => "++",
+            Self::MinusMinus => "--",
             Self::Multiply => "*",
             Self::Divide => "/",
+            Self::Modulo => "%",
             Self::Exponent => "**",
+            Self::BitAnd => "&",
+            Self::BitOr => "|",
+            Self::BitXor => "^",
 
             Self::Equals => "=",
+
+            Self::LessThan => "<",
+            Self::GreaterThan => ">",
+            Self::LessThanEqual => "<=",
+            Self::GreaterThanEqual => ">=",
+            Self::EqualsEquals => "==",
+            Self::NotEquals => "!=",
+
+            Self::LogicalAnd => "&&",
+            Self::LogicalOr => "||",
+            Self::LogicalNot => "!",
         }
     }
+
+    pub fn is_range(&self) -> bool {
+        matches!(self, Self::Range | Self::RangeInclusive)
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Self::LessThan
+                | Self::GreaterThan
+                | Self::LessThanEqual
+                | Self::GreaterThanEqual
+                | Self::EqualsEquals
+                | Self::NotEquals
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -135,6 +199,21 @@ impl<'a> TokenStream {
         Some(self.tokens[next_index].tok())
     }
 
+    /// Whether the next token starts a line after the one the previous token is on
+    pub fn at_new_line(&'a self) -> bool {
+        let next_index = *self.next_index.read().unwrap();
+        let (Some(previous), Some(next)) = (
+            next_index
+                .checked_sub(1)
+                .and_then(|index| self.tokens.get(index)),
+            self.tokens.get(next_index),
+        ) else {
+            return false;
+        };
+
+        next.span().line_num > previous.span().line_num
+    }
+
     pub fn back(&'a self) {
         let mut s = self.next_index.write().unwrap();
         *s -= 1;
@@ -0,0 +1,30 @@
+use xlang_core::Module;
+
+/// Parses `source`, panicking if it reports errors
+fn parse(source: &str) -> Module {
+    let (module, errors) = Module::parse_str(source, "test");
+    assert!(
+        errors.is_empty(),
+        "`{source}` failed to parse: {:?}",
+        errors.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+    );
+    module
+}
+
+#[test]
+fn one_line_function_bodies_end_with_their_line() {
+    let module = parse("f: (i32 x) -> (i32 r), r = x\na: f(1)\nb: 2");
+    assert_eq!(module.stmts.len(), 3);
+}
+
+#[test]
+fn commas_continue_function_bodies_onto_the_next_line() {
+    let module = parse("f: (i32 x) -> (i32 r, i32 s), r = x,\n    s = x\na: f(1)");
+    assert_eq!(module.stmts.len(), 2);
+}
+
+#[test]
+fn function_bodies_without_a_comma_report_an_error() {
+    let (_, errors) = Module::parse_str("f: (i32 x) -> (i32 r), r = x s = x", "test");
+    assert!(!errors.is_empty());
+}
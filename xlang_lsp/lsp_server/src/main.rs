@@ -9,7 +9,7 @@ use tower_lsp::lsp_types::request::Request;
 use tower_lsp::{lsp_types::*, LanguageServer};
 use tower_lsp::{Client, LspService, Server};
 use xlang_core::ast::{
-    ArgList, AstNode, Expression, ParamaterList, ParsedTemplate, Statement, Type,
+    ArgList, AstNode, Expression, ParamaterList, ParsedTemplate, Pattern, Statement, Type,
 };
 use xlang_core::token::{Operator, Span, SpannedToken, Token};
 use xlang_core::Module;
@@ -144,7 +144,7 @@ impl Backend {
                                 0,
                             );
                         }
-                        ParsedTemplate::Template(st, _o, _c) => {
+                        ParsedTemplate::Template(st, _o, _c, _spec) => {
                             self.recurse_expression(st, module, scope, scope_index, builder);
                         }
                     }
@@ -167,7 +167,9 @@ impl Backend {
                     match &sym.value {
                         ScopeValue::ConstValue(ConstValue {
                             kind:
-                                ConstValueKind::Function { .. } | ConstValueKind::NativeFunction { .. },
+                                ConstValueKind::Function { .. }
+                                | ConstValueKind::NativeFunction { .. }
+                                | ConstValueKind::Native { .. },
                             ..
                         }) => {
                             builder.push(
@@ -211,16 +213,7 @@ impl Backend {
                     }
                 }
             }
-            Expression::Float(_, _, tok) => {
-                builder.push(
-                    tok.span().line_num,
-                    tok.span().position,
-                    tok.span().length,
-                    get_stype_index(SemanticTokenType::NUMBER),
-                    0,
-                );
-            }
-            Expression::Integer(_, _, tok) => {
+            Expression::Float(_, _, suffix, tok) | Expression::Integer(_, _, suffix, tok) => {
                 builder.push(
                     tok.span().line_num,
                     tok.span().position,
@@ -228,16 +221,24 @@ impl Backend {
                     get_stype_index(SemanticTokenType::NUMBER),
                     0,
                 );
+                if let Some(suffix) = suffix {
+                    self.recurse_type(module, suffix, scope_index, builder);
+                }
             }
             Expression::Function {
                 parameters,
                 return_parameters,
+                requires,
                 body,
                 ..
             } => {
                 self.recurse_params(module, parameters, scope_index, builder);
                 self.recurse_params(module, return_parameters, scope_index, builder);
 
+                if let Some((_, cond)) = requires {
+                    self.recurse_expression(cond, module, scope, scope_index, builder);
+                }
+
                 if let Some(body) = body {
                     self.recurse(module, scope, body, scope_index, builder);
                 }
@@ -246,10 +247,32 @@ impl Backend {
                 self.recurse_expression(expr, module, scope, scope_index, builder);
                 self.recurse_args(module, scope, args, scope_index, builder);
             }
-            Expression::Tuple(_) => (),
+            Expression::Tuple(values, labels) => {
+                for label in labels.iter().flatten() {
+                    builder.push(
+                        label.span().line_num,
+                        label.span().position,
+                        label.span().length,
+                        get_stype_index_from_str("property"),
+                        0,
+                    );
+                }
+                values.iter().for_each(|value| {
+                    self.recurse_expression(value, module, scope, scope_index, builder)
+                })
+            }
+            Expression::Sequence(values) => values.iter().for_each(|value| {
+                self.recurse_expression(value, module, scope, scope_index, builder)
+            }),
             Expression::Array { values, .. } => values.iter_items().for_each(|item| {
                 self.recurse_expression(item, module, scope, scope_index, builder)
             }),
+            Expression::Index { expr, index, .. } => {
+                self.recurse_expression(expr, module, scope, scope_index, builder);
+                if let Some(index) = index {
+                    self.recurse_expression(index, module, scope, scope_index, builder);
+                }
+            }
             Expression::BinaryExpression { left, right, .. } => {
                 if let Some(left) = left {
                     self.recurse_expression(left, module, scope, scope_index, builder);
@@ -261,6 +284,78 @@ impl Backend {
             Expression::Record { parameters } => {
                 self.recurse_params(module, parameters, scope_index, builder);
             }
+            Expression::RecordLiteral { name, fields, .. } => {
+                if let Some(name) = name {
+                    builder.push(
+                        name.span().line_num,
+                        name.span().position,
+                        name.span().length,
+                        get_stype_index(SemanticTokenType::TYPE),
+                        0,
+                    );
+                }
+                fields
+                    .iter_items()
+                    .for_each(|field| self.recurse(module, scope, field, scope_index, builder))
+            }
+            Expression::IfLet { expr, body, .. }
+            | Expression::For { expr, body, .. }
+            | Expression::Repeat {
+                count: expr, body, ..
+            }
+            | Expression::While {
+                condition: expr,
+                body,
+                ..
+            }
+            | Expression::With { expr, body, .. } => {
+                self.recurse_expression(expr, module, scope, scope_index, builder);
+                self.recurse(module, scope, body, scope_index, builder);
+            }
+            Expression::If {
+                branches,
+                else_body,
+                ..
+            } => {
+                for (condition, body) in branches {
+                    self.recurse_expression(condition, module, scope, scope_index, builder);
+                    self.recurse(module, scope, body, scope_index, builder);
+                }
+                if let Some(body) = else_body {
+                    self.recurse(module, scope, body, scope_index, builder);
+                }
+            }
+            Expression::Match { expr, arms, .. } => {
+                self.recurse_expression(expr, module, scope, scope_index, builder);
+                for arm in arms.iter_items() {
+                    match &arm.pattern {
+                        Pattern::Typed { ty, .. } => {
+                            self.recurse_type(module, ty, scope_index, builder)
+                        }
+                        Pattern::Literals(literals) => {
+                            for literal in literals {
+                                self.recurse_expression(
+                                    literal,
+                                    module,
+                                    scope,
+                                    scope_index,
+                                    builder,
+                                );
+                            }
+                        }
+                        _ => (),
+                    }
+                    self.recurse(module, scope, &arm.body, scope_index, builder);
+                }
+            }
+            Expression::Defer { expr, .. } => {
+                self.recurse_expression(expr, module, scope, scope_index, builder)
+            }
+            Expression::Return { expr, .. } => {
+                if let Some(expr) = expr {
+                    self.recurse_expression(expr, module, scope, scope_index, builder)
+                }
+            }
         }
     }
 
@@ -289,7 +384,8 @@ impl Backend {
                 self.recurse_type(module, ty, scope_index, builder);
             }
 
-            if let Some(name) = &item.name {
+            let bindings = item.pattern.iter().flat_map(|pattern| pattern.bindings());
+            for name in item.name.iter().chain(bindings) {
                 builder.push(
                     name.span().line_num,
                     name.span().position,
@@ -318,7 +414,7 @@ impl Backend {
                     0,
                 );
             }
-            Type::Float { token, .. } => {
+            Type::Float { token, .. } | Type::Boolean(token) | Type::String(token) => {
                 builder.push(
                     token.span().line_num,
                     token.span().position,
@@ -327,7 +423,7 @@ impl Backend {
                     0,
                 );
             }
-            Type::Ident(ident) => {
+            Type::Ident(ident, _) => {
                 builder.push(
                     ident.span().line_num,
                     ident.span().position,
@@ -336,6 +432,9 @@ impl Backend {
                     0,
                 );
             }
+            Type::Optional { ty, .. } | Type::Array { ty, .. } | Type::Ref { ty, .. } => {
+                self.recurse_type(_module, ty, _scope_index, builder)
+            }
         }
     }
 
@@ -370,6 +469,30 @@ impl Backend {
                     self.recurse_expression(expr, module, scope, scope_index, builder);
                 }
             }
+            Statement::Const {
+                const_token,
+                ident,
+                expr,
+                ..
+            } => {
+                builder.push(
+                    const_token.span().line_num,
+                    const_token.span().position,
+                    const_token.span().length,
+                    get_stype_index_from_str("keyword"),
+                    0,
+                );
+                builder.push(
+                    ident.span().line_num,
+                    ident.span().position,
+                    ident.span().length,
+                    get_stype_index_from_str("variable"),
+                    0,
+                );
+                if let Some(expr) = expr {
+                    self.recurse_expression(expr, module, scope, scope_index, builder);
+                }
+            }
             Statement::Expression(e) => {
                 self.recurse_expression(e, module, scope, scope_index, builder)
             }
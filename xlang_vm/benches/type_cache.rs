@@ -0,0 +1,21 @@
+#![feature(test)]
+
+extern crate test;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use test::Bencher;
+
+/// Calls a function whose parameters are records many times, resolving their type names on each
+/// call
+#[bench]
+fn repeated_calls_with_typed_parameters(b: &mut Bencher) {
+    let source = "P: (i32 x)\nQ: (i32 y)\n\
+                  f: (P p, Q q) -> (i32 out), out = p.x + q.y\n\
+                  total: 0i32\nfor i in 0..1000 { total = f(P(1i32), Q(2i32)).out }";
+    b.iter(|| {
+        let evaluator = common::evaluate_ok(source);
+        assert_eq!(common::display(&evaluator, "total"), "3");
+    });
+}
@@ -1,10 +1,12 @@
 use std::{
+    cmp::Ordering,
     fmt::{Debug, Display},
+    hash::{Hash, Hasher},
     sync::Arc,
 };
 
 use linked_hash_map::LinkedHashMap;
-use xlang_core::ast::Statement;
+use xlang_core::ast::{Alignment, Expression, FormatSpec, Pattern, Statement};
 use xlang_util::{
     format::{NodeDisplay, TreeDisplay},
     Rf,
@@ -24,6 +26,7 @@ pub enum Type {
     Float {
         width: u8,
     },
+    Boolean,
     Function {
         parameters: LinkedHashMap<String, Type>,
         return_parameters: LinkedHashMap<String, Type>,
@@ -32,10 +35,20 @@ pub enum Type {
     Symbol(Rf<Scope>),
     Ident(String),
     Tuple(Vec<Type>),
+    /// Element type, and length if the array has a fixed length
+    Array(Box<Type>, Option<usize>),
+    Range(Box<Type>),
+    Optional(Box<Type>),
+    /// Type of the value being referenced
+    Ref(Box<Type>),
+    Thunk,
     RecordInstance {
         rf: Option<Rf<Scope>>,
         members: LinkedHashMap<String, Type>,
     },
+    /// The types of the branches of a conditional that yield different records. The value still
+    /// has the type of the branch that was taken, see `ConstValue::narrow`.
+    Union(Vec<Type>),
 }
 
 impl PartialEq for Type {
@@ -65,6 +78,10 @@ impl PartialEq for Type {
             (Self::Symbol(l0), Self::Symbol(r0)) => l0 == r0,
             (Self::Ident(l0), Self::Ident(r0)) => l0 == r0,
             (Self::Tuple(l0), Self::Tuple(r0)) => l0 == r0,
+            (Self::Array(l0, l1), Self::Array(r0, r1)) => l0 == r0 && l1 == r1,
+            (Self::Range(l0), Self::Range(r0)) => l0 == r0,
+            (Self::Optional(l0), Self::Optional(r0)) => l0 == r0,
+            (Self::Ref(l0), Self::Ref(r0)) => l0 == r0,
             (
                 Self::RecordInstance {
                     rf: l_rf,
@@ -77,6 +94,7 @@ impl PartialEq for Type {
             ) => l_rf == r_rf && l_members == r_members,
             (Self::RecordInstance { rf: Some(l_rf), .. }, Self::Symbol(sym)) => l_rf == sym,
             (Self::Symbol(sym), Self::RecordInstance { rf: Some(l_rf), .. }) => sym == l_rf,
+            (Self::Union(l0), Self::Union(r0)) => l0 == r0,
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
@@ -101,6 +119,7 @@ impl Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::String => f.write_str("string"),
+            Self::Boolean => f.write_str("bool"),
             Self::Float { width, .. } => write!(f, "f{width}"),
             Self::Integer {
                 width,
@@ -141,7 +160,7 @@ impl Display for Type {
             }
             Self::Symbol(rs) => {
                 let rs = rs.borrow();
-                if let ScopeValue::Record { ident, members } = &rs.value {
+                if let ScopeValue::Record { ident, members, .. } = &rs.value {
                     write!(f, "{ident}: (")?;
                     let mut iter = members.iter();
 
@@ -168,6 +187,23 @@ impl Display for Type {
                 write!(f, ")")
             }
             Self::Ident(i) => f.write_str(i),
+            Self::Array(ty, None) => write!(f, "[{ty}]"),
+            Self::Array(ty, Some(len)) => write!(f, "[{ty}; {len}]"),
+            Self::Range(ty) => write!(f, "range<{ty}>"),
+            Self::Optional(ty) => write!(f, "{ty}?"),
+            Self::Ref(ty) => write!(f, "&{ty}"),
+            Self::Thunk => f.write_str("thunk"),
+            Self::Union(types) => {
+                let mut iter = types.iter();
+
+                if let Some(val) = iter.next() {
+                    write!(f, "{val}")?;
+                }
+                for val in iter {
+                    write!(f, " | {val}")?;
+                }
+                Ok(())
+            }
             Self::Tuple(ty) => {
                 write!(f, "(")?;
                 let mut iter = ty.iter();
@@ -188,9 +224,16 @@ impl NodeDisplay for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::String => f.write_str("string"),
+            Self::Boolean => f.write_str("bool"),
             Self::Symbol { .. } => write!(f, "Symbol"),
             Self::RecordInstance { .. } => write!(f, "Record Instance"),
             Self::Tuple(_) => write!(f, "Tuple"),
+            Self::Array(..) => write!(f, "Array"),
+            Self::Range(_) => write!(f, "Range"),
+            Self::Optional(_) => write!(f, "Optional"),
+            Self::Ref(_) => write!(f, "Reference"),
+            Self::Thunk => write!(f, "Thunk"),
+            Self::Union(_) => write!(f, "Union"),
             Self::Empty => write!(f, "Empty"),
             Self::CoercibleInteger => write!(f, "Coercible Integer"),
             Self::CoercibleFloat => write!(f, "Coercible Float"),
@@ -219,7 +262,8 @@ impl TreeDisplay for Type {
     fn num_children(&self) -> usize {
         match self {
             Type::Function { .. } => 2,
-            Type::Tuple(tu) => tu.len(),
+            Type::Tuple(tu) | Type::Union(tu) => tu.len(),
+            Type::Array(..) | Type::Range(_) | Type::Optional(_) => 1,
             Type::RecordInstance { members, .. } => members.len(),
             _ => 0,
         }
@@ -235,13 +279,14 @@ impl TreeDisplay for Type {
                 1 => Some(return_parameters),
                 _ => None,
             },
-            Type::Tuple(tu) => {
+            Type::Tuple(tu) | Type::Union(tu) => {
                 if let Some(ty) = tu.get(_index) {
                     Some(ty)
                 } else {
                     None
                 }
             }
+            Type::Array(ty, _) | Type::Range(ty) | Type::Optional(ty) => Some(&**ty),
             Type::RecordInstance { .. } => None,
             _ => None,
         }
@@ -255,21 +300,30 @@ impl TreeDisplay for Type {
     }
 }
 
+pub type NativeCallback = Arc<dyn Fn(&[ConstValue]) -> ConstValue + Sync + Send>;
+
 #[derive(Clone)]
 pub enum ConstValueKind {
     Empty,
+    Null,
     Integer {
         value: u64,
     },
     Float {
         value: f64,
     },
+    Bool(bool),
     String {
         string: String,
     },
     Function {
         rf: Rf<Scope>,
         body: Statement,
+        pure: bool,
+        /// Checked with the parameters bound before the body runs
+        requires: Option<Expression>,
+        /// Destructuring parameters, keyed by the name their argument is bound to
+        patterns: Vec<(String, Pattern)>,
     },
     NativeFunction {
         rf: Rf<Scope>,
@@ -279,10 +333,40 @@ pub enum ConstValueKind {
                 + Send,
         >,
     },
-    Tuple(Vec<ConstValue>),
+    /// Registered by the host with `Evaluator::register_native`. Takes its arguments in order
+    /// rather than by name, and any number of them when `arity` is `None`.
+    Native {
+        name: String,
+        arity: Option<usize>,
+        callback: NativeCallback,
+    },
+    /// Elements, and the label of each element if it was given one
+    Tuple(Vec<ConstValue>, Vec<Option<String>>),
+    /// Elements are shared between copies of the array until one of them is written to
+    Array(Arc<Vec<ConstValue>>),
+    Range {
+        start: u64,
+        end: u64,
+        inclusive: bool,
+    },
     RecordInstance {
         rf: Rf<Scope>,
-        members: LinkedHashMap<String, ConstValue>,
+        /// Shared between copies of the instance until one of them is written to
+        members: Arc<LinkedHashMap<String, ConstValue>>,
+        /// Set by `freeze`, after which none of the members can be assigned to
+        frozen: bool,
+    },
+    /// An unevaluated expression and the scopes it was created in. The scopes are shared rather
+    /// than copied, so forcing the thunk sees the values symbols have at that point.
+    Thunk {
+        expr: Expression,
+        scope: Vec<Rf<Scope>>,
+    },
+    /// A binding, or a field reached from it through `path`. Reads and writes go through the
+    /// binding's symbol, so they see and make changes to the original value.
+    Ref {
+        target: Rf<Scope>,
+        path: Vec<String>,
     },
 }
 
@@ -290,22 +374,57 @@ impl Display for ConstValueKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConstValueKind::Empty => f.write_str("()"),
+            ConstValueKind::Null => f.write_str("null"),
             ConstValueKind::Integer { value } => write!(f, "{value}"),
             ConstValueKind::Float { value } => write!(f, "{value}"),
+            ConstValueKind::Bool(value) => write!(f, "{value}"),
             ConstValueKind::String { string } => write!(f, "{string}"),
             ConstValueKind::Function { body, .. } => write!(f, "{}", body.format()),
             ConstValueKind::NativeFunction { .. } => write!(f, "Native Function"),
-            ConstValueKind::Tuple(list) => {
-                let mut iter = list.iter();
-                let Some(item) = iter.next() else {
+            ConstValueKind::Native { name, .. } => write!(f, "Native Function {name}"),
+            ConstValueKind::Thunk { .. } => write!(f, "Thunk"),
+            ConstValueKind::Ref { target, path } => {
+                match resolve_path(&target.borrow().value, path) {
+                    Some(value) => write!(f, "&{value}"),
+                    None => f.write_str("&()"),
+                }
+            }
+            ConstValueKind::Tuple(list, labels) => {
+                if list.is_empty() {
                     return writeln!(f, "()");
-                };
-                write!(f, "{}", item.kind)?;
-                for item in iter {
-                    write!(f, ", {}", item.kind)?;
+                }
+                for (i, (item, label)) in list.iter().zip(labels).enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    if let Some(label) = label {
+                        write!(f, "{label}: ")?;
+                    }
+                    write!(f, "{item}")?;
                 }
                 Ok(())
             }
+            ConstValueKind::Array(list) => {
+                write!(f, "[")?;
+                let mut iter = list.iter();
+                if let Some(item) = iter.next() {
+                    write!(f, "{item}")?;
+                }
+                for item in iter {
+                    write!(f, ", {item}")?;
+                }
+                write!(f, "]")
+            }
+            ConstValueKind::Range {
+                start,
+                end,
+                inclusive: false,
+            } => write!(f, "{start}..{end}"),
+            ConstValueKind::Range {
+                start,
+                end,
+                inclusive: true,
+            } => write!(f, "{start}..={end}"),
             ConstValueKind::RecordInstance { members, .. } => {
                 let mut iter = members.iter();
                 write!(f, "{{ ")?;
@@ -338,9 +457,23 @@ impl ConstValueKind {
         }
     }
 
+    pub fn as_bool(&self) -> bool {
+        match self {
+            ConstValueKind::Bool(value) => *value,
+            _ => panic!(),
+        }
+    }
+
+    pub fn as_string(&self) -> &str {
+        match self {
+            ConstValueKind::String { string } => string,
+            _ => panic!(),
+        }
+    }
+
     pub fn as_record_instance(&self) -> (&Rf<Scope>, &LinkedHashMap<String, ConstValue>) {
         match self {
-            ConstValueKind::RecordInstance { rf, members } => (rf, members),
+            ConstValueKind::RecordInstance { rf, members, .. } => (rf, members),
             _ => panic!(),
         }
     }
@@ -354,12 +487,19 @@ impl NodeDisplay for ConstValueKind {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             ConstValueKind::Empty => write!(f, "Empty"),
+            ConstValueKind::Null => write!(f, "Null"),
             ConstValueKind::Integer { value } => write!(f, "Integer: {value}"),
             ConstValueKind::Float { value } => write!(f, "Float: {value}"),
+            ConstValueKind::Bool(value) => write!(f, "Bool: {value}"),
             ConstValueKind::String { string } => write!(f, "String: {string}"),
             ConstValueKind::Function { .. } => write!(f, "Function"),
             ConstValueKind::NativeFunction { .. } => write!(f, "Native Function"),
-            ConstValueKind::Tuple(_) => write!(f, "Tuple"),
+            ConstValueKind::Native { name, .. } => write!(f, "Native Function: {name}"),
+            ConstValueKind::Thunk { .. } => write!(f, "Thunk"),
+            ConstValueKind::Ref { .. } => write!(f, "Reference"),
+            ConstValueKind::Tuple(..) => write!(f, "Tuple"),
+            ConstValueKind::Array(_) => write!(f, "Array"),
+            ConstValueKind::Range { .. } => write!(f, "Range: {self}"),
             ConstValueKind::RecordInstance { .. } => write!(f, "Record Instance"),
         }
     }
@@ -369,7 +509,8 @@ impl TreeDisplay for ConstValueKind {
     fn num_children(&self) -> usize {
         match self {
             ConstValueKind::Function { .. } => 1,
-            ConstValueKind::Tuple(list) => list.len(),
+            ConstValueKind::Tuple(list, _) => list.len(),
+            ConstValueKind::Array(list) => list.len(),
             ConstValueKind::RecordInstance { members, .. } => members.len(),
             _ => 0,
         }
@@ -381,7 +522,14 @@ impl TreeDisplay for ConstValueKind {
                 0 => Some(body),
                 _ => None,
             },
-            ConstValueKind::Tuple(tu) => {
+            ConstValueKind::Tuple(tu, _) => {
+                if let Some(val) = tu.get(index) {
+                    Some(val)
+                } else {
+                    None
+                }
+            }
+            ConstValueKind::Array(tu) => {
                 if let Some(val) = tu.get(index) {
                     Some(val)
                 } else {
@@ -415,17 +563,71 @@ impl ConstValue {
         }
     }
 
-    pub fn default_for(ty: &Type) -> ConstValue {
+    /// The zero value of `ty`, used for uninitialized parameters. Records are defaulted member by
+    /// member. Returns `None` for types without a sensible default, like functions.
+    pub fn default_for(ty: &Type) -> Option<ConstValue> {
         let kind = match ty {
             Type::Empty => ConstValueKind::Empty,
-            Type::Integer { .. } => ConstValueKind::Integer { value: 0 },
-            Type::Float { .. } => ConstValueKind::Float { value: 0.0 },
+            Type::Integer { .. } | Type::CoercibleInteger => ConstValueKind::Integer { value: 0 },
+            Type::Float { .. } | Type::CoercibleFloat => ConstValueKind::Float { value: 0.0 },
+            Type::Boolean => ConstValueKind::Bool(false),
+            Type::String => ConstValueKind::String {
+                string: String::new(),
+            },
+            Type::Tuple(types) => ConstValueKind::Tuple(
+                types
+                    .iter()
+                    .map(ConstValue::default_for)
+                    .collect::<Option<_>>()?,
+                vec![None; types.len()],
+            ),
+            Type::Array(_, None) => ConstValueKind::Array(Arc::default()),
+            Type::Array(ty, Some(len)) => {
+                let value = ConstValue::default_for(ty)?;
+                ConstValueKind::Array(Arc::new(vec![value; *len]))
+            }
+            Type::Optional(_) => ConstValueKind::Null,
+            Type::Function { .. } => return None,
+            Type::Symbol(sym) => {
+                let ScopeValue::Record { members, .. } = &sym.borrow().value else {
+                    return None;
+                };
+                let members = Self::default_members(members)?;
+
+                return Some(ConstValue::record_instance(sym.clone(), members));
+            }
+            Type::RecordInstance { rf, members } => {
+                let members = Self::default_members(members)?;
+                let rf = rf
+                    .clone()
+                    .unwrap_or_else(|| Rf::new(Scope::new(ScopeValue::Root, 0)));
+
+                return Some(ConstValue::record_instance(rf, members));
+            }
             _ => ConstValueKind::Empty,
         };
 
-        ConstValue {
+        Some(ConstValue {
             ty: ty.clone(),
             kind,
+        })
+    }
+
+    fn default_members(
+        members: &LinkedHashMap<String, Type>,
+    ) -> Option<LinkedHashMap<String, ConstValue>> {
+        members
+            .iter()
+            .map(|(name, ty)| Some((name.clone(), ConstValue::default_for(ty)?)))
+            .collect()
+    }
+
+    /// A null optional of `ty`. `Type::Empty` is used when the type isn't known yet, as for the
+    /// `null` literal.
+    pub fn null(ty: Type) -> ConstValue {
+        ConstValue {
+            ty: Type::Optional(Box::new(ty)),
+            kind: ConstValueKind::Null,
         }
     }
 
@@ -436,6 +638,13 @@ impl ConstValue {
         }
     }
 
+    pub fn bool(value: bool) -> ConstValue {
+        ConstValue {
+            ty: Type::Boolean,
+            kind: ConstValueKind::Bool(value),
+        }
+    }
+
     pub fn integer(value: u64, width: u8, signed: bool) -> ConstValue {
         ConstValue {
             kind: ConstValueKind::Integer { value },
@@ -450,6 +659,27 @@ impl ConstValue {
         }
     }
 
+    /// Resolves an untyped integer to the narrowest type that holds it. Signed types are preferred,
+    /// so `5` becomes an `i8` rather than a `u8`, and only values past `i64::MAX` are unsigned.
+    pub fn smallest_fitting(&self) -> Option<ConstValue> {
+        match self {
+            ConstValue {
+                kind: ConstValueKind::Integer { value },
+                ty: Type::CoercibleInteger,
+            } => {
+                let width = [8, 16, 32, 64]
+                    .into_iter()
+                    .find(|width| *value < 1 << (width - 1));
+
+                Some(match width {
+                    Some(width) => ConstValue::integer(*value, width, true),
+                    None => ConstValue::integer(*value, 64, false),
+                })
+            }
+            _ => None,
+        }
+    }
+
     pub fn float(value: f64, width: u8) -> ConstValue {
         ConstValue {
             kind: ConstValueKind::Float { value },
@@ -469,9 +699,18 @@ impl ConstValue {
         parameters: LinkedHashMap<String, Type>,
         return_parameters: LinkedHashMap<String, Type>,
         node: Rf<Scope>,
+        pure: bool,
+        requires: Option<Expression>,
+        patterns: Vec<(String, Pattern)>,
     ) -> ConstValue {
         ConstValue {
-            kind: ConstValueKind::Function { body, rf: node },
+            kind: ConstValueKind::Function {
+                body,
+                rf: node,
+                pure,
+                requires,
+                patterns,
+            },
             ty: Type::Function {
                 parameters,
                 return_parameters,
@@ -479,14 +718,89 @@ impl ConstValue {
         }
     }
 
+    pub fn reference(target: Rf<Scope>, path: Vec<String>, ty: Type) -> ConstValue {
+        ConstValue {
+            ty: Type::Ref(Box::new(ty)),
+            kind: ConstValueKind::Ref { target, path },
+        }
+    }
+
+    /// The current value behind a reference, or `None` if this isn't a reference or the field it
+    /// points at no longer exists
+    pub fn read_ref(&self) -> Option<ConstValue> {
+        let ConstValueKind::Ref { target, path } = &self.kind else {
+            return None;
+        };
+
+        resolve_path(&target.borrow().value, path).cloned()
+    }
+
+    /// Replaces the value behind a reference, returning whether there was one to replace
+    pub fn write_ref(&self, value: ConstValue) -> bool {
+        let ConstValueKind::Ref { target, path } = &self.kind else {
+            return false;
+        };
+
+        let mut target = target.borrow_mut();
+        let ScopeValue::ConstValue(current) = &mut target.value else {
+            return false;
+        };
+        let mut current = current;
+        for field in path {
+            let ConstValueKind::RecordInstance { members, .. } = &mut current.kind else {
+                return false;
+            };
+            let Some(member) = Arc::make_mut(members).get_mut(field) else {
+                return false;
+            };
+            current = member;
+        }
+
+        *current = value;
+        true
+    }
+
+    pub fn thunk(expr: Expression, scope: Vec<Rf<Scope>>) -> ConstValue {
+        ConstValue {
+            ty: Type::Thunk,
+            kind: ConstValueKind::Thunk { expr, scope },
+        }
+    }
+
     pub fn tuple(values: Vec<ConstValue>) -> ConstValue {
+        let labels = vec![None; values.len()];
+        ConstValue::labeled_tuple(values, labels)
+    }
+
+    /// Creates a tuple whose elements can also be accessed by their label, if they have one.
+    /// Labels aren't part of the type, so `(x: 1, y: 2)` is a `(i32, i32)`.
+    pub fn labeled_tuple(values: Vec<ConstValue>, labels: Vec<Option<String>>) -> ConstValue {
         let types: Vec<_> = values.iter().map(|val| val.ty.clone()).collect();
         ConstValue {
-            kind: ConstValueKind::Tuple(values),
+            kind: ConstValueKind::Tuple(values, labels),
             ty: Type::Tuple(types),
         }
     }
 
+    /// Creates an array of `values`, which should all be of type `ty`
+    pub fn array(values: Vec<ConstValue>, ty: Type) -> ConstValue {
+        ConstValue {
+            kind: ConstValueKind::Array(Arc::new(values)),
+            ty: Type::Array(Box::new(ty), None),
+        }
+    }
+
+    pub fn range(start: u64, end: u64, inclusive: bool, ty: Type) -> ConstValue {
+        ConstValue {
+            kind: ConstValueKind::Range {
+                start,
+                end,
+                inclusive,
+            },
+            ty: Type::Range(Box::new(ty)),
+        }
+    }
+
     pub fn record_instance(
         sym: Rf<Scope>,
         values: LinkedHashMap<String, ConstValue>,
@@ -501,13 +815,166 @@ impl ConstValue {
             },
             kind: ConstValueKind::RecordInstance {
                 rf: sym,
-                members: values,
+                members: Arc::new(values),
+                frozen: false,
             },
         }
     }
 
+    /// The value with the type of the branch that made it, if it has the union of the types of
+    /// several branches. Only records are put in unions, so the type comes from the record.
+    pub fn narrow(&self) -> ConstValue {
+        match (&self.ty, &self.kind) {
+            (Type::Union(_), ConstValueKind::RecordInstance { rf, members, .. }) => {
+                let types = members.values().map(|val| val.ty.clone());
+                ConstValue {
+                    ty: Type::RecordInstance {
+                        rf: Some(rf.clone()),
+                        members: LinkedHashMap::from_iter(members.keys().cloned().zip(types)),
+                    },
+                    kind: self.kind.clone(),
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// A copy of a record instance whose members can't be assigned to, and neither can those of
+    /// any records nested in it. Returns `None` if the value isn't a record instance.
+    pub fn freeze(&self) -> Option<ConstValue> {
+        let ConstValueKind::RecordInstance { rf, members, .. } = &self.kind else {
+            return None;
+        };
+
+        let members = members
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.clone(),
+                    value.freeze().unwrap_or_else(|| value.clone()),
+                )
+            })
+            .collect::<LinkedHashMap<_, _>>();
+        Some(ConstValue {
+            ty: self.ty.clone(),
+            kind: ConstValueKind::RecordInstance {
+                rf: rf.clone(),
+                members: Arc::new(members),
+                frozen: true,
+            },
+        })
+    }
+
+    /// Combines two record instances, which don't need to be of the same type. The result has the
+    /// union of their fields: fields of `other` replace fields of the same name in `self`, keeping
+    /// their position, and fields only in `other` are added after the fields of `self`. A field in
+    /// both must be of the same type, after implicitly casting the field of `other`. The result
+    /// is an instance of the same record if both are, or an anonymous record otherwise.
+    pub fn merge(&self, other: &ConstValue) -> Result<ConstValue, Box<MergeError>> {
+        let ConstValueKind::RecordInstance {
+            rf: base_rf,
+            members: base,
+            ..
+        } = &self.kind
+        else {
+            return Err(Box::new(MergeError::NotRecord(self.ty.clone())));
+        };
+        let ConstValueKind::RecordInstance {
+            rf: other_rf,
+            members: other,
+            ..
+        } = &other.kind
+        else {
+            return Err(Box::new(MergeError::NotRecord(other.ty.clone())));
+        };
+
+        let mut members = LinkedHashMap::clone(base);
+        for (name, value) in other.iter() {
+            let Some(base) = members.get_mut(name) else {
+                members.insert(name.clone(), value.clone());
+                continue;
+            };
+
+            let value = value
+                .try_implicit_cast(&base.ty)
+                .unwrap_or_else(|| value.clone());
+            if value.ty != base.ty {
+                return Err(Box::new(MergeError::FieldMismatch {
+                    field: name.clone(),
+                    base: base.ty.clone(),
+                    other: value.ty,
+                }));
+            }
+            *base = value;
+        }
+
+        let rf = if base_rf == other_rf {
+            base_rf.clone()
+        } else {
+            Rf::new(Scope::new(ScopeValue::Root, 0))
+        };
+
+        Ok(ConstValue::record_instance(rf, members))
+    }
+
+    /// Converts a record instance to the record `target`. Fields with the same name are copied
+    /// over, after implicitly casting them to the type of the field in `target`. Fields only in
+    /// `target` take their default value, and fields only in `self` are dropped.
+    pub fn convert(&self, target: &Rf<Scope>) -> Result<ConstValue, Box<ConvertError>> {
+        let ConstValueKind::RecordInstance { members, .. } = &self.kind else {
+            return Err(Box::new(ConvertError::NotRecord(self.ty.clone())));
+        };
+        let ScopeValue::Record {
+            members: target_members,
+            ..
+        } = &target.borrow().value
+        else {
+            return Err(Box::new(ConvertError::NotRecord(Type::Symbol(
+                target.clone(),
+            ))));
+        };
+
+        let values = target_members
+            .iter()
+            .map(|(name, ty)| {
+                let Some(value) = members.get(name) else {
+                    return ConstValue::default_for(ty)
+                        .map(|value| (name.clone(), value))
+                        .ok_or_else(|| Box::new(ConvertError::NoDefaultValue(ty.clone())));
+                };
+                let value = value.try_implicit_cast(ty).unwrap_or_else(|| value.clone());
+                if &value.ty != ty {
+                    return Err(Box::new(ConvertError::FieldMismatch {
+                        field: name.clone(),
+                        expected: ty.clone(),
+                        found: value.ty,
+                    }));
+                }
+                Ok((name.clone(), value))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ConstValue::record_instance(target.clone(), values))
+    }
+
     pub fn try_implicit_cast(&self, ty: &Type) -> Option<ConstValue> {
         match (self, ty) {
+            // A union is narrowed to the type of the branch that made the value
+            (
+                ConstValue {
+                    ty: Type::Union(_), ..
+                },
+                ty,
+            ) if !matches!(ty, Type::Union(_)) => {
+                let value = self.narrow();
+                let value = value.try_implicit_cast(ty).unwrap_or(value);
+                (value.ty == *ty).then_some(value)
+            }
+            // Values of one of the types of a union can be used where the union is expected
+            (value, Type::Union(types)) if types.contains(&value.ty) => Some(ConstValue {
+                ty: ty.clone(),
+                kind: value.kind.clone(),
+            }),
             (
                 ConstValue {
                     kind: ConstValueKind::Integer { value },
@@ -522,14 +989,561 @@ impl ConstValue {
                 },
                 Type::Float { width },
             ) => Some(ConstValue::float(*value, *width)),
+            // An untyped null becomes a null of any optional type
+            (
+                ConstValue {
+                    kind: ConstValueKind::Null,
+                    ty: Type::Optional(inner),
+                },
+                Type::Optional(ty),
+            ) if **inner == Type::Empty => Some(ConstValue::null(Type::clone(ty))),
+            // Elements are cast to the element type, and arrays of the right length take on a
+            // fixed length
+            (
+                ConstValue {
+                    kind: ConstValueKind::Array(values),
+                    ty: Type::Array(..),
+                },
+                Type::Array(ty, len),
+            ) if len.is_none_or(|len| len == values.len()) => {
+                let values = values
+                    .iter()
+                    .map(|value| {
+                        let value = value.try_implicit_cast(ty).unwrap_or_else(|| value.clone());
+                        (value.ty == **ty).then_some(value)
+                    })
+                    .collect::<Option<_>>()?;
+
+                Some(ConstValue {
+                    kind: ConstValueKind::Array(Arc::new(values)),
+                    ty: Type::Array(ty.clone(), *len),
+                })
+            }
+            // Values are wrapped when passed where an optional is expected
+            (value, Type::Optional(ty)) if !matches!(value.ty, Type::Optional(_)) => {
+                let value = value.try_implicit_cast(ty).unwrap_or_else(|| value.clone());
+                (value.ty == **ty).then(|| ConstValue {
+                    ty: Type::Optional(ty.clone()),
+                    kind: value.kind,
+                })
+            }
             _ => None,
         }
     }
+
+    /// The value of an integer, which is negative if it's of a signed type below zero. Signed
+    /// integers are stored sign extended, and untyped integers are signed, so an untyped integer
+    /// past `i64::MAX` is taken to be negative.
+    pub fn integer_value(&self) -> Option<i128> {
+        let ConstValueKind::Integer { value } = &self.kind else {
+            return None;
+        };
+        // Optionals hold a value of their inner type
+        let ty = match &self.ty {
+            Type::Optional(ty) => &**ty,
+            ty => ty,
+        };
+
+        Some(integer_of_type(*value, ty))
+    }
+
+    /// The start and exclusive end of a range, read with the signedness of its element type, or
+    /// `None` if the value isn't a range
+    pub fn range_bounds(&self) -> Option<(i128, i128)> {
+        match (&self.kind, &self.ty) {
+            (
+                ConstValueKind::Range {
+                    start,
+                    end,
+                    inclusive,
+                },
+                Type::Range(ty),
+            ) => Some((
+                integer_of_type(*start, ty),
+                integer_of_type(*end, ty) + *inclusive as i128,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Orders two values, or returns `None` if they can't be ordered. Numbers are compared by
+    /// value regardless of their width, signedness or whether they're integers or floats, and
+    /// strings are compared by their Unicode scalar values. Optionals compare by their values, and null sorts
+    /// before any value that isn't null. Tuples are ordered by their first elements that differ,
+    /// and can't be ordered if they have a different number of elements.
+    pub fn compare(&self, other: &ConstValue) -> Option<Ordering> {
+        match (&self.kind, &other.kind) {
+            (ConstValueKind::Null, ConstValueKind::Null) => Some(Ordering::Equal),
+            (ConstValueKind::Null, _) => Some(Ordering::Less),
+            (_, ConstValueKind::Null) => Some(Ordering::Greater),
+            (ConstValueKind::Integer { .. }, ConstValueKind::Integer { .. }) => {
+                Some(self.integer_value()?.cmp(&other.integer_value()?))
+            }
+            (ConstValueKind::Float { value: l }, ConstValueKind::Float { value: r }) => {
+                l.partial_cmp(r)
+            }
+            (ConstValueKind::Integer { .. }, ConstValueKind::Float { value: r }) => {
+                (self.integer_value()? as f64).partial_cmp(r)
+            }
+            (ConstValueKind::Float { value: l }, ConstValueKind::Integer { .. }) => {
+                l.partial_cmp(&(other.integer_value()? as f64))
+            }
+            (ConstValueKind::String { string: l }, ConstValueKind::String { string: r }) => {
+                Some(l.cmp(r))
+            }
+            (ConstValueKind::Tuple(l, _), ConstValueKind::Tuple(r, _)) if l.len() == r.len() => {
+                for (l, r) in l.iter().zip(r) {
+                    match l.compare(r)? {
+                        Ordering::Equal => (),
+                        ordering => return Some(ordering),
+                    }
+                }
+                Some(Ordering::Equal)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether two values are equal. This is the same as `compare` finding them equal, except
+    /// that booleans are equal to each other too, including inside tuples. Tuples with a
+    /// different number of elements are never equal.
+    pub fn eq_value(&self, other: &ConstValue) -> bool {
+        match (&self.kind, &other.kind) {
+            (ConstValueKind::Bool(l), ConstValueKind::Bool(r)) => l == r,
+            (ConstValueKind::Tuple(l, _), ConstValueKind::Tuple(r, _)) => {
+                l.len() == r.len() && l.iter().zip(r).all(|(l, r)| l.eq_value(r))
+            }
+            _ => self.compare(other) == Some(Ordering::Equal),
+        }
+    }
+
+    /// Where two values differ, as the path to each difference from the values, like `.inner.v`
+    /// or `[2]`, and the values found there. Records of the same type, tuples of the same length
+    /// and arrays of the same length are compared member by member, anything else with
+    /// `eq_value`. The values are equal when there are no differences.
+    pub fn differences(&self, other: &ConstValue) -> Vec<(String, ConstValue, ConstValue)> {
+        let mut differences = Vec::new();
+        self.collect_differences(other, String::new(), &mut differences);
+        differences
+    }
+
+    fn collect_differences(
+        &self,
+        other: &ConstValue,
+        path: String,
+        differences: &mut Vec<(String, ConstValue, ConstValue)>,
+    ) {
+        match (&self.kind, &other.kind) {
+            (
+                ConstValueKind::RecordInstance {
+                    rf: l_rf,
+                    members: l_members,
+                    ..
+                },
+                ConstValueKind::RecordInstance {
+                    rf: r_rf,
+                    members: r_members,
+                    ..
+                },
+            ) if l_rf == r_rf => {
+                for (name, l) in l_members.iter() {
+                    if let Some(r) = r_members.get(name) {
+                        l.collect_differences(r, format!("{path}.{name}"), differences);
+                    }
+                }
+            }
+            (ConstValueKind::Tuple(l, _), ConstValueKind::Tuple(r, _)) if l.len() == r.len() => {
+                for (i, (l, r)) in l.iter().zip(r).enumerate() {
+                    l.collect_differences(r, format!("{path}.{i}"), differences);
+                }
+            }
+            (ConstValueKind::Array(l), ConstValueKind::Array(r)) if l.len() == r.len() => {
+                for (i, (l, r)) in l.iter().zip(r.iter()).enumerate() {
+                    l.collect_differences(r, format!("{path}[{i}]"), differences);
+                }
+            }
+            _ if !self.eq_value(other) => differences.push((path, self.clone(), other.clone())),
+            _ => (),
+        }
+    }
+
+    /// Hashes the value so that values which are `eq_value` to each other hash the same. Numbers
+    /// are hashed by value, so an integer hashes the same as a float equal to it.
+    pub fn hash_value<H: Hasher>(&self, state: &mut H) {
+        match &self.kind {
+            ConstValueKind::Integer { .. } => {
+                hash_number(self.integer_value().unwrap_or_default() as f64, state)
+            }
+            ConstValueKind::Float { value } => hash_number(*value, state),
+            ConstValueKind::Bool(value) => value.hash(state),
+            ConstValueKind::String { string } => string.hash(state),
+            ConstValueKind::Tuple(values, _) => {
+                values.len().hash(state);
+                for value in values {
+                    value.hash_value(state);
+                }
+            }
+            // Everything else is only equal to itself by kind, if at all
+            kind => std::mem::discriminant(kind).hash(state),
+        }
+    }
+
+    /// The value inside an optional, or the value itself if it isn't optional. Null values
+    /// aren't unwrapped.
+    pub fn unwrap_optional(self) -> ConstValue {
+        match (self.ty, self.kind) {
+            (Type::Optional(ty), kind) if !matches!(kind, ConstValueKind::Null) => {
+                ConstValue { ty: *ty, kind }
+            }
+            (ty, kind) => ConstValue { ty, kind },
+        }
+    }
+
+    /// Iterates the elements of an array or the integers of a range, or returns `None` if the
+    /// value isn't iterable. Ranges produce their integers as they're iterated.
+    pub fn into_elements(self) -> Option<ConstValueIter> {
+        match (self.kind, self.ty) {
+            (ConstValueKind::Array(values), _) => Some(ConstValueIter::Array(
+                Arc::unwrap_or_clone(values).into_iter(),
+            )),
+            (
+                ConstValueKind::Range {
+                    start,
+                    end,
+                    inclusive,
+                },
+                Type::Range(ty),
+            ) => {
+                let start = integer_of_type(start, &ty);
+                let end = integer_of_type(end, &ty) + inclusive as i128;
+                Some(ConstValueIter::Range {
+                    range: start..end,
+                    ty: *ty,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Formats the value like [`Display`], but prints floats with `precision` digits after the
+    /// decimal point when one is given. `None` keeps the shortest round-trip form.
+    pub fn to_display_string(&self, precision: Option<usize>) -> String {
+        match (&self.kind, precision) {
+            (ConstValueKind::Float { value }, Some(precision)) => format!("{value:.precision$}"),
+            (ConstValueKind::Tuple(list, _), Some(_)) if !list.is_empty() => list
+                .iter()
+                .map(|item| item.to_display_string(precision))
+                .intersperse(", ".to_string())
+                .collect(),
+            (ConstValueKind::Array(list), Some(_)) => {
+                let items = list
+                    .iter()
+                    .map(|item| item.to_display_string(precision))
+                    .intersperse(", ".to_string())
+                    .collect::<String>();
+                format!("[{items}]")
+            }
+            (ConstValueKind::RecordInstance { members, .. }, Some(_)) if !members.is_empty() => {
+                let members = members
+                    .iter()
+                    .map(|(name, value)| format!("{name}: {}", value.to_display_string(precision)))
+                    .intersperse(", ".to_string())
+                    .collect::<String>();
+                format!("{{ {members} }}")
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// Formats the value as directed by an interpolation's format specifier, or `None` if the
+    /// specifier can't be applied to its type. Any value can be padded to a width, but zero
+    /// padding is only valid for numbers and a precision only for floats. Numbers are aligned to
+    /// the right by default and everything else to the left.
+    pub fn to_formatted_string(
+        &self,
+        spec: &FormatSpec,
+        precision: Option<usize>,
+    ) -> Option<String> {
+        let text = match (&self.kind, &self.ty) {
+            (ConstValueKind::Float { value }, _) => match spec.precision.or(precision) {
+                Some(precision) => format!("{value:.precision$}"),
+                None => value.to_string(),
+            },
+            _ if spec.precision.is_some() => return None,
+            (ConstValueKind::Integer { .. }, _) => self.integer_value()?.to_string(),
+            _ if spec.zero => return None,
+            _ => self.to_display_string(precision),
+        };
+
+        let pad = spec.width.unwrap_or(0).saturating_sub(text.chars().count());
+        if pad == 0 {
+            return Some(text);
+        }
+        if spec.zero {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            return Some(format!("{sign}{}{digits}", "0".repeat(pad)));
+        }
+
+        let number = matches!(
+            self.kind,
+            ConstValueKind::Integer { .. } | ConstValueKind::Float { .. }
+        );
+        let align = spec.align.unwrap_or(if number {
+            Alignment::Right
+        } else {
+            Alignment::Left
+        });
+        let (before, after) = match align {
+            Alignment::Left => (0, pad),
+            Alignment::Right => (pad, 0),
+            Alignment::Center => (pad / 2, pad - pad / 2),
+        };
+        Some(format!("{}{text}{}", " ".repeat(before), " ".repeat(after)))
+    }
+
+    /// Copies the value into an [`OwnedData`] that holds no `Rf` handles or function bodies, so
+    /// it can be handed across an FFI or serialization boundary.
+    pub fn to_owned_data(&self) -> Result<OwnedData, OwnedDataError> {
+        if let Type::Symbol(_) = &self.ty {
+            return Err(OwnedDataError::Symbol);
+        }
+
+        let data = match &self.kind {
+            ConstValueKind::Empty => OwnedData::Empty,
+            ConstValueKind::Null => OwnedData::Null,
+            ConstValueKind::Integer { value } => match &self.ty {
+                Type::Integer { width, signed } => OwnedData::Integer {
+                    value: *value,
+                    width: Some(*width),
+                    signed: *signed,
+                },
+                // Untyped integers are signed
+                _ => OwnedData::Integer {
+                    value: *value,
+                    width: None,
+                    signed: true,
+                },
+            },
+            ConstValueKind::Float { value } => match &self.ty {
+                Type::Float { width } => OwnedData::Float {
+                    value: *value,
+                    width: Some(*width),
+                },
+                _ => OwnedData::Float {
+                    value: *value,
+                    width: None,
+                },
+            },
+            ConstValueKind::Bool(value) => OwnedData::Bool(*value),
+            ConstValueKind::String { string } => OwnedData::String(string.clone()),
+            ConstValueKind::Function { .. }
+            | ConstValueKind::NativeFunction { .. }
+            | ConstValueKind::Native { .. } => return Err(OwnedDataError::Function),
+            ConstValueKind::Thunk { .. } => return Err(OwnedDataError::Thunk),
+            ConstValueKind::Ref { .. } => return Err(OwnedDataError::Ref),
+            ConstValueKind::Tuple(values, _) => OwnedData::Tuple(
+                values
+                    .iter()
+                    .map(ConstValue::to_owned_data)
+                    .collect::<Result<_, _>>()?,
+            ),
+            ConstValueKind::Array(values) => OwnedData::Array(
+                values
+                    .iter()
+                    .map(ConstValue::to_owned_data)
+                    .collect::<Result<_, _>>()?,
+            ),
+            ConstValueKind::Range {
+                start,
+                end,
+                inclusive,
+            } => OwnedData::Range {
+                start: *start,
+                end: *end,
+                inclusive: *inclusive,
+            },
+            ConstValueKind::RecordInstance { rf, members, .. } => {
+                let name = match &rf.borrow().value {
+                    ScopeValue::Record { ident, .. } => Some(ident.clone()),
+                    _ => None,
+                };
+                let members = members
+                    .iter()
+                    .map(|(name, value)| Ok((name.clone(), value.to_owned_data()?)))
+                    .collect::<Result<_, _>>()?;
+
+                OwnedData::Record { name, members }
+            }
+        };
+
+        Ok(data)
+    }
+}
+
+/// The value reached by following the record fields in `path` from a symbol's value
+fn resolve_path<'a>(value: &'a ScopeValue, path: &[String]) -> Option<&'a ConstValue> {
+    let ScopeValue::ConstValue(value) = value else {
+        return None;
+    };
+
+    path.iter()
+        .try_fold(value, |value, field| match &value.kind {
+            ConstValueKind::RecordInstance { members, .. } => members.get(field),
+            _ => None,
+        })
+}
+
+/// Reads the bits of an integer of type `ty`, which are sign extended when `ty` is signed
+fn integer_of_type(value: u64, ty: &Type) -> i128 {
+    match ty {
+        Type::Integer { signed: true, .. } | Type::CoercibleInteger => value as i64 as i128,
+        _ => value as i128,
+    }
+}
+
+/// Iterator returned by [`ConstValue::into_elements`]
+pub enum ConstValueIter {
+    Array(std::vec::IntoIter<ConstValue>),
+    Range {
+        range: std::ops::Range<i128>,
+        ty: Type,
+    },
+}
+
+impl Iterator for ConstValueIter {
+    type Item = ConstValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            ConstValueIter::Array(values) => values.next(),
+            ConstValueIter::Range { range, ty } => range.next().map(|value| ConstValue {
+                ty: ty.clone(),
+                kind: ConstValueKind::Integer {
+                    value: value as u64,
+                },
+            }),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            ConstValueIter::Array(values) => values.size_hint(),
+            ConstValueIter::Range { range, .. } => range.size_hint(),
+        }
+    }
+}
+
+/// Plain data representation of a [`ConstValue`].
+///
+/// Integers and floats keep their width when they have one; coercible literals have a width of
+/// `None`. Records keep the name of their record type, which function return values don't have.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedData {
+    Empty,
+    Null,
+    Integer {
+        value: u64,
+        width: Option<u8>,
+        signed: bool,
+    },
+    Float {
+        value: f64,
+        width: Option<u8>,
+    },
+    Bool(bool),
+    String(String),
+    Tuple(Vec<OwnedData>),
+    Array(Vec<OwnedData>),
+    Range {
+        start: u64,
+        end: u64,
+        inclusive: bool,
+    },
+    Record {
+        name: Option<String>,
+        members: LinkedHashMap<String, OwnedData>,
+    },
+}
+
+/// Why two values couldn't be merged with [`ConstValue::merge`]
+#[derive(Debug, Clone)]
+pub enum MergeError {
+    /// One of the values isn't a record instance
+    NotRecord(Type),
+    /// A field in both records has a different type in each
+    FieldMismatch {
+        field: String,
+        base: Type,
+        other: Type,
+    },
+}
+
+/// Why a value couldn't be converted with [`ConstValue::convert`]
+#[derive(Debug, Clone)]
+pub enum ConvertError {
+    /// The value or the type it's converted to isn't a record
+    NotRecord(Type),
+    /// A field in both records has a type that can't be cast to the new one
+    FieldMismatch {
+        field: String,
+        expected: Type,
+        found: Type,
+    },
+    /// A field only in the new record has a type without a default value
+    NoDefaultValue(Type),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedDataError {
+    /// Functions reference their body and declaring scope
+    Function,
+    /// Record types are symbols in the scope tree rather than data
+    Symbol,
+    /// Thunks hold an unevaluated expression and its scopes
+    Thunk,
+    /// References point at a symbol in the scope tree
+    Ref,
+}
+
+impl Display for OwnedDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OwnedDataError::Function => f.write_str("functions cannot be converted to owned data"),
+            OwnedDataError::Symbol => f.write_str("symbols cannot be converted to owned data"),
+            OwnedDataError::Thunk => f.write_str("thunks cannot be converted to owned data"),
+            OwnedDataError::Ref => f.write_str("references cannot be converted to owned data"),
+        }
+    }
+}
+
+impl std::error::Error for OwnedDataError {}
+
+/// Hashes a number the way it's compared, where `0.0` and `-0.0` are equal
+fn hash_number<H: Hasher>(value: f64, state: &mut H) {
+    let value = if value == 0.0 { 0.0 } else { value };
+    value.to_bits().hash(state);
 }
 
 impl Display for ConstValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(&self.kind, f)
+        if let Some(value) = self.integer_value() {
+            return write!(f, "{value}");
+        }
+        match (&self.kind, self.range_bounds()) {
+            (
+                ConstValueKind::Range {
+                    inclusive: true, ..
+                },
+                Some((start, end)),
+            ) => {
+                write!(f, "{start}..={}", end - 1)
+            }
+            (ConstValueKind::Range { .. }, Some((start, end))) => write!(f, "{start}..{end}"),
+            _ => std::fmt::Display::fmt(&self.kind, f),
+        }
     }
 }
 
@@ -23,10 +23,19 @@ impl Display for ErrorLevel {
     }
 }
 
+/// A function call that was in progress when an error occurred
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub name: String,
+    pub range: Range,
+}
+
 #[derive(Debug, Clone)]
 pub struct EvaluationError {
     pub kind: EvaluationErrorKind,
     pub range: Range,
+    /// Calls leading to the error, outermost first
+    pub backtrace: Option<Vec<CallFrame>>,
 }
 
 impl Display for EvaluationError {
@@ -76,6 +85,19 @@ impl EvaluationError {
                 note
             )
         }
+        if let Some(backtrace) = &self.backtrace {
+            for frame in backtrace.iter().rev() {
+                println!(
+                    "{:>padding$} {} in call to `{}` at {}:{}:{}",
+                    "=".bold().blue(),
+                    "note:".bold().bright_white(),
+                    frame.name.bold(),
+                    file_path,
+                    frame.range.start.line_num + 1,
+                    frame.range.start.position + 1
+                )
+            }
+        }
     }
 }
 
@@ -88,14 +110,75 @@ pub enum TypeHint {
     ReturnParameter,
     Function,
     Record,
+    Element,
+    Condition,
+    Operand,
+    Pattern,
+}
+
+/// Why a function marked `pure`, or the initializer of a `const`, isn't
+#[derive(Debug, Clone)]
+pub enum Impurity {
+    /// Assigns to a binding declared outside the function
+    OuterAssignment(String),
+    /// Calls a function that isn't pure, including native functions
+    ImpureCall(String),
+    /// Assigns through a reference, which may point outside the function
+    RefAssignment,
 }
 
 #[derive(Debug, Clone)]
 pub enum EvaluationErrorKind {
     TypeMismatch(Type, Type, TypeHint),
     ArgCountMismatch(u8, u8),
-    NotInitialized { hint: TypeHint },
+    NotInitialized {
+        hint: TypeHint,
+    },
     BinExpMismatch(Operator, Type, Type),
+    IncomparableTypes(Type, Type),
+    ExpectedSizedInteger(Type),
+    InvalidTupleIndex(u64, Type),
+    InvalidIndex(Type, Type),
+    IndexOutOfBounds(String, usize),
+    NotCharBoundary(usize),
+    NotCallable(Type),
+    ForwardReference(String),
+    ImpureFunction(String, Impurity),
+    NoDefaultValue(Type),
+    NullAccess(Type),
+    IntegerOverflow(String, Type),
+    ArrayLengthMismatch {
+        expected: usize,
+        found: usize,
+    },
+    PreconditionFailed,
+    NotIterable(Type),
+    PatternMismatch(Type),
+    ExpectedRecord(Type),
+    MissingCleanup(Type),
+    NotAReference(Type),
+    NotAPlace,
+    InvalidRadix(i64),
+    NegativeCount(i64),
+    AssignToImmutable(String),
+    InvalidTupleLabel(String, Type),
+    IterationLimitExceeded(usize),
+    DivisionByZero,
+    NonConstInitializer(String, Impurity),
+    MissingField(String, Type),
+    InvalidFormatSpec(String, Type),
+    ReturnOutsideFunction,
+    UnexpectedReturnValue(usize),
+    RecursionLimitExceeded(usize),
+    Panic(String),
+    UnaryExpMismatch(Operator, Type),
+    ImmutableMember(String),
+    /// Path to each place the values differ, and the value found there in each
+    AssertionFailed(Vec<(String, String, String)>),
+    ExpectedIntegerType,
+    UnknownField(String, String),
+    /// No arm of a `match` matched the value, which is displayed along with its type
+    NonExhaustiveMatch(String, Type),
     SymbolNotFound(String),
 }
 
@@ -105,6 +188,45 @@ impl EvaluationErrorKind {
             EvaluationErrorKind::TypeMismatch(_, _, _) => ErrorLevel::Error,
             EvaluationErrorKind::ArgCountMismatch(_, _) => ErrorLevel::Error,
             EvaluationErrorKind::BinExpMismatch(_, _, _) => ErrorLevel::Error,
+            EvaluationErrorKind::IncomparableTypes(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::ExpectedSizedInteger(_) => ErrorLevel::Error,
+            EvaluationErrorKind::InvalidTupleIndex(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::InvalidIndex(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::IndexOutOfBounds(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::NotCharBoundary(_) => ErrorLevel::Error,
+            EvaluationErrorKind::NotCallable(_) => ErrorLevel::Error,
+            EvaluationErrorKind::ForwardReference(_) => ErrorLevel::Error,
+            EvaluationErrorKind::ImpureFunction(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::NoDefaultValue(_) => ErrorLevel::Error,
+            EvaluationErrorKind::NullAccess(_) => ErrorLevel::Error,
+            EvaluationErrorKind::IntegerOverflow(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::ArrayLengthMismatch { .. } => ErrorLevel::Error,
+            EvaluationErrorKind::PreconditionFailed => ErrorLevel::Error,
+            EvaluationErrorKind::NotIterable(_) => ErrorLevel::Error,
+            EvaluationErrorKind::PatternMismatch(_) => ErrorLevel::Error,
+            EvaluationErrorKind::ExpectedRecord(_) => ErrorLevel::Error,
+            EvaluationErrorKind::MissingCleanup(_) => ErrorLevel::Error,
+            EvaluationErrorKind::NotAReference(_) => ErrorLevel::Error,
+            EvaluationErrorKind::NotAPlace => ErrorLevel::Error,
+            EvaluationErrorKind::InvalidRadix(_) => ErrorLevel::Error,
+            EvaluationErrorKind::NegativeCount(_) => ErrorLevel::Error,
+            EvaluationErrorKind::AssignToImmutable(_) => ErrorLevel::Error,
+            EvaluationErrorKind::InvalidTupleLabel(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::IterationLimitExceeded(_) => ErrorLevel::Error,
+            EvaluationErrorKind::DivisionByZero => ErrorLevel::Error,
+            EvaluationErrorKind::NonConstInitializer(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::MissingField(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::InvalidFormatSpec(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::ReturnOutsideFunction => ErrorLevel::Error,
+            EvaluationErrorKind::UnexpectedReturnValue(_) => ErrorLevel::Error,
+            EvaluationErrorKind::RecursionLimitExceeded(_) => ErrorLevel::Error,
+            EvaluationErrorKind::Panic(_) => ErrorLevel::Error,
+            EvaluationErrorKind::UnaryExpMismatch(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::ImmutableMember(_) => ErrorLevel::Error,
+            EvaluationErrorKind::AssertionFailed(_) => ErrorLevel::Error,
+            EvaluationErrorKind::ExpectedIntegerType => ErrorLevel::Error,
+            EvaluationErrorKind::UnknownField(_, _) => ErrorLevel::Error,
+            EvaluationErrorKind::NonExhaustiveMatch(_, _) => ErrorLevel::Error,
             EvaluationErrorKind::SymbolNotFound(_) => ErrorLevel::Error,
 
             EvaluationErrorKind::NotInitialized { .. } => ErrorLevel::Warning,
@@ -122,8 +244,9 @@ impl EvaluationErrorKind {
             }
             Self::ArgCountMismatch(found, expected) => {
                 vec![format!(
-                    "expected: `{}` found: `{}`",
+                    "expected `{}` argument{}, found `{}`",
                     expected.to_string().bold(),
+                    if *expected == 1 { "" } else { "s" },
                     found.to_string().bold()
                 )]
             }
@@ -150,6 +273,278 @@ impl EvaluationErrorKind {
                     r.to_string().bold()
                 )]
             }
+            Self::IncomparableTypes(l, r) => {
+                vec![format!(
+                    "values of type `{}` and `{}` cannot be compared",
+                    l.to_string().bold(),
+                    r.to_string().bold()
+                )]
+            }
+            Self::ExpectedSizedInteger(ty) => {
+                vec![format!(
+                    "expected an integer with a declared width, found `{}`",
+                    ty.to_string().bold()
+                )]
+            }
+            Self::InvalidTupleIndex(index, Type::Tuple(types)) => {
+                vec![format!(
+                    "index `{}` is out of range for a tuple of {} elements",
+                    index.to_string().bold(),
+                    types.len()
+                )]
+            }
+            Self::InvalidTupleIndex(_, ty) => {
+                vec![format!("type `{}` is not a tuple", ty.to_string().bold())]
+            }
+            Self::InvalidIndex(ty, index) => {
+                vec![format!(
+                    "cannot index `{}` with `{}`",
+                    ty.to_string().bold(),
+                    index.to_string().bold()
+                )]
+            }
+            Self::IndexOutOfBounds(index, len) => {
+                vec![format!(
+                    "index `{}` is out of bounds for length {}",
+                    index.bold(),
+                    len
+                )]
+            }
+            Self::NotCharBoundary(offset) => {
+                vec![format!(
+                    "byte `{}` is in the middle of a character",
+                    offset.to_string().bold()
+                )]
+            }
+            Self::NotCallable(ty) => {
+                vec![format!(
+                    "value of type `{}` is not a function or record",
+                    ty.to_string().bold()
+                )]
+            }
+            Self::ForwardReference(field) => {
+                vec![format!(
+                    "field `{}` is used before it's initialized",
+                    field.bold()
+                )]
+            }
+            Self::ImpureFunction(func, Impurity::OuterAssignment(name)) => {
+                vec![format!(
+                    "pure function `{}` assigns to `{}` from an outer scope",
+                    func.bold(),
+                    name.bold()
+                )]
+            }
+            Self::ImpureFunction(func, Impurity::RefAssignment) => {
+                vec![format!(
+                    "pure function `{}` assigns through a reference",
+                    func.bold()
+                )]
+            }
+            Self::ImpureFunction(func, Impurity::ImpureCall(name)) => {
+                vec![format!(
+                    "pure function `{}` calls `{}`, which isn't pure",
+                    func.bold(),
+                    name.bold()
+                )]
+            }
+            Self::NoDefaultValue(ty) => {
+                vec![format!(
+                    "type `{}` has no default value",
+                    ty.to_string().bold()
+                )]
+            }
+            Self::NullAccess(ty) => {
+                vec![format!(
+                    "value of type `{}` is null; use `?.` to access it conditionally",
+                    ty.to_string().bold()
+                )]
+            }
+            Self::IntegerOverflow(value, ty) => {
+                vec![format!(
+                    "value `{}` doesn't fit in `{}`",
+                    value.bold(),
+                    ty.to_string().bold()
+                )]
+            }
+            Self::ArrayLengthMismatch { expected, found } => {
+                vec![format!(
+                    "expected an array of {} elements, found {}",
+                    expected.to_string().bold(),
+                    found.to_string().bold()
+                )]
+            }
+            Self::PreconditionFailed => {
+                vec![format!(
+                    "`{}` condition evaluated to `{}`",
+                    "requires".bold(),
+                    "false".bold()
+                )]
+            }
+            Self::NotIterable(ty) => {
+                vec![format!(
+                    "value of type `{}` is not an array or range",
+                    ty.to_string().bold()
+                )]
+            }
+            Self::PatternMismatch(ty) => {
+                vec![format!(
+                    "value of type `{}` doesn't match the pattern",
+                    ty.to_string().bold()
+                )]
+            }
+            Self::ExpectedRecord(ty) => {
+                vec![format!(
+                    "value of type `{}` is not a record instance",
+                    ty.to_string().bold()
+                )]
+            }
+            Self::MissingCleanup(ty) => {
+                vec![format!(
+                    "value of type `{}` has no `{}` method to clean it up",
+                    ty.to_string().bold(),
+                    "close".bold()
+                )]
+            }
+            Self::NotAReference(ty) => {
+                vec![format!(
+                    "value of type `{}` is not a reference",
+                    ty.to_string().bold()
+                )]
+            }
+            Self::NotAPlace => {
+                vec!["only bindings and their fields can be referenced".to_string()]
+            }
+            Self::InvalidRadix(radix) => {
+                vec![format!(
+                    "radix `{}` isn't between 2 and 36",
+                    radix.to_string().bold()
+                )]
+            }
+            Self::NegativeCount(count) => {
+                vec![format!(
+                    "count `{}` can't be negative",
+                    count.to_string().bold()
+                )]
+            }
+            Self::AssignToImmutable(name) => {
+                vec![format!(
+                    "`{}` is declared `{}` and can't be assigned to",
+                    name.bold(),
+                    "const".bold()
+                )]
+            }
+            Self::InvalidTupleLabel(label, ty) => {
+                vec![format!(
+                    "tuple `{}` has no element labeled `{}`",
+                    ty.to_string().bold(),
+                    label.bold()
+                )]
+            }
+            Self::IterationLimitExceeded(limit) => {
+                vec![format!(
+                    "loop would run more than {} times",
+                    limit.to_string().bold()
+                )]
+            }
+            Self::DivisionByZero => {
+                vec!["the right side of the operation is zero".to_string()]
+            }
+            Self::NonConstInitializer(name, Impurity::OuterAssignment(outer)) => {
+                vec![format!(
+                    "initializer of constant `{}` assigns to `{}`",
+                    name.bold(),
+                    outer.bold()
+                )]
+            }
+            Self::NonConstInitializer(name, Impurity::RefAssignment) => {
+                vec![format!(
+                    "initializer of constant `{}` assigns through a reference",
+                    name.bold()
+                )]
+            }
+            Self::NonConstInitializer(name, Impurity::ImpureCall(func)) => {
+                vec![format!(
+                    "initializer of constant `{}` calls `{}`, which isn't pure",
+                    name.bold(),
+                    func.bold()
+                )]
+            }
+            Self::MissingField(field, ty) => {
+                vec![format!(
+                    "record `{}` has no field `{}`",
+                    ty.to_string().bold(),
+                    field.bold()
+                )]
+            }
+            Self::InvalidFormatSpec(spec, ty) => {
+                vec![format!(
+                    "`{}` can't be used to format a value of type `{}`",
+                    spec.bold(),
+                    ty.to_string().bold()
+                )]
+            }
+            Self::ReturnOutsideFunction => {
+                vec![format!(
+                    "`{}` can only be used inside a function",
+                    "return".bold()
+                )]
+            }
+            Self::UnexpectedReturnValue(count) => {
+                vec![format!(
+                    "a value can only be returned from a function with one return parameter, \
+                     this one has {}",
+                    count.to_string().bold()
+                )]
+            }
+            Self::RecursionLimitExceeded(limit) => {
+                vec![format!(
+                    "calls would be nested more than {} deep",
+                    limit.to_string().bold()
+                )]
+            }
+            Self::Panic(message) => vec![format!("panicked with `{}`", message.bold())],
+            Self::UnaryExpMismatch(o, ty) => vec![format!(
+                "cannot apply operator `{}` to type `{}`",
+                o.as_str().to_string().bold(),
+                ty.to_string().bold()
+            )],
+            Self::ImmutableMember(name) => vec![format!(
+                "`{}` belongs to a frozen record and can't be assigned to",
+                name.bold()
+            )],
+            Self::AssertionFailed(differences) => differences
+                .iter()
+                .map(|(path, left, right)| {
+                    let values = format!("`{}` != `{}`", left.bold(), right.bold());
+                    if path.is_empty() {
+                        values
+                    } else {
+                        format!("at `{}`: {values}", path.bold())
+                    }
+                })
+                .collect(),
+            Self::ExpectedIntegerType => vec![format!(
+                "expected one of `{}`",
+                "i8, i16, i32, i64, u8, u16, u32, u64".bold()
+            )],
+            Self::UnknownField(field, record) => {
+                vec![format!(
+                    "record `{}` has no field `{}`",
+                    record.bold(),
+                    field.bold()
+                )]
+            }
+            Self::NonExhaustiveMatch(value, ty) => {
+                vec![
+                    format!(
+                        "no arm matches `{}` of type `{}`",
+                        value.bold(),
+                        ty.to_string().bold()
+                    ),
+                    format!("add a `{}` arm to match anything else", "_".bold()),
+                ]
+            }
             Self::SymbolNotFound(sym) => {
                 vec![format!("symbol `{}` not found in scope", sym.bold(),)]
             }
@@ -173,12 +568,99 @@ impl Display for EvaluationErrorKind {
             Self::TypeMismatch(_, _, TypeHint::Function) => {
                 f.write_str(&"function type mismatch".bold().bright_white())
             }
+            Self::TypeMismatch(_, _, TypeHint::Element) => {
+                f.write_str(&"element type mismatch".bold().bright_white())
+            }
+            Self::TypeMismatch(_, _, TypeHint::Condition) => {
+                f.write_str(&"condition type mismatch".bold().bright_white())
+            }
+            Self::TypeMismatch(_, _, TypeHint::Operand) => {
+                f.write_str(&"operand type mismatch".bold().bright_white())
+            }
+            Self::TypeMismatch(_, _, TypeHint::Pattern) => {
+                f.write_str(&"pattern type mismatch".bold().bright_white())
+            }
             Self::TypeMismatch(_, _, _) => f.write_str(&"type mismatch".bold().bright_white()),
-            Self::ArgCountMismatch(_, _) => f.write_str(&"type mismatch".bold().bright_white()),
+            Self::ArgCountMismatch(_, _) => {
+                f.write_str(&"argument count mismatch".bold().bright_white())
+            }
             Self::NotInitialized { .. } => f.write_str(&"never initialized".bold().bright_white()),
             Self::BinExpMismatch { .. } => {
                 f.write_str(&"operation cannot be evaluated".bold().bright_white())
             }
+            Self::IncomparableTypes(_, _) => {
+                f.write_str(&"incomparable types".bold().bright_white())
+            }
+            Self::ExpectedSizedInteger(_) => {
+                f.write_str(&"expected sized integer".bold().bright_white())
+            }
+            Self::InvalidTupleIndex(_, _) => {
+                f.write_str(&"invalid tuple index".bold().bright_white())
+            }
+            Self::InvalidIndex(_, _) => f.write_str(&"invalid index".bold().bright_white()),
+            Self::IndexOutOfBounds(_, _) => {
+                f.write_str(&"index out of bounds".bold().bright_white())
+            }
+            Self::NotCharBoundary(_) => f.write_str(&"not a char boundary".bold().bright_white()),
+            Self::NotCallable(_) => f.write_str(&"not callable".bold().bright_white()),
+            Self::ForwardReference(_) => f.write_str(&"forward reference".bold().bright_white()),
+            Self::ImpureFunction(_, _) => f.write_str(&"impure function".bold().bright_white()),
+            Self::NoDefaultValue(_) => f.write_str(&"no default value".bold().bright_white()),
+            Self::NullAccess(_) => f.write_str(&"null access".bold().bright_white()),
+            Self::IntegerOverflow(_, _) => f.write_str(&"integer overflow".bold().bright_white()),
+            Self::ArrayLengthMismatch { .. } => {
+                f.write_str(&"array length mismatch".bold().bright_white())
+            }
+            Self::PreconditionFailed => f.write_str(&"precondition failed".bold().bright_white()),
+            Self::NotIterable(_) => f.write_str(&"not iterable".bold().bright_white()),
+            Self::PatternMismatch(_) => f.write_str(&"pattern mismatch".bold().bright_white()),
+            Self::ExpectedRecord(_) => f.write_str(&"expected record".bold().bright_white()),
+            Self::MissingCleanup(_) => f.write_str(&"missing cleanup".bold().bright_white()),
+            Self::NotAReference(_) => f.write_str(&"not a reference".bold().bright_white()),
+            Self::NotAPlace => f.write_str(&"invalid reference".bold().bright_white()),
+            Self::InvalidRadix(_) => f.write_str(&"invalid radix".bold().bright_white()),
+            Self::NegativeCount(_) => f.write_str(&"negative count".bold().bright_white()),
+            Self::AssignToImmutable(_) => {
+                f.write_str(&"assignment to constant".bold().bright_white())
+            }
+            Self::InvalidTupleLabel(_, _) => {
+                f.write_str(&"invalid tuple label".bold().bright_white())
+            }
+            Self::IterationLimitExceeded(_) => {
+                f.write_str(&"iteration limit exceeded".bold().bright_white())
+            }
+            Self::DivisionByZero => f.write_str(&"division by zero".bold().bright_white()),
+            Self::NonConstInitializer(_, _) => {
+                f.write_str(&"non-constant initializer".bold().bright_white())
+            }
+            Self::MissingField(_, _) => f.write_str(&"missing field".bold().bright_white()),
+            Self::InvalidFormatSpec(_, _) => {
+                f.write_str(&"invalid format specifier".bold().bright_white())
+            }
+            Self::ReturnOutsideFunction => {
+                f.write_str(&"return outside of function".bold().bright_white())
+            }
+            Self::UnexpectedReturnValue(_) => {
+                f.write_str(&"unexpected return value".bold().bright_white())
+            }
+            Self::RecursionLimitExceeded(_) => {
+                f.write_str(&"recursion limit exceeded".bold().bright_white())
+            }
+            Self::Panic(_) => f.write_str(&"panic".bold().bright_white()),
+            Self::UnaryExpMismatch(_, _) => {
+                f.write_str(&"operation cannot be evaluated".bold().bright_white())
+            }
+            Self::ImmutableMember(_) => {
+                f.write_str(&"assignment to frozen record".bold().bright_white())
+            }
+            Self::AssertionFailed(_) => f.write_str(&"assertion failed".bold().bright_white()),
+            Self::ExpectedIntegerType => {
+                f.write_str(&"expected integer type".bold().bright_white())
+            }
+            Self::UnknownField(_, _) => f.write_str(&"unknown field".bold().bright_white()),
+            Self::NonExhaustiveMatch(_, _) => {
+                f.write_str(&"non-exhaustive match".bold().bright_white())
+            }
             Self::SymbolNotFound(_) => f.write_str("symbol not found"),
         }
     }
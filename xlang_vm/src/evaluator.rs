@@ -1,25 +1,148 @@
-use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    io::Write,
+    sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
 
 use linked_hash_map::LinkedHashMap;
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::UnicodeSegmentation;
 use xlang_core::{
     ast::{
-        ArgList, AstNode, Expression, ParamaterList, ParsedTemplate, ParsedTemplateString,
-        Statement,
+        ArgList, AstNode, Expression, MatchArm, NodeId, ParamaterList, ParsedTemplate,
+        ParsedTemplateString, Pattern, PunctuationList, Statement,
     },
     token::{Operator, Range, SpannedToken, Token},
     Module,
 };
-use xlang_util::format::TreeDisplay;
+use xlang_util::{format::TreeDisplay, Rf};
 
 use crate::{
-    const_value::{ConstValue, ConstValueKind, Type},
-    error::{EvaluationError, EvaluationErrorKind, TypeHint},
-    scope::{ScopeManager, ScopeValue},
+    const_value::{ConstValue, ConstValueKind, ConvertError, MergeError, NativeCallback, Type},
+    error::{CallFrame, EvaluationError, EvaluationErrorKind, Impurity, TypeHint},
+    scope::{Scope, ScopeManager, ScopeValue},
 };
 
+/// Stack left when a function body is evaluated on a new segment of stack
+const CALL_STACK_RED_ZONE: usize = 1024 * 1024;
+/// Size of each new segment of stack for function bodies
+const CALL_STACK_SEGMENT: usize = 8 * 1024 * 1024;
+
+/// Control flow that unwinds the statements being evaluated instead of continuing after them
+#[derive(Clone)]
+pub enum Flow {
+    /// `return`, with the value given for the function's return parameter if there was one
+    Return {
+        value: Option<Box<ConstValue>>,
+        range: Range,
+    },
+    /// `panic(msg)` or `unreachable()`, which unwinds every call up to the statement of the
+    /// module being evaluated
+    Panic,
+}
+
+/// What each position in a string counts when measuring or indexing it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StringMode {
+    /// UTF-8 bytes. Indexing that splits a character is an error.
+    Bytes,
+    /// Unicode scalar values
+    #[default]
+    Chars,
+    /// Extended grapheme clusters, what's usually seen as one character
+    #[cfg(feature = "graphemes")]
+    Graphemes,
+}
+
+impl StringMode {
+    pub fn len(self, string: &str) -> usize {
+        match self {
+            Self::Bytes => string.len(),
+            Self::Chars => string.chars().count(),
+            #[cfg(feature = "graphemes")]
+            Self::Graphemes => string.graphemes(true).count(),
+        }
+    }
+
+    /// The part of `string` from position `start` up to `end`, or the byte offset that isn't on
+    /// a character boundary
+    pub fn slice(self, string: &str, start: usize, end: usize) -> Result<String, usize> {
+        match self {
+            Self::Bytes => string.get(start..end).map(str::to_string).ok_or_else(|| {
+                if string.is_char_boundary(start) {
+                    end
+                } else {
+                    start
+                }
+            }),
+            Self::Chars => Ok(string.chars().skip(start).take(end - start).collect()),
+            #[cfg(feature = "graphemes")]
+            Self::Graphemes => Ok(string
+                .graphemes(true)
+                .skip(start)
+                .take(end - start)
+                .collect()),
+        }
+    }
+}
+
+/// Whether `value.name` calls a method that takes just `value`, as if it were `value.name()`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyCalls {
+    /// `value.name` only accesses a member
+    #[default]
+    Disabled,
+    /// Methods are only called when `value` has no member `name`, so a record member holding a
+    /// function is returned rather than called
+    MembersFirst,
+    /// Methods are called even when `value` has a member with the same name
+    MethodsFirst,
+}
+
 pub struct EvaluatorState {
     pub scope: ScopeManager,
     pub errors: Vec<EvaluationError>,
+    /// Digits printed after the decimal point when displaying floats. `None` prints the shortest
+    /// representation that round-trips. Shared with `print` and `println` like `output`.
+    pub float_precision: Arc<Mutex<Option<usize>>>,
+    /// Calls currently being evaluated, outermost first
+    pub call_stack: Vec<CallFrame>,
+    /// Whether `value[-n]` counts from the end instead of being out of bounds
+    pub negative_indexing: bool,
+    /// Types resolved from type names, keyed by the id of the AST node along with the name it
+    /// was resolved from. Entries for a name are dropped whenever that name is declared, since it
+    /// may now resolve to a different symbol.
+    pub type_cache: HashMap<NodeId, (String, Type)>,
+    /// Whether untyped integers bound by a declaration take the smallest type that fits them.
+    /// This makes the type of a declaration depend on its value, so `a: 5` is an `i8` and
+    /// `a + 200` overflows it. Off by default, where they stay `{integer}` until used with a
+    /// typed value.
+    pub sized_literals: bool,
+    /// How strings are measured by `len` and indexed
+    pub string_mode: StringMode,
+    /// Whether methods can be called like members, without parentheses
+    pub property_calls: PropertyCalls,
+    /// Flags set by the host for `cfg(name)` to check, so one script can target several
+    /// environments
+    pub features: HashSet<String>,
+    /// The most times a `while`, `for` or `repeat` loop can run, or elements builtins like `fill`
+    /// can produce, before it's reported as an error, so evaluation doesn't hang or run out of
+    /// memory
+    pub max_iterations: usize,
+    /// Function bodies currently being evaluated
+    pub call_depth: usize,
+    /// The deepest calls can be nested before they're reported as an error, so recursion without
+    /// a base case is reported rather than running until memory runs out. Function bodies are
+    /// evaluated on a stack that grows as needed, so the host's stack size doesn't matter.
+    pub max_call_depth: usize,
+    /// Set while a `return` or a panic unwinds. Statement lists and loops stop once it's set, and
+    /// no more calls are made. A return is taken by the call it returns from, and a panic by the
+    /// module statement it was raised in.
+    pub flow: Option<Flow>,
+    /// Where `print` and `println` write to. Shared with them so it can be replaced after
+    /// they're registered.
+    pub output: Arc<Mutex<Box<dyn Write + Send>>>,
 }
 
 pub struct Evaluator {
@@ -29,12 +152,155 @@ pub struct Evaluator {
 
 impl Evaluator {
     pub fn new(module: Arc<Module>, scope_manager: ScopeManager) -> Evaluator {
-        Evaluator {
+        let evaluator = Evaluator {
             module,
             state: RwLock::new(EvaluatorState {
                 scope: scope_manager,
                 errors: Vec::new(),
+                float_precision: Arc::new(Mutex::new(None)),
+                call_stack: Vec::new(),
+                negative_indexing: false,
+                type_cache: HashMap::new(),
+                sized_literals: false,
+                string_mode: StringMode::default(),
+                property_calls: PropertyCalls::default(),
+                features: HashSet::new(),
+                max_iterations: 1_000_000,
+                call_depth: 0,
+                max_call_depth: 256,
+                flow: None,
+                output: Arc::new(Mutex::new(Box::new(std::io::stdout()))),
             }),
+        };
+        evaluator.register_print();
+        evaluator.register_constants();
+        evaluator
+    }
+
+    pub fn set_float_precision(&self, precision: Option<usize>) {
+        *self.rstate().float_precision.lock().unwrap() = precision;
+    }
+
+    pub fn float_precision(&self) -> Option<usize> {
+        *self.rstate().float_precision.lock().unwrap()
+    }
+
+    pub fn set_negative_indexing(&self, enabled: bool) {
+        self.wstate().negative_indexing = enabled;
+    }
+
+    pub fn set_sized_literals(&self, enabled: bool) {
+        self.wstate().sized_literals = enabled;
+    }
+
+    pub fn set_max_iterations(&self, max_iterations: usize) {
+        self.wstate().max_iterations = max_iterations;
+    }
+
+    pub fn set_max_call_depth(&self, max_call_depth: usize) {
+        self.wstate().max_call_depth = max_call_depth;
+    }
+
+    pub fn set_string_mode(&self, mode: StringMode) {
+        self.wstate().string_mode = mode;
+    }
+
+    pub fn set_property_calls(&self, property_calls: PropertyCalls) {
+        self.wstate().property_calls = property_calls;
+    }
+
+    /// Sets whether the flag `name` is on for `cfg(name)`
+    pub fn set_feature(&self, name: &str, enabled: bool) {
+        let features = &mut self.wstate().features;
+        if enabled {
+            features.insert(name.to_string());
+        } else {
+            features.remove(name);
+        }
+    }
+
+    pub fn set_output(&self, output: impl Write + Send + 'static) {
+        *self.rstate().output.lock().unwrap() = Box::new(output);
+    }
+
+    /// Makes `f` callable as `name` from the module. It's called with the evaluated arguments,
+    /// after checking there are `arity` of them. Any number are accepted when `arity` is `None`.
+    pub fn register_native(
+        &self,
+        name: &str,
+        arity: Option<usize>,
+        f: impl Fn(&[ConstValue]) -> ConstValue + Sync + Send + 'static,
+    ) {
+        let callback: NativeCallback = Arc::new(f);
+        let value = ConstValue {
+            kind: ConstValueKind::Native {
+                name: name.to_string(),
+                arity,
+                callback,
+            },
+            ty: Type::Function {
+                parameters: LinkedHashMap::new(),
+                return_parameters: LinkedHashMap::new(),
+            },
+        };
+
+        let module = self.rstate().scope.module.clone();
+        module
+            .borrow_mut()
+            .insert(name, ScopeValue::ConstValue(value), 0);
+    }
+
+    /// `print(..)` and `println(..)` write their arguments separated by spaces to the output,
+    /// `println` followed by a newline. Symbols the module declares or imports with either name
+    /// take their place.
+    fn register_print(&self) {
+        for (name, end) in [("print", ""), ("println", "\n")] {
+            if self.rstate().scope.find_symbol(name).is_some() {
+                continue;
+            }
+
+            let output = self.rstate().output.clone();
+            let float_precision = self.rstate().float_precision.clone();
+            self.register_native(name, None, move |args| {
+                let precision = *float_precision.lock().unwrap();
+                let line = args
+                    .iter()
+                    .map(|arg| arg.to_display_string(precision))
+                    .intersperse(" ".to_string())
+                    .collect::<String>();
+
+                let mut output = output.lock().unwrap();
+                // Output that can't be written is dropped rather than failing evaluation
+                let _ = write!(output, "{line}{end}");
+                let _ = output.flush();
+                ConstValue::empty()
+            });
+        }
+    }
+
+    /// `pi`, `e`, `inf` and `nan` are untyped floats that can't be assigned to. Like `print`,
+    /// symbols the module declares or imports with the same name take their place.
+    fn register_constants(&self) {
+        let constants = [
+            ("pi", std::f64::consts::PI),
+            ("e", std::f64::consts::E),
+            ("inf", f64::INFINITY),
+            ("nan", f64::NAN),
+        ];
+        for (name, value) in constants {
+            if self.rstate().scope.find_symbol(name).is_some() {
+                continue;
+            }
+
+            let module = self.rstate().scope.module.clone();
+            let sym = module.borrow_mut().insert(
+                name,
+                ScopeValue::ConstValue(ConstValue::cfloat(value)),
+                0,
+            );
+            let mut sym = sym.borrow_mut();
+            sym.constant = true;
+            sym.prelude = true;
         }
     }
 
@@ -49,18 +315,103 @@ impl Evaluator {
 
 impl Evaluator {
     pub fn evaluate(&self) -> Vec<ConstValue> {
-        let vals = self
-            .module
-            .stmts
-            .iter()
-            .enumerate()
-            .map(|(index, stmt)| self.evaluate_statement(stmt, index))
-            .collect();
+        self.statements().map(|(_, value)| value).collect()
+    }
+
+    /// Evaluates the module one top-level statement at a time, yielding each statement with its
+    /// value. Nothing is evaluated until the next one is asked for, so the state can be inspected
+    /// in between, as a debugger would. Once the last statement is evaluated, the expressions
+    /// deferred in the module scope are run before the iterator ends.
+    pub fn statements(&self) -> impl Iterator<Item = (&Statement, ConstValue)> + '_ {
+        let values = self.module.stmts.iter().enumerate().map(|(index, stmt)| {
+            let value = self.evaluate_statement(stmt, index);
+            // Panics have already been reported
+            let flow = self.wstate().flow.take();
+            if let Some(Flow::Return { range, .. }) = flow {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ReturnOutsideFunction,
+                    range,
+                    backtrace: None,
+                });
+            }
+            (stmt, value)
+        });
+
+        // The module scope is never popped, so its deferred expressions run once it's finished
+        let deferred = std::iter::from_fn(|| {
+            let deferred = self.wstate().scope.take_deferred();
+            for expr in deferred.iter().rev() {
+                self.evaluate_expression(expr, self.module.stmts.len());
+            }
+            None
+        });
+
+        values.chain(deferred)
+    }
+
+    /// Like [`Evaluator::evaluate`], but fails with the errors reported while evaluating if
+    /// there were any, warnings included. They're drained from the state either way, so calling
+    /// it again starts with no errors.
+    pub fn evaluate_checked(&self) -> Result<Vec<ConstValue>, Vec<EvaluationError>> {
+        let vals = self.evaluate();
+        let errors = self.take_errors();
+
+        if errors.is_empty() {
+            Ok(vals)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The errors reported so far
+    pub fn errors(&self) -> Vec<EvaluationError> {
+        self.rstate().errors.clone()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.rstate().errors.is_empty()
+    }
+
+    /// Removes the errors reported so far and returns them
+    pub fn take_errors(&self) -> Vec<EvaluationError> {
+        std::mem::take(&mut self.wstate().errors)
+    }
+
+    /// The top-level bindings of the module, in declaration order. Records and modules aren't
+    /// values so they're always skipped, and functions are only included if asked for. Constants
+    /// from the prelude aren't declared by the module, so they're skipped too.
+    pub fn exports(&self, include_functions: bool) -> LinkedHashMap<String, ConstValue> {
+        let state = self.rstate();
+        let module = state.scope.module.borrow();
 
-        vals
+        module
+            .children
+            .iter()
+            .filter(|(_, sym)| !sym.borrow().prelude)
+            .filter_map(|(name, sym)| match &sym.borrow().value {
+                ScopeValue::ConstValue(ConstValue {
+                    kind: ConstValueKind::Function { .. } | ConstValueKind::NativeFunction { .. },
+                    ..
+                }) if !include_functions => None,
+                // Registered by the host rather than declared by the module
+                ScopeValue::ConstValue(ConstValue {
+                    kind: ConstValueKind::Native { .. },
+                    ..
+                }) => None,
+                ScopeValue::ConstValue(value) => Some((name.clone(), value.clone())),
+                _ => None,
+            })
+            .collect()
     }
 
     pub fn evaluate_statement(&self, statement: &Statement, index: usize) -> ConstValue {
+        if let Statement::Decleration { ident, .. } | Statement::Const { ident, .. } = statement {
+            let name = ident.as_str();
+            self.wstate()
+                .type_cache
+                .retain(|_, (cached, _)| cached != name);
+        }
+
         match statement {
             Statement::Decleration {
                 ident: SpannedToken(_, Token::Ident(id)),
@@ -72,6 +423,7 @@ impl Evaluator {
                     id,
                     ScopeValue::Record {
                         members,
+                        defaults: LinkedHashMap::from_iter(parameters.defaults()),
                         ident: id.to_string(),
                     },
                     index,
@@ -81,16 +433,37 @@ impl Evaluator {
                 ident: SpannedToken(_, Token::Ident(id)),
                 expr:
                     Some(Expression::Function {
+                        pure,
                         parameters,
                         return_parameters,
+                        requires,
                         body: Some(body),
                         ..
                     }),
                 ..
             } => {
+                let patterns = parameters.patterns();
                 let parameters = self.evaluate_params(parameters);
                 let return_parameters = self.evaluate_params(return_parameters);
 
+                if pure.is_some() {
+                    let mut locals: Vec<_> = parameters
+                        .keys()
+                        .chain(return_parameters.keys())
+                        .cloned()
+                        .chain(patterns.iter().flat_map(|(_, pattern)| {
+                            pattern
+                                .bindings()
+                                .into_iter()
+                                .map(|binding| binding.as_str().to_string())
+                        }))
+                        .collect();
+                    if let Some((_, cond)) = requires {
+                        self.check_purity_expression(id, cond, &mut locals);
+                    }
+                    self.check_purity(id, body, &mut locals);
+                }
+
                 // self.wstate()
                 //     .scope
                 //     .update_value(id, ScopeValue::ConstValue(ConstValue::empty()), index);
@@ -102,6 +475,9 @@ impl Evaluator {
                         parameters,
                         return_parameters,
                         sym,
+                        pure.is_some(),
+                        requires.as_ref().map(|(_, cond)| Expression::clone(cond)),
+                        patterns,
                     )),
                     index,
                 );
@@ -111,29 +487,39 @@ impl Evaluator {
                 expr: Some(expr),
                 ..
             } => {
+                if !self.check_declarable(ident.as_str(), ident.get_range()) {
+                    return ConstValue::empty();
+                }
                 let expr = self.evaluate_expression(expr, index);
+                let expr = if self.rstate().sized_literals {
+                    expr.smallest_fitting().unwrap_or(expr)
+                } else {
+                    expr
+                };
                 self.wstate().scope.update_value(
                     ident.as_str(),
                     ScopeValue::ConstValue(expr),
                     index,
                 );
             }
+            Statement::Const {
+                ident,
+                expr: Some(expr),
+                ..
+            } => self.evaluate_const(ident.as_str(), expr, index),
             Statement::Expression(expr) => return self.evaluate_expression(expr, index),
+            // Statement lists are sequences: each statement is evaluated in order for its side
+            // effects and the list yields the last value. Tuples are only built by `(a, b)`.
             Statement::List(list) => {
-                if list.num_children() == 1 {
-                    let item = list
-                        .iter_items()
-                        .next()
-                        .expect("Value should have been present. This is probably a rustc bug");
-                    return self.evaluate_statement(item, 0);
-                } else {
-                    let values: Vec<_> = list
-                        .iter_items()
-                        .enumerate()
-                        .map(|(index, stmt)| self.evaluate_statement(stmt, index))
-                        .collect();
-                    return ConstValue::tuple(values);
+                let mut value = ConstValue::empty();
+                for (index, stmt) in list.iter_items().enumerate() {
+                    value = self.evaluate_statement(stmt, index);
+                    // The rest of the list is skipped by a `return`
+                    if self.rstate().flow.is_some() {
+                        break;
+                    }
                 }
+                return value;
             }
 
             // Statement::UseStatement { args, .. } => {
@@ -150,8 +536,8 @@ impl Evaluator {
 
     pub fn evaluate_params(&self, params: &ParamaterList) -> LinkedHashMap<String, Type> {
         let iter = params.items.iter_items().filter_map(|f| {
-            if let (Some(ident), Some(ty)) = (&f.name, &f.ty) {
-                Some((ident.as_str().to_string(), self.evaluate_type(ty)))
+            if let (Some(ident), Some(ty)) = (f.binding(), &f.ty) {
+                Some((ident, self.evaluate_type(ty)))
             } else {
                 None
             }
@@ -161,16 +547,44 @@ impl Evaluator {
 
     pub fn evaluate_expression(&self, expression: &Expression, index: usize) -> ConstValue {
         match expression {
-            Expression::Integer(val, _, _) => ConstValue::cinteger(*val),
-            Expression::Float(val, _, _) => ConstValue::cfloat(*val),
+            // Untyped integers are signed, so literals too large for that are a `u64`
+            Expression::Integer(val, _, None, _) if *val > i64::MAX as u64 => {
+                ConstValue::integer(*val, 64, false)
+            }
+            Expression::Integer(val, _, None, _) => ConstValue::cinteger(*val),
+            Expression::Float(val, _, None, _) => ConstValue::cfloat(*val),
+            Expression::Integer(val, _, Some(suffix), _) => {
+                self.evaluate_suffixed_integer(*val, suffix, false, expression.get_range())
+            }
+            Expression::Float(val, _, Some(suffix), _) => match self.evaluate_type(suffix) {
+                Type::Float { width } => ConstValue::float(*val, width),
+                // Reported by the parser
+                _ => ConstValue::empty(),
+            },
             Expression::String(ParsedTemplateString(vs), _) => {
                 let str = vs
                     .iter()
                     .map(|f| match f {
                         ParsedTemplate::String(s) => s.as_str().to_string(),
-                        ParsedTemplate::Template(t, _, _) => {
+                        ParsedTemplate::Template(t, _, _, None) => {
                             let expr = self.evaluate_expression(t, index);
-                            format!("{expr}")
+                            expr.to_display_string(self.float_precision())
+                        }
+                        ParsedTemplate::Template(t, _, _, Some(spec)) => {
+                            let expr = self.evaluate_expression(t, index);
+                            let precision = self.float_precision();
+                            expr.to_formatted_string(spec, precision)
+                                .unwrap_or_else(|| {
+                                    self.add_error(EvaluationError {
+                                        kind: EvaluationErrorKind::InvalidFormatSpec(
+                                            spec.token.as_str().to_string(),
+                                            expr.ty.clone(),
+                                        ),
+                                        range: spec.token.span().into(),
+                                        backtrace: None,
+                                    });
+                                    expr.to_display_string(precision)
+                                })
                         }
                     })
                     .intersperse("".to_string())
@@ -189,10 +603,16 @@ impl Evaluator {
                         },
                         _ => ConstValue::empty(),
                     }
+                } else if id == "null" {
+                    // Like builtins, `null` can be shadowed
+                    ConstValue::null(Type::Empty)
+                } else if id == "true" || id == "false" {
+                    ConstValue::bool(id == "true")
                 } else {
                     self.add_error(EvaluationError {
                         kind: EvaluationErrorKind::SymbolNotFound(id.to_string()),
                         range: tok.get_range(),
+                        backtrace: None,
                     });
                     ConstValue::empty()
                 }
@@ -202,216 +622,3627 @@ impl Evaluator {
                 right: Some(right),
                 op_token: Some(SpannedToken(_, Token::Operator(o))),
             } => self.evaluate_binary_expression(left, o, right, index),
+            // `&place` references a binding or one of its fields
+            Expression::BinaryExpression {
+                left: None,
+                right: Some(right),
+                op_token: Some(SpannedToken(_, Token::Operator(Operator::BitAnd))),
+            } => self.evaluate_reference(right, index),
+            Expression::BinaryExpression {
+                left: None,
+                right: Some(right),
+                op_token: Some(SpannedToken(_, Token::Operator(Operator::LogicalNot))),
+            } => match self.evaluate_expression(right, index) {
+                ConstValue {
+                    kind: ConstValueKind::Bool(value),
+                    ..
+                } => ConstValue::bool(!value),
+                value => {
+                    self.not_a_bool(value, right.get_range());
+                    ConstValue::empty()
+                }
+            },
+            Expression::BinaryExpression {
+                left: None,
+                right: Some(right),
+                op_token: Some(SpannedToken(_, Token::Operator(Operator::Multiply))),
+            } => {
+                let reference = self.evaluate_expression(right, index);
+                match reference.read_ref() {
+                    Some(value) => value,
+                    None => {
+                        self.not_a_reference(reference, right.get_range());
+                        ConstValue::empty()
+                    }
+                }
+            }
+            Expression::BinaryExpression {
+                left: None,
+                right: Some(right),
+                op_token:
+                    Some(SpannedToken(_, Token::Operator(op @ (Operator::Minus | Operator::Plus)))),
+            } => match right.as_ref() {
+                Expression::Integer(val, _, Some(suffix), _) if *op == Operator::Minus => {
+                    self.evaluate_suffixed_integer(*val, suffix, true, expression.get_range())
+                }
+                right => {
+                    let value = self.evaluate_expression(right, index);
+                    self.evaluate_sign(op, value, expression.get_range())
+                }
+            },
+            Expression::BinaryExpression {
+                left: Some(place),
+                right: None,
+                op_token: Some(SpannedToken(_, Token::Operator(op))),
+            }
+            | Expression::BinaryExpression {
+                left: None,
+                right: Some(place),
+                op_token: Some(SpannedToken(_, Token::Operator(op))),
+            } if matches!(op, Operator::PlusPlus | Operator::MinusMinus) => {
+                let postfix =
+                    matches!(expression, Expression::BinaryExpression { right: None, .. });
+                self.evaluate_increment(place, op, postfix, index)
+            }
             Expression::FunctionCall {
                 expr,
                 args: raw_args,
             } => {
-                let expr = self.evaluate_expression(expr, index);
-                let args = self.evaluate_args(raw_args, index);
-
-                match (expr.ty, expr.kind) {
-                    // Function is called
-                    (
-                        Type::Function {
-                            parameters: ptypes,
-                            return_parameters: rptypes,
-                        },
-                        ConstValueKind::Function { body, rf },
-                    ) => {
-                        self.wstate().scope.push_scope(rf.clone());
+                if let Expression::Ident(SpannedToken(_, Token::Ident(name))) = expr.as_ref() {
+                    // Builtins can be shadowed by user symbols
+                    if self.rstate().scope.find_symbol(name).is_none() {
+                        let range = expression.get_range();
+                        if let Some(value) = self.evaluate_builtin(name, raw_args, range, index) {
+                            return value;
+                        }
+                    }
+                }
 
-                        let has_args: Option<Vec<_>> = args
-                            .into_iter()
-                            .zip(ptypes.into_iter())
-                            .enumerate()
-                            .map(|(i, (arg, (name, ty)))| {
-                                let arg = arg.try_implicit_cast(&ty).unwrap_or(arg);
+                let callee = expr.as_ref();
+                let expr = self.evaluate_expression(callee, index);
+                let args = self.evaluate_args(raw_args, index);
 
-                                if arg.ty != ty {
-                                    self.add_error(EvaluationError {
-                                        kind: EvaluationErrorKind::TypeMismatch(
-                                            arg.ty,
-                                            ty,
-                                            TypeHint::Parameter,
-                                        ),
-                                        range: raw_args
-                                            .items
-                                            .iter_items()
-                                            .nth(i)
-                                            .unwrap()
-                                            .get_range(),
-                                    });
-                                    return None;
-                                }
-                                self.wstate().scope.update_value(
-                                    &name,
-                                    ScopeValue::ConstValue(arg),
-                                    index,
-                                );
+                let range = expression.get_range();
+                self.with_frame(callee, range, || {
+                    self.evaluate_call(expr, args, raw_args.get_range(), range, index)
+                })
+            }
+            Expression::Array { values, .. } => {
+                let values: Vec<_> = values
+                    .iter_items()
+                    .map(|value| (self.evaluate_expression(value, index), value.get_range()))
+                    .collect();
 
-                                Some(())
-                            })
-                            .collect();
+                // Untyped literals take the type of the first typed element. Empty elements have
+                // already reported why they're empty, so they don't decide the type.
+                let ty = values
+                    .iter()
+                    .map(|(value, _)| &value.ty)
+                    .find(|ty| {
+                        !matches!(
+                            ty,
+                            Type::CoercibleInteger | Type::CoercibleFloat | Type::Empty
+                        )
+                    })
+                    .or_else(|| {
+                        values
+                            .iter()
+                            .map(|(value, _)| &value.ty)
+                            .find(|ty| **ty != Type::Empty)
+                    })
+                    .cloned()
+                    .unwrap_or(Type::Empty);
 
-                        if has_args.is_none() {
-                            return ConstValue::empty();
+                let values = values
+                    .into_iter()
+                    .map(|(value, range)| {
+                        let value = value.try_implicit_cast(&ty).unwrap_or(value);
+                        if value.ty != ty && value.ty != Type::Empty {
+                            self.add_error(EvaluationError {
+                                kind: EvaluationErrorKind::TypeMismatch(
+                                    value.ty.clone(),
+                                    ty.clone(),
+                                    TypeHint::Element,
+                                ),
+                                range,
+                                backtrace: None,
+                            });
                         }
+                        value
+                    })
+                    .collect();
 
-                        let _ = self.evaluate_statement(&body, index);
+                ConstValue::array(values, ty)
+            }
+            Expression::Index {
+                expr,
+                index: Some(raw_index),
+                ..
+            } => {
+                let value = self.evaluate_expression(expr, index);
 
-                        // TODO: verify types here as well
+                let (raw_offset, from_end) = index_offset(raw_index);
+                let index_value = self.evaluate_expression(raw_offset, index);
 
-                        let return_values: LinkedHashMap<_, _> = rptypes
-                            .into_iter()
-                            .map(|(name, ty)| {
-                                let sym = self.rstate().scope.find_symbol_local(&name);
-                                let vl = if let Some(sym) = sym {
-                                    let sym = sym.borrow();
-                                    if let ScopeValue::ConstValue(cv) = &sym.value {
-                                        if cv.ty == ty {
-                                            cv.clone()
-                                        } else {
-                                            // TODO: error handling
-                                            ConstValue::empty()
-                                        }
-                                    } else {
-                                        // TODO: error handling
-                                        ConstValue::empty()
-                                    }
-                                } else {
-                                    self.add_error(EvaluationError {
-                                        kind: EvaluationErrorKind::NotInitialized {
-                                            hint: TypeHint::ReturnParameter,
-                                        },
-                                        range: expression.get_range(),
-                                    });
-                                    ConstValue::default_for(&ty)
-                                };
-                                (name, vl)
-                            })
-                            .collect();
+                self.evaluate_index(value, index_value, from_end, raw_index.get_range())
+            }
+            Expression::RecordLiteral {
+                name: Some(name),
+                fields,
+                ..
+            } => self.evaluate_record_instantiation(name, fields, index),
+            Expression::RecordLiteral { fields, .. } => self.evaluate_record_literal(fields, index),
+            Expression::IfLet {
+                pattern,
+                expr,
+                body,
+                ..
+            } => self.evaluate_if_let(pattern, expr, body, index),
+            Expression::If {
+                branches,
+                else_body,
+                ..
+            } => self.evaluate_if(branches, else_body.as_deref(), index),
+            Expression::For {
+                pattern,
+                expr,
+                body,
+                ..
+            } => self.evaluate_for(pattern, expr, body, index),
+            Expression::Repeat { count, body, .. } => self.evaluate_repeat(count, body, index),
+            Expression::While {
+                condition, body, ..
+            } => self.evaluate_while(condition, body, index),
+            Expression::With {
+                expr,
+                binding,
+                body,
+                ..
+            } => self.evaluate_with(expr, binding, body, index),
+            Expression::Match { expr, arms, .. } => self.evaluate_match(expr, arms, index),
+            Expression::Defer { expr, .. } => {
+                self.wstate().scope.defer((**expr).clone());
+                ConstValue::empty()
+            }
+            Expression::Return { expr, .. } => {
+                let value = expr
+                    .as_ref()
+                    .map(|expr| Box::new(self.evaluate_expression(expr, index)));
+                self.wstate().flow = Some(Flow::Return {
+                    value,
+                    range: expression.get_range(),
+                });
+                ConstValue::empty()
+            }
+            Expression::Sequence(values) => values
+                .iter()
+                .map(|value| self.evaluate_expression(value, index))
+                .last()
+                .unwrap_or_else(ConstValue::empty),
+            Expression::Tuple(values, labels) => ConstValue::labeled_tuple(
+                values
+                    .iter()
+                    .map(|value| self.evaluate_expression(value, index))
+                    .collect(),
+                labels
+                    .iter()
+                    .map(|label| label.as_ref().map(|label| label.as_str().to_string()))
+                    .collect(),
+            ),
+            _ => ConstValue::empty(),
+        }
+    }
 
-                        let value = ConstValue::record_instance(rf, return_values);
+    /// Evaluates the fields of a record literal in order. Each field is visible to the fields
+    /// after it, so `{ a: 1, b: a + 1 }` is allowed, but referencing a later field is an error.
+    pub fn evaluate_record_literal(
+        &self,
+        fields: &PunctuationList<Statement>,
+        index: usize,
+    ) -> ConstValue {
+        let rf = Rf::new(Scope::new(ScopeValue::Root, index));
+        self.wstate().scope.push_scope(rf.clone());
 
-                        self.wstate().scope.pop_scope();
+        let fields: Vec<_> = fields
+            .iter_items()
+            .filter_map(|field| match field {
+                Statement::Decleration {
+                    ident: SpannedToken(_, Token::Ident(name)),
+                    expr: Some(expr),
+                    ..
+                } => Some((name.as_str(), expr)),
+                _ => None,
+            })
+            .collect();
 
-                        value
-                    }
-                    (
-                        Type::Function {
-                            parameters: ptypes, ..
-                        },
-                        ConstValueKind::NativeFunction { rf, callback },
-                    ) => {
-                        let arglen = args.len();
-                        let plen = ptypes.len();
+        let mut members = LinkedHashMap::new();
+        for (i, (name, expr)) in fields.iter().enumerate() {
+            let later: Vec<_> = fields[i + 1..].iter().map(|(name, _)| *name).collect();
+            if let Some(tok) = find_ident(expr, &later) {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ForwardReference(tok.as_str().to_string()),
+                    range: tok.get_range(),
+                    backtrace: None,
+                });
+                continue;
+            }
 
-                        let has_args: Option<LinkedHashMap<_, _>> = args
-                            .into_iter()
-                            .zip(ptypes.into_iter())
-                            .enumerate()
-                            .map(|(i, (arg, (name, ty)))| {
-                                let arg = arg.try_implicit_cast(&ty).unwrap_or(arg);
+            let value = self.evaluate_expression(expr, index);
+            // Insert rather than update so fields shadow outer symbols of the same name
+            self.wstate()
+                .scope
+                .insert_value(name, ScopeValue::ConstValue(value.clone()), index);
+            members.insert(name.to_string(), value);
+        }
 
-                                if arg.ty != ty {
-                                    self.add_error(EvaluationError {
-                                        kind: EvaluationErrorKind::TypeMismatch(
-                                            arg.ty,
-                                            ty,
-                                            TypeHint::Parameter,
-                                        ),
-                                        range: raw_args
-                                            .items
-                                            .iter_items()
-                                            .nth(i)
-                                            .unwrap()
-                                            .get_range(),
-                                    });
-                                    return None;
-                                }
+        self.pop_scope(index);
 
-                                Some((name, arg))
-                            })
-                            .collect();
+        ConstValue::record_instance(rf, members)
+    }
 
-                        if has_args.is_none() {
-                            return ConstValue::empty();
-                        } else if arglen != plen {
-                            self.add_error(EvaluationError {
-                                kind: EvaluationErrorKind::ArgCountMismatch(arglen as _, plen as _),
-                                range: raw_args.get_range(),
-                            });
-                            return ConstValue::empty();
-                        }
+    /// Evaluates `Name { field: value }`, instantiating the record `Name` from fields given by
+    /// name. Fields are evaluated like those of an anonymous record, so they can refer to the
+    /// ones before them. Fields that are left out take their declared default, or the zero value
+    /// of their type if they don't have one.
+    pub fn evaluate_record_instantiation(
+        &self,
+        name: &SpannedToken,
+        fields: &PunctuationList<Statement>,
+        index: usize,
+    ) -> ConstValue {
+        let sym = self.rstate().scope.find_symbol(name.as_str());
+        let Some(sym) = sym else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::SymbolNotFound(name.as_str().to_string()),
+                range: name.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        let (members, defaults) = match &sym.borrow().value {
+            ScopeValue::Record {
+                members, defaults, ..
+            } => (members.clone(), defaults.clone()),
+            ScopeValue::ConstValue(value) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ExpectedRecord(value.ty.clone()),
+                    range: name.get_range(),
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+            _ => return ConstValue::empty(),
+        };
 
-                        let return_vals = callback(has_args.as_ref().unwrap());
+        let literal = self.evaluate_record_literal(fields, index);
+        let ConstValueKind::RecordInstance { members: given, .. } = literal.kind else {
+            return ConstValue::empty();
+        };
 
-                        ConstValue::record_instance(rf, return_vals)
-                    }
-                    // Record is instantiated
-                    (Type::Symbol(sym), _) => {
-                        if let ScopeValue::Record { members, .. } = &sym.borrow().value {
-                            let len_off = members.len() != args.len();
-                            let args_vals: LinkedHashMap<_, _> = members
-                                .iter()
-                                .zip(args.into_iter())
-                                .enumerate()
-                                .filter_map(|(i, ((name, ty), arg))| {
-                                    let arg = arg.try_implicit_cast(ty).unwrap_or(arg);
-                                    if &arg.ty == ty {
-                                        Some((name.clone(), arg))
-                                    } else {
-                                        self.add_error(EvaluationError {
-                                            kind: EvaluationErrorKind::TypeMismatch(
-                                                arg.ty,
-                                                ty.clone(),
-                                                TypeHint::Parameter,
-                                            ),
-                                            range: raw_args
-                                                .items
-                                                .iter_items()
-                                                .nth(i)
-                                                .unwrap()
-                                                .get_range(),
-                                        });
-                                        None
-                                    }
-                                })
-                                .collect();
+        let mut valid = true;
+        for field in fields.iter_items() {
+            let Statement::Decleration { ident, .. } = field else {
+                continue;
+            };
+            if !members.contains_key(ident.as_str()) {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::UnknownField(
+                        ident.as_str().to_string(),
+                        name.as_str().to_string(),
+                    ),
+                    range: ident.get_range(),
+                    backtrace: None,
+                });
+                valid = false;
+            }
+        }
 
-                            if len_off {
-                                // If the number of arguments doesn't match the record
-                                self.add_error(EvaluationError {
-                                    kind: EvaluationErrorKind::ArgCountMismatch(
-                                        raw_args.len() as _,
-                                        members.len() as _,
-                                    ),
-                                    range: raw_args.get_range(),
-                                });
-                            } else if args_vals.len() == members.len() {
-                                // Everything good!
-                                return ConstValue::record_instance(sym.clone(), args_vals);
-                            }
-                        }
-                        ConstValue::empty()
+        let mut values = LinkedHashMap::new();
+        for (member, ty) in &members {
+            let value = match (given.get(member), defaults.get(member)) {
+                (Some(value), _) => {
+                    // Empty values have already reported why they're empty
+                    if value.ty == Type::Empty {
+                        valid = false;
+                        continue;
                     }
-                    // TODO: throw error
-                    _ => ConstValue::empty(),
+                    let range = fields
+                        .iter_items()
+                        .find_map(|field| match field {
+                            Statement::Decleration {
+                                ident,
+                                expr: Some(expr),
+                                ..
+                            } if ident.as_str() == member => Some(expr.get_range()),
+                            _ => None,
+                        })
+                        .unwrap_or_else(|| name.get_range());
+                    if !self.check_array_length(value, ty, range) {
+                        valid = false;
+                        continue;
+                    }
+                    let value = value.try_implicit_cast(ty).unwrap_or_else(|| value.clone());
+                    if &value.ty != ty {
+                        self.add_error(EvaluationError {
+                            kind: EvaluationErrorKind::TypeMismatch(
+                                value.ty,
+                                ty.clone(),
+                                TypeHint::Record,
+                            ),
+                            range,
+                            backtrace: None,
+                        });
+                        valid = false;
+                        continue;
+                    }
+                    value
                 }
+                (None, Some(default)) => self.evaluate_default(default, ty, index),
+                (None, None) => ConstValue::default_for(ty).unwrap_or_else(|| {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::NoDefaultValue(ty.clone()),
+                        range: name.get_range(),
+                        backtrace: None,
+                    });
+                    ConstValue::empty()
+                }),
+            };
+            if value.ty == Type::Empty {
+                valid = false;
             }
-            _ => ConstValue::empty(),
+            values.insert(member.clone(), value);
         }
+
+        if !valid {
+            return ConstValue::empty();
+        }
+        ConstValue::record_instance(sym, values)
     }
 
-    pub fn evaluate_binary_expression(
+    /// Evaluates `body` with the bindings of `pattern` in a scope of its own if `expr` matches it,
+    /// yielding the last value of the body. Otherwise the body is skipped.
+    pub fn evaluate_if_let(
         &self,
-        raw_left: &Expression,
+        pattern: &Pattern,
+        expr: &Expression,
+        body: &Statement,
+        index: usize,
+    ) -> ConstValue {
+        let value = self.evaluate_expression(expr, index);
+        let Some(bindings) = self.match_pattern(pattern, value, index) else {
+            return ConstValue::empty();
+        };
+
+        self.evaluate_with_bindings(bindings, body, index)
+    }
+
+    /// Evaluates the body of the first branch whose condition is true, or the else body if none
+    /// are. Conditions after the one that's true aren't evaluated. Only the branch that's taken is
+    /// evaluated, so its value is the result, and no branch yields `()`. Branches don't need to
+    /// agree on a type: when they yield different records the result has the union of their
+    /// types, and `match type(..)` on it tells them apart.
+    pub fn evaluate_if(
+        &self,
+        branches: &[(Expression, Statement)],
+        else_body: Option<&Statement>,
+        index: usize,
+    ) -> ConstValue {
+        let bodies = branches.iter().map(|(_, body)| body).chain(else_body);
+        for (condition, body) in branches {
+            let value = self.evaluate_expression(condition, index);
+            match value.kind {
+                ConstValueKind::Bool(true) => {
+                    let value = self.evaluate_with_bindings(Vec::new(), body, index);
+                    return self.unify_branches(value, bodies);
+                }
+                ConstValueKind::Bool(false) => (),
+                // Empty values have already reported why they're empty
+                _ if value.ty == Type::Empty => return ConstValue::empty(),
+                _ => {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::TypeMismatch(
+                            value.ty,
+                            Type::Boolean,
+                            TypeHint::Condition,
+                        ),
+                        range: condition.get_range(),
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+            }
+        }
+
+        match else_body {
+            Some(body) => {
+                let value = self.evaluate_with_bindings(Vec::new(), body, index);
+                self.unify_branches(value, bodies)
+            }
+            None => ConstValue::empty(),
+        }
+    }
+
+    /// The result of a conditional whose taken branch yielded `value`. If other branches end in
+    /// instantiations of different records, the result has the union of the types of all of them
+    /// in the order of the branches.
+    fn unify_branches<'a>(
+        &self,
+        value: ConstValue,
+        bodies: impl IntoIterator<Item = &'a Statement>,
+    ) -> ConstValue {
+        let Type::RecordInstance {
+            rf: Some(taken), ..
+        } = &value.ty
+        else {
+            return value;
+        };
+
+        let mut types = Vec::new();
+        for body in bodies {
+            let ty = match self.branch_record(body) {
+                Some(Type::RecordInstance { rf: Some(rf), .. }) if rf == *taken => value.ty.clone(),
+                Some(ty) => ty,
+                // The taken branch can yield a record without instantiating it
+                None => continue,
+            };
+            if !types.contains(&ty) {
+                types.push(ty);
+            }
+        }
+        if !types.contains(&value.ty) {
+            types.push(value.ty.clone());
+        }
+
+        if types.len() < 2 {
+            return value;
+        }
+        ConstValue {
+            ty: Type::Union(types),
+            ..value
+        }
+    }
+
+    /// The type of the record `body` instantiates last, found without evaluating it
+    fn branch_record(&self, body: &Statement) -> Option<Type> {
+        let mut expr = match body {
+            Statement::List(list) => return self.branch_record(list.iter_items().last()?),
+            Statement::Expression(expr) => expr,
+            _ => return None,
+        };
+        while let Expression::Sequence(values) = expr {
+            expr = values.last()?;
+        }
+
+        let name = match expr {
+            Expression::FunctionCall { expr, .. } => match expr.as_ref() {
+                Expression::Ident(name) => name,
+                _ => return None,
+            },
+            Expression::RecordLiteral {
+                name: Some(name), ..
+            } => name,
+            _ => return None,
+        };
+        let sym = self.rstate().scope.find_symbol(name.as_str())?;
+        let members = match &sym.borrow().value {
+            ScopeValue::Record { members, .. } => members.clone(),
+            _ => return None,
+        };
+        Some(Type::RecordInstance {
+            rf: Some(sym),
+            members,
+        })
+    }
+
+    /// Evaluates `body` for each element of an array or range, with the bindings of `pattern` in
+    /// a scope of its own. Elements that don't match the pattern are reported and end the loop,
+    /// and a loop over more elements than the iteration limit is an error that doesn't run at all.
+    pub fn evaluate_for(
+        &self,
+        pattern: &Pattern,
+        expr: &Expression,
+        body: &Statement,
+        index: usize,
+    ) -> ConstValue {
+        let values = self.evaluate_expression(expr, index);
+        let ty = values.ty.clone();
+
+        let Some(elements) = values.into_elements() else {
+            // Empty values have already reported why they're empty
+            if ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NotIterable(ty),
+                    range: expr.get_range(),
+                    backtrace: None,
+                });
+            }
+            return ConstValue::empty();
+        };
+        if !self.check_iteration_limit(elements.size_hint().0, expr.get_range()) {
+            return ConstValue::empty();
+        }
+
+        for element in elements {
+            let ty = element.ty.clone();
+            let Some(bindings) = self.match_pattern(pattern, element, index) else {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::PatternMismatch(ty),
+                    range: pattern.get_range(),
+                    backtrace: None,
+                });
+                break;
+            };
+
+            self.evaluate_with_bindings(bindings, body, index);
+            if self.rstate().flow.is_some() {
+                break;
+            }
+        }
+
+        ConstValue::empty()
+    }
+
+    /// Evaluates `body` the number of times given by `count`, each time in a scope of its own. A
+    /// count over the iteration limit is an error and the body isn't run at all.
+    pub fn evaluate_repeat(
+        &self,
+        count: &Expression,
+        body: &Statement,
+        index: usize,
+    ) -> ConstValue {
+        let value = self.evaluate_expression(count, index);
+        let Some(times) = self.evaluate_count(&value, count.get_range()) else {
+            return ConstValue::empty();
+        };
+
+        for _ in 0..times {
+            self.evaluate_with_bindings(Vec::new(), body, index);
+            if self.rstate().flow.is_some() {
+                break;
+            }
+        }
+
+        ConstValue::empty()
+    }
+
+    /// Evaluates `body` until `condition` is false, checking the condition again before each
+    /// iteration so assignments in the body are seen. The loop is stopped and reported once it
+    /// has run `max_iterations` times, since a condition that never becomes false would hang.
+    pub fn evaluate_while(
+        &self,
+        condition: &Expression,
+        body: &Statement,
+        index: usize,
+    ) -> ConstValue {
+        let max_iterations = self.rstate().max_iterations;
+        let mut iterations = 0;
+
+        loop {
+            let value = self.evaluate_expression(condition, index);
+            match value.kind {
+                ConstValueKind::Bool(true) => (),
+                ConstValueKind::Bool(false) => break,
+                // Empty values have already reported why they're empty
+                _ if value.ty == Type::Empty => break,
+                _ => {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::TypeMismatch(
+                            value.ty,
+                            Type::Boolean,
+                            TypeHint::Condition,
+                        ),
+                        range: condition.get_range(),
+                        backtrace: None,
+                    });
+                    break;
+                }
+            }
+
+            if iterations == max_iterations {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::IterationLimitExceeded(max_iterations),
+                    range: condition.get_range(),
+                    backtrace: None,
+                });
+                break;
+            }
+            iterations += 1;
+
+            self.evaluate_with_bindings(Vec::new(), body, index);
+            if self.rstate().flow.is_some() {
+                break;
+            }
+        }
+
+        ConstValue::empty()
+    }
+
+    /// Evaluates `body` with the resource from `expr` bound to `binding`. Closing the resource is
+    /// deferred before the body runs, so it happens after any of the body's own deferred
+    /// expressions and even if the body fails.
+    pub fn evaluate_with(
+        &self,
+        expr: &Expression,
+        binding: &SpannedToken,
+        body: &Statement,
+        index: usize,
+    ) -> ConstValue {
+        let value = self.evaluate_expression(expr, index);
+        // Empty values have already reported why they're empty
+        if value.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        let close = self.rstate().scope.find_symbol("close");
+        let has_close = close.is_some_and(|close| {
+            matches!(
+                &close.borrow().value,
+                ScopeValue::ConstValue(ConstValue {
+                    kind: ConstValueKind::Function { .. }
+                        | ConstValueKind::NativeFunction { .. }
+                        | ConstValueKind::Native { .. },
+                    ..
+                })
+            )
+        });
+        if !has_close {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::MissingCleanup(value.ty),
+                range: expr.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        self.wstate()
+            .scope
+            .push_scope(Rf::new(Scope::new(ScopeValue::Root, index)));
+        self.wstate()
+            .scope
+            .insert_value(binding.as_str(), ScopeValue::ConstValue(value), index);
+
+        // `binding.close()`, pointing at the binding so errors from closing show up there
+        let span = *binding.span();
+        self.wstate().scope.defer(Expression::BinaryExpression {
+            left: Some(Box::new(Expression::Ident(binding.clone()))),
+            right: Some(Box::new(Expression::FunctionCall {
+                expr: Box::new(Expression::Ident(SpannedToken::new(
+                    Token::Ident("close".to_string()),
+                    span,
+                ))),
+                args: ArgList {
+                    range: span.into(),
+                    items: PunctuationList::default(),
+                },
+            })),
+            op_token: Some(SpannedToken::new(Token::Operator(Operator::Dot), span)),
+        });
+
+        let value = self.evaluate_statement(body, index);
+        self.pop_scope(index);
+
+        value
+    }
+
+    /// References the binding or field named by `place`, which is an identifier followed by any
+    /// number of field accesses
+    pub fn evaluate_reference(&self, place: &Expression, index: usize) -> ConstValue {
+        let (root, path) = place_path(place);
+
+        let Expression::Ident(SpannedToken(_, Token::Ident(name))) = root else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::NotAPlace,
+                range: place.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        // References can be assigned through, so constants and frozen members can't be referenced
+        if !self.check_assignable(name, root.get_range())
+            || !self.check_mutable_member(name, &path, place.get_range())
+        {
+            return ConstValue::empty();
+        }
+
+        let value = self.evaluate_expression(place, index);
+        // Empty values have already reported why they're empty
+        if value.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        let Some(target) = self.rstate().scope.find_symbol(name) else {
+            return ConstValue::empty();
+        };
+        ConstValue::reference(target, path, value.ty)
+    }
+
+    /// Evaluates prefix `-value` and `+value` on a number. Unary plus leaves it as is. Negating
+    /// keeps the type of the value, so negating an unsigned integer is an error, as is negating
+    /// the smallest value of a signed type.
+    fn evaluate_sign(&self, op: &Operator, value: ConstValue, range: Range) -> ConstValue {
+        match (&value.ty, &value.kind, op) {
+            // Empty values have already reported why they're empty
+            (Type::Empty, ..) => value,
+            (
+                Type::Integer { .. }
+                | Type::CoercibleInteger
+                | Type::Float { .. }
+                | Type::CoercibleFloat,
+                _,
+                Operator::Plus,
+            ) => value,
+            (
+                Type::Float { .. } | Type::CoercibleFloat,
+                ConstValueKind::Float { value: float },
+                _,
+            ) => ConstValue {
+                kind: ConstValueKind::Float { value: -float },
+                ty: value.ty,
+            },
+            (Type::Integer { signed: true, .. } | Type::CoercibleInteger, ..) => {
+                let width = match value.ty {
+                    Type::Integer { width, .. } => width,
+                    _ => 64,
+                };
+                let negated = -value.integer_value().unwrap_or_default();
+                if negated > (1i128 << (width - 1)) - 1 {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::IntegerOverflow(negated.to_string(), value.ty),
+                        range,
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+
+                ConstValue {
+                    kind: ConstValueKind::Integer {
+                        value: negated as i64 as u64,
+                    },
+                    ty: value.ty,
+                }
+            }
+            _ => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::UnaryExpMismatch(op.clone(), value.ty),
+                    range,
+                    backtrace: None,
+                });
+                ConstValue::empty()
+            }
+        }
+    }
+
+    /// A reference to `place`, which is either something that can be referenced or a dereferenced
+    /// reference like `*r`
+    fn evaluate_place(&self, place: &Expression, index: usize) -> ConstValue {
+        match place {
+            Expression::BinaryExpression {
+                left: None,
+                right: Some(right),
+                op_token: Some(SpannedToken(_, Token::Operator(Operator::Multiply))),
+            } => {
+                let reference = self.evaluate_expression(right, index);
+                if !matches!(reference.ty, Type::Ref(_)) {
+                    self.not_a_reference(reference, right.get_range());
+                    return ConstValue::empty();
+                }
+                reference
+            }
+            place => self.evaluate_reference(place, index),
+        }
+    }
+
+    /// Assigns `right` to an element of the array at `place`, for `place[i] = value`. The array
+    /// can be anywhere `++` can be applied, or an element of another array. The index is checked
+    /// the same way as when reading the element, and the value has to be of the element type.
+    fn evaluate_element_assignment(
+        &self,
+        place: &Expression,
+        raw_index: &Expression,
+        right: ConstValue,
+        right_range: Range,
+        index: usize,
+    ) -> ConstValue {
+        // An element of an element is assigned by assigning the changed array to its place
+        let (mut array, reference) = match place {
+            Expression::Index { .. } => (self.evaluate_expression(place, index), None),
+            place => {
+                let reference = self.evaluate_place(place, index);
+                let Some(array) = reference.read_ref() else {
+                    return ConstValue::empty();
+                };
+                (array, Some(reference))
+            }
+        };
+        let (raw_offset, from_end) = index_offset(raw_index);
+        let offset = self.evaluate_expression(raw_offset, index);
+        // Empty values have already reported why they're empty
+        if array.ty == Type::Empty || offset.ty == Type::Empty || right.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        let Type::Array(ty, _) = &array.ty else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(
+                    array.ty,
+                    Type::Array(Box::new(right.ty), None),
+                    TypeHint::Variable,
+                ),
+                range: place.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        // Slices can be read but not assigned to
+        if !matches!(offset.kind, ConstValueKind::Integer { .. }) {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::InvalidIndex(array.ty, offset.ty),
+                range: raw_index.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+        let Some((position, _)) =
+            self.index_bounds(&array, &offset, from_end, raw_index.get_range())
+        else {
+            return ConstValue::empty();
+        };
+
+        let ty = Type::clone(ty);
+        let right = right.try_implicit_cast(&ty).unwrap_or(right);
+        if right.ty != ty {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(right.ty, ty, TypeHint::Element),
+                range: right_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        if let ConstValueKind::Array(values) = &mut array.kind {
+            Arc::make_mut(values)[position] = right.clone();
+        }
+        match (reference, place) {
+            (Some(reference), _) => {
+                reference.write_ref(array);
+            }
+            (
+                None,
+                Expression::Index {
+                    expr: outer,
+                    index: Some(outer_index),
+                    ..
+                },
+            ) => {
+                let range = place.get_range();
+                let array =
+                    self.evaluate_element_assignment(outer, outer_index, array, range, index);
+                if array.ty == Type::Empty {
+                    return ConstValue::empty();
+                }
+            }
+            _ => return ConstValue::empty(),
+        }
+        right
+    }
+
+    /// Adds or subtracts one from the integer at `place`, for `++` and `--`. Evaluates to the
+    /// value from before the change when `postfix`, otherwise the value after it. Places are the
+    /// same as what can be referenced, along with dereferencing a reference like `(*r)++`.
+    fn evaluate_increment(
+        &self,
+        place: &Expression,
+        op: &Operator,
+        postfix: bool,
+        index: usize,
+    ) -> ConstValue {
+        let reference = self.evaluate_place(place, index);
+        let Some(value) = reference.read_ref() else {
+            return ConstValue::empty();
+        };
+
+        let (min, max) = match &value.ty {
+            Type::Integer { width, signed } => integer_bounds(*width, *signed),
+            // Untyped integers are `i64`s
+            Type::CoercibleInteger => integer_bounds(64, true),
+            ty => {
+                if *ty != Type::Empty {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::UnaryExpMismatch(op.clone(), ty.clone()),
+                        range: place.get_range(),
+                        backtrace: None,
+                    });
+                }
+                return ConstValue::empty();
+            }
+        };
+
+        let current = value.integer_value().unwrap_or_default();
+        let new = match op {
+            Operator::PlusPlus => current + 1,
+            _ => current - 1,
+        };
+        if new < min || new > max {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::IntegerOverflow(new.to_string(), value.ty),
+                range: place.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        // Signed integers are stored sign extended
+        let new = ConstValue {
+            kind: ConstValueKind::Integer { value: new as u64 },
+            ty: value.ty.clone(),
+        };
+        reference.write_ref(new.clone());
+
+        if postfix {
+            value
+        } else {
+            new
+        }
+    }
+
+    /// Reports an operand of a logical operator that isn't a bool
+    fn not_a_bool(&self, value: ConstValue, range: Range) {
+        // Empty values have already reported why they're empty
+        if value.ty != Type::Empty {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(value.ty, Type::Boolean, TypeHint::Operand),
+                range,
+                backtrace: None,
+            });
+        }
+    }
+
+    fn not_a_reference(&self, value: ConstValue, range: Range) {
+        // Empty values have already reported why they're empty
+        if value.ty != Type::Empty {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::NotAReference(value.ty),
+                range,
+                backtrace: None,
+            });
+        }
+    }
+
+    /// Evaluates the body of the first arm whose pattern matches the value of `expr`, with the
+    /// bindings from the pattern in a scope of their own.
+    ///
+    /// `match type(x) { ... }` matches on the type of `x` instead, where each arm names a type
+    /// like `i32`, a record, or one of `Integer`, `Float`, `Bool`, `String`, `Array`, `Tuple`,
+    /// `Range`, `Optional` and `Function`, and `_` matches any type.
+    pub fn evaluate_match(
+        &self,
+        expr: &Expression,
+        arms: &PunctuationList<MatchArm>,
+        index: usize,
+    ) -> ConstValue {
+        let type_of = match expr {
+            Expression::FunctionCall { expr: callee, args }
+                if matches!(
+                    callee.as_ref(),
+                    Expression::Ident(SpannedToken(_, Token::Ident(name))) if name == "type"
+                ) && args.iter_items().count() == 1
+                    && self.rstate().scope.find_symbol("type").is_none() =>
+            {
+                args.iter_items().next()
+            }
+            _ => None,
+        };
+
+        let raw_value = type_of.unwrap_or(expr);
+        let value = self.evaluate_expression(raw_value, index);
+        // Empty values have already reported why they're empty
+        if value.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        for arm in arms.iter_items() {
+            let bindings = if type_of.is_some() {
+                let matches = match &arm.pattern {
+                    Pattern::Ident(name) => {
+                        name.as_str() == "_" || type_has_name(&value.narrow().ty, name.as_str())
+                    }
+                    _ => false,
+                };
+                matches.then(Vec::new)
+            } else {
+                self.match_pattern(&arm.pattern, value.clone(), index)
+            };
+
+            if let Some(bindings) = bindings {
+                let value = self.evaluate_with_bindings(bindings, &arm.body, index);
+                return self.unify_branches(value, arms.iter_items().map(|arm| &arm.body));
+            }
+        }
+
+        self.add_error(EvaluationError {
+            kind: EvaluationErrorKind::NonExhaustiveMatch(value.to_string(), value.ty),
+            range: raw_value.get_range(),
+            backtrace: None,
+        });
+        ConstValue::empty()
+    }
+
+    /// The values bound by `pattern` if `value` matches it
+    fn match_pattern(
+        &self,
+        pattern: &Pattern,
+        value: ConstValue,
+        index: usize,
+    ) -> Option<Vec<(String, ConstValue)>> {
+        if let Pattern::Literals(literals) = pattern {
+            return self.match_literals(literals, &value, index).then(Vec::new);
+        }
+        if let Pattern::Typed { binding, ty } = pattern {
+            if let ConstValueKind::Null = value.kind {
+                return None;
+            }
+
+            let ty = self.evaluate_type(ty);
+            let value = value.unwrap_optional();
+            let value = value.try_implicit_cast(&ty).unwrap_or(value);
+            return (value.ty == ty).then(|| vec![(binding.as_str().to_string(), value)]);
+        }
+
+        match (pattern, value.kind) {
+            // `_` matches anything without binding it
+            (Pattern::Ident(binding), _) if binding.as_str() == "_" => Some(Vec::new()),
+            (Pattern::Some { .. }, ConstValueKind::Null) => None,
+            (Pattern::Some { binding, .. }, kind) | (Pattern::Ident(binding), kind) => {
+                let value = ConstValue { ty: value.ty, kind };
+                Some(vec![(
+                    binding.as_str().to_string(),
+                    value.unwrap_optional(),
+                )])
+            }
+            (Pattern::Tuple { bindings, .. }, ConstValueKind::Tuple(values, _))
+                if bindings.len() == values.len() =>
+            {
+                Some(
+                    bindings
+                        .iter()
+                        .map(|binding| binding.as_str().to_string())
+                        .zip(values)
+                        .collect(),
+                )
+            }
+            (Pattern::Record { bindings, .. }, ConstValueKind::RecordInstance { members, .. }) => {
+                bindings
+                    .iter()
+                    .map(|binding| {
+                        let value = members.get(binding.as_str())?.clone();
+                        Some((binding.as_str().to_string(), value))
+                    })
+                    .collect()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `value` equals any of `literals`. Every literal is checked against the type of
+    /// the value before any are compared, so an arm mixing literals of incompatible types is
+    /// reported whichever of them would have matched.
+    fn match_literals(&self, literals: &[Expression], value: &ConstValue, index: usize) -> bool {
+        let mut candidates = Vec::with_capacity(literals.len());
+        for literal in literals {
+            let candidate = self.evaluate_expression(literal, index);
+            let candidate = candidate.try_implicit_cast(&value.ty).unwrap_or(candidate);
+            if candidate.ty != value.ty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        candidate.ty,
+                        value.ty.clone(),
+                        TypeHint::Pattern,
+                    ),
+                    range: literal.get_range(),
+                    backtrace: None,
+                });
+                return false;
+            }
+            candidates.push(candidate);
+        }
+
+        candidates.iter().any(|candidate| candidate.eq_value(value))
+    }
+
+    /// The values bound by a destructuring parameter for `arg`. Unlike a match arm there's
+    /// nothing to fall back to, so a non-record argument or a missing field is reported.
+    fn destructure_argument(
+        &self,
+        pattern: &Pattern,
+        arg: &ConstValue,
+        range: Range,
+    ) -> Option<Vec<(String, ConstValue)>> {
+        let ConstValueKind::RecordInstance { members, .. } = &arg.kind else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ExpectedRecord(arg.ty.clone()),
+                range,
+                backtrace: None,
+            });
+            return None;
+        };
+
+        pattern
+            .bindings()
+            .into_iter()
+            .map(|binding| match members.get(binding.as_str()) {
+                Some(value) => Some((binding.as_str().to_string(), value.clone())),
+                None => {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::MissingField(
+                            binding.as_str().to_string(),
+                            arg.ty.clone(),
+                        ),
+                        range: binding.span().into(),
+                        backtrace: None,
+                    });
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Runs the expressions deferred in the current scope, most recent first, while the scope is
+    /// still visible to them, then pops it
+    fn pop_scope(&self, index: usize) {
+        let deferred = self.wstate().scope.take_deferred();
+        // Deferred expressions run in full even while a `return` is unwinding the scope
+        let flow = self.wstate().flow.take();
+        for expr in deferred.iter().rev() {
+            self.evaluate_expression(expr, index);
+        }
+        self.wstate().flow = flow;
+
+        self.wstate().scope.pop_scope();
+    }
+
+    /// Evaluates `body` in a new scope holding `bindings`, yielding its last value
+    fn evaluate_with_bindings(
+        &self,
+        bindings: Vec<(String, ConstValue)>,
+        body: &Statement,
+        index: usize,
+    ) -> ConstValue {
+        self.wstate()
+            .scope
+            .push_scope(Rf::new(Scope::new(ScopeValue::Root, index)));
+        for (name, value) in bindings {
+            self.wstate()
+                .scope
+                .insert_value(&name, ScopeValue::ConstValue(value), index);
+        }
+
+        let value = self.evaluate_statement(body, index);
+        self.pop_scope(index);
+
+        value
+    }
+
+    /// Binds `name` to the value of `expr`, after which it can't be assigned to. The initializer
+    /// is held to the same rules as the body of a pure function: it's only evaluated if it can't
+    /// call impure functions or assign to other bindings.
+    fn evaluate_const(&self, name: &str, expr: &Expression, index: usize) {
+        if !self.check_declarable(name, expr.get_range()) {
+            return;
+        }
+
+        let errors = self.rstate().errors.len();
+        self.check_purity_expression(name, expr, &mut Vec::new());
+        let mut state = self.wstate();
+        let mut folded = true;
+        for error in &mut state.errors[errors..] {
+            if let EvaluationErrorKind::ImpureFunction(name, impurity) = &error.kind {
+                error.kind =
+                    EvaluationErrorKind::NonConstInitializer(name.clone(), impurity.clone());
+                folded = false;
+            }
+        }
+        drop(state);
+
+        let value = if folded {
+            self.evaluate_expression(expr, index)
+        } else {
+            ConstValue::empty()
+        };
+        let value = if self.rstate().sized_literals {
+            value.smallest_fitting().unwrap_or(value)
+        } else {
+            value
+        };
+
+        let mut state = self.wstate();
+        state
+            .scope
+            .update_value(name, ScopeValue::ConstValue(value), index);
+        if let Some(sym) = state.scope.find_symbol(name) {
+            sym.borrow_mut().constant = true;
+        }
+    }
+
+    /// Like [`Self::check_assignable`], for declaring `name` again. Constants from the prelude
+    /// aren't declared by the module, so a declaration shadows them instead.
+    fn check_declarable(&self, name: &str, range: Range) -> bool {
+        let prelude = self
+            .rstate()
+            .scope
+            .find_symbol(name)
+            .is_some_and(|sym| sym.borrow().prelude);
+        prelude || self.check_assignable(name, range)
+    }
+
+    /// Reports an error if `name` was declared with `const`, returning whether it can be assigned
+    fn check_assignable(&self, name: &str, range: Range) -> bool {
+        let constant = self
+            .rstate()
+            .scope
+            .find_symbol(name)
+            .is_some_and(|sym| sym.borrow().constant);
+        if constant {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::AssignToImmutable(name.to_string()),
+                range,
+                backtrace: None,
+            });
+        }
+        !constant
+    }
+
+    /// Reports each way the body of the pure function `func` could have side effects. `locals` are
+    /// the bindings owned by the function, which it's free to assign to.
+    fn check_purity(&self, func: &str, statement: &Statement, locals: &mut Vec<String>) {
+        match statement {
+            Statement::Decleration { ident, expr, .. } | Statement::Const { ident, expr, .. } => {
+                if let Some(expr) = expr {
+                    self.check_purity_expression(func, expr, locals);
+                }
+                locals.push(ident.as_str().to_string());
+            }
+            Statement::Expression(expr) => self.check_purity_expression(func, expr, locals),
+            Statement::List(list) => list
+                .iter_items()
+                .for_each(|stmt| self.check_purity(func, stmt, locals)),
+            Statement::UseStatement { .. } => (),
+        }
+    }
+
+    fn check_pure_call(&self, func: &str, callee: &Expression, locals: &[String]) {
+        let Expression::Ident(tok @ SpannedToken(_, Token::Ident(name))) = callee else {
+            return;
+        };
+        // Recursion and locals can't make the function impure
+        if name == func || locals.contains(name) {
+            return;
+        }
+        let Some(sym) = self.rstate().scope.find_symbol(name) else {
+            return;
+        };
+        let impure = matches!(
+            &sym.borrow().value,
+            ScopeValue::ConstValue(ConstValue {
+                kind: ConstValueKind::Function { pure: false, .. }
+                    | ConstValueKind::NativeFunction { .. }
+                    | ConstValueKind::Native { .. },
+                ..
+            })
+        );
+        if impure {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ImpureFunction(
+                    func.to_string(),
+                    Impurity::ImpureCall(name.clone()),
+                ),
+                range: tok.get_range(),
+                backtrace: None,
+            });
+        }
+    }
+
+    /// Reports assigning to the member of `name` at `path` if it belongs to a frozen record,
+    /// returning whether it can be assigned to
+    fn check_mutable_member(&self, name: &str, path: &[String], range: Range) -> bool {
+        let Some(sym) = self.rstate().scope.find_symbol(name) else {
+            return true;
+        };
+        let sym = sym.borrow();
+        let ScopeValue::ConstValue(value) = &sym.value else {
+            return true;
+        };
+
+        let mut value = value;
+        for field in path {
+            let ConstValueKind::RecordInstance {
+                members, frozen, ..
+            } = &value.kind
+            else {
+                return true;
+            };
+            if *frozen {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ImmutableMember(
+                        std::iter::once(name)
+                            .chain(path.iter().map(String::as_str))
+                            .intersperse(".")
+                            .collect(),
+                    ),
+                    range,
+                    backtrace: None,
+                });
+                return false;
+            }
+            let Some(member) = members.get(field) else {
+                return true;
+            };
+            value = member;
+        }
+
+        true
+    }
+
+    /// Reports assigning to `target` if it's outside the pure function `func`
+    fn check_pure_assignment(&self, func: &str, target: &Expression, locals: &[String]) {
+        // Assigning to an element assigns to the array it's in
+        let mut target = target;
+        while let Expression::Index { expr, .. } = target {
+            target = expr;
+        }
+
+        if let Expression::BinaryExpression {
+            left: None,
+            op_token: Some(SpannedToken(_, Token::Operator(Operator::Multiply))),
+            ..
+        } = target
+        {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ImpureFunction(
+                    func.to_string(),
+                    Impurity::RefAssignment,
+                ),
+                range: target.get_range(),
+                backtrace: None,
+            });
+        }
+
+        // Assigning to a member assigns to the binding it's accessed from
+        while let Expression::BinaryExpression {
+            left: Some(left), ..
+        } = target
+        {
+            target = left;
+        }
+        if let Expression::Ident(tok @ SpannedToken(_, Token::Ident(name))) = target {
+            if !locals.contains(name) {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ImpureFunction(
+                        func.to_string(),
+                        Impurity::OuterAssignment(name.clone()),
+                    ),
+                    range: tok.get_range(),
+                    backtrace: None,
+                });
+            }
+        }
+    }
+
+    fn check_purity_expression(&self, func: &str, expr: &Expression, locals: &mut Vec<String>) {
+        match expr {
+            Expression::BinaryExpression {
+                left: Some(left),
+                right: Some(right),
+                op_token: Some(SpannedToken(_, Token::Operator(op))),
+            } => match op {
+                Operator::Equals => {
+                    self.check_pure_assignment(func, left, locals);
+                    self.check_purity_expression(func, right, locals);
+                }
+                Operator::Dot => {
+                    self.check_purity_expression(func, left, locals);
+                    if let Expression::FunctionCall { .. } = right.as_ref() {
+                        self.check_purity_expression(func, right, locals);
+                    }
+                }
+                Operator::Pipe => {
+                    self.check_purity_expression(func, left, locals);
+                    self.check_pure_call(func, right, locals);
+                    self.check_purity_expression(func, right, locals);
+                }
+                _ => {
+                    self.check_purity_expression(func, left, locals);
+                    self.check_purity_expression(func, right, locals);
+                }
+            },
+            Expression::BinaryExpression {
+                left: Some(place),
+                right: None,
+                op_token: Some(SpannedToken(_, Token::Operator(op))),
+            }
+            | Expression::BinaryExpression {
+                left: None,
+                right: Some(place),
+                op_token: Some(SpannedToken(_, Token::Operator(op))),
+            } if matches!(op, Operator::PlusPlus | Operator::MinusMinus) => {
+                self.check_pure_assignment(func, place, locals);
+                self.check_purity_expression(func, place, locals);
+            }
+            Expression::FunctionCall { expr, args } => {
+                // `swap` assigns to both of its arguments
+                if matches!(
+                    expr.as_ref(),
+                    Expression::Ident(SpannedToken(_, Token::Ident(name)))
+                        if name == "swap"
+                            && !locals.contains(name)
+                            && self.rstate().scope.find_symbol(name).is_none()
+                ) {
+                    args.iter_items()
+                        .for_each(|arg| self.check_pure_assignment(func, arg, locals));
+                }
+                self.check_pure_call(func, expr, locals);
+                self.check_purity_expression(func, expr, locals);
+                args.iter_items()
+                    .for_each(|arg| self.check_purity_expression(func, arg, locals));
+            }
+            Expression::String(ParsedTemplateString(templates), _) => {
+                for template in templates {
+                    if let ParsedTemplate::Template(expr, ..) = template {
+                        self.check_purity_expression(func, expr, locals);
+                    }
+                }
+            }
+            Expression::Tuple(values, _) | Expression::Sequence(values) => values
+                .iter()
+                .for_each(|value| self.check_purity_expression(func, value, locals)),
+            Expression::Array { values, .. } => values
+                .iter_items()
+                .for_each(|value| self.check_purity_expression(func, value, locals)),
+            Expression::Index { expr, index, .. } => {
+                self.check_purity_expression(func, expr, locals);
+                if let Some(index) = index {
+                    self.check_purity_expression(func, index, locals);
+                }
+            }
+            Expression::RecordLiteral { fields, .. } => {
+                // Fields are only visible inside the literal
+                let len = locals.len();
+                fields
+                    .iter_items()
+                    .for_each(|field| self.check_purity(func, field, locals));
+                locals.truncate(len);
+            }
+            Expression::IfLet {
+                pattern,
+                expr,
+                body,
+                ..
+            }
+            | Expression::For {
+                pattern,
+                expr,
+                body,
+                ..
+            } => {
+                self.check_purity_expression(func, expr, locals);
+
+                // Bindings are only visible inside the body
+                let len = locals.len();
+                locals.extend(
+                    pattern
+                        .bindings()
+                        .into_iter()
+                        .map(|binding| binding.as_str().to_string()),
+                );
+                self.check_purity(func, body, locals);
+                locals.truncate(len);
+            }
+            Expression::Repeat {
+                count: expr, body, ..
+            }
+            | Expression::While {
+                condition: expr,
+                body,
+                ..
+            } => {
+                self.check_purity_expression(func, expr, locals);
+
+                let len = locals.len();
+                self.check_purity(func, body, locals);
+                locals.truncate(len);
+            }
+            Expression::With {
+                expr,
+                binding,
+                body,
+                ..
+            } => {
+                self.check_purity_expression(func, expr, locals);
+
+                let len = locals.len();
+                locals.push(binding.as_str().to_string());
+                self.check_purity(func, body, locals);
+                locals.truncate(len);
+            }
+            Expression::If {
+                branches,
+                else_body,
+                ..
+            } => {
+                for (condition, body) in branches {
+                    self.check_purity_expression(func, condition, locals);
+                    self.check_purity(func, body, locals);
+                }
+                if let Some(body) = else_body {
+                    self.check_purity(func, body, locals);
+                }
+            }
+            Expression::Match { expr, arms, .. } => {
+                self.check_purity_expression(func, expr, locals);
+
+                // Bindings are only visible inside their arm
+                for arm in arms.iter_items() {
+                    let len = locals.len();
+                    locals.extend(
+                        arm.pattern
+                            .bindings()
+                            .into_iter()
+                            .map(|binding| binding.as_str().to_string()),
+                    );
+                    self.check_purity(func, &arm.body, locals);
+                    locals.truncate(len);
+                }
+            }
+            Expression::Defer { expr, .. } => self.check_purity_expression(func, expr, locals),
+            Expression::Return {
+                expr: Some(expr), ..
+            } => self.check_purity_expression(func, expr, locals),
+            // Prefix operators like `-x` and `!x`
+            Expression::BinaryExpression {
+                left: None,
+                right: Some(right),
+                ..
+            } => self.check_purity_expression(func, right, locals),
+            _ => (),
+        }
+    }
+
+    /// Indexes an array or string with an integer, or slices it with a range. With `from_end`, an
+    /// integer index counts back from the end, if negative indexing is enabled.
+    pub fn evaluate_index(
+        &self,
+        value: ConstValue,
+        index: ConstValue,
+        from_end: bool,
+        range: Range,
+    ) -> ConstValue {
+        let Some((start, end)) = self.index_bounds(&value, &index, from_end, range) else {
+            return ConstValue::empty();
+        };
+
+        match (value.kind, value.ty, index.kind) {
+            (ConstValueKind::Array(values), _, ConstValueKind::Integer { .. }) => {
+                values[start].clone()
+            }
+            (ConstValueKind::Array(values), Type::Array(ty, _), _) => {
+                ConstValue::array(values[start..end].to_vec(), *ty)
+            }
+            (ConstValueKind::String { string }, _, _) => {
+                let mode = self.rstate().string_mode;
+                match mode.slice(&string, start, end) {
+                    Ok(string) => ConstValue::string(string),
+                    Err(offset) => {
+                        self.add_error(EvaluationError {
+                            kind: EvaluationErrorKind::NotCharBoundary(offset),
+                            range,
+                            backtrace: None,
+                        });
+                        ConstValue::empty()
+                    }
+                }
+            }
+            _ => ConstValue::empty(),
+        }
+    }
+
+    /// The positions an index or slice of `value` covers, from the start up to the end. Reports
+    /// indexes that are out of bounds or can't index `value`.
+    fn index_bounds(
+        &self,
+        value: &ConstValue,
+        index: &ConstValue,
+        from_end: bool,
+        range: Range,
+    ) -> Option<(usize, usize)> {
+        let len = match &value.kind {
+            ConstValueKind::Array(values) => values.len(),
+            ConstValueKind::String { string } => self.rstate().string_mode.len(string),
+            _ => 0,
+        };
+
+        let bounds = match (&value.kind, &index.kind) {
+            (
+                ConstValueKind::Array(_) | ConstValueKind::String { .. },
+                ConstValueKind::Integer { value },
+            ) if from_end => {
+                let start = len
+                    .checked_sub(*value as usize)
+                    .filter(|_| *value > 0 && self.rstate().negative_indexing);
+                let Some(start) = start else {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::IndexOutOfBounds(format!("-{index}"), len),
+                        range,
+                        backtrace: None,
+                    });
+                    return None;
+                };
+                Some((start, start + 1))
+            }
+            (
+                ConstValueKind::Array(_) | ConstValueKind::String { .. },
+                ConstValueKind::Integer { value },
+            ) => Some((*value as usize, (*value as usize).saturating_add(1))),
+            (
+                ConstValueKind::Array(_) | ConstValueKind::String { .. },
+                ConstValueKind::Range { .. },
+            ) => index.range_bounds().map(|(start, end)| {
+                // Negative bounds are out of bounds
+                (
+                    usize::try_from(start).unwrap_or(usize::MAX),
+                    usize::try_from(end).unwrap_or(usize::MAX),
+                )
+            }),
+            _ => None,
+        };
+
+        let Some((start, end)) = bounds else {
+            // Empty values have already reported why they're empty
+            if value.ty != Type::Empty && index.ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::InvalidIndex(value.ty.clone(), index.ty.clone()),
+                    range,
+                    backtrace: None,
+                });
+            }
+            return None;
+        };
+
+        if start > end || end > len {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::IndexOutOfBounds(index.to_string(), len),
+                range,
+                backtrace: None,
+            });
+            return None;
+        }
+
+        Some((start, end))
+    }
+
+    /// Calls `expr` with already evaluated arguments. Each argument carries the range used when
+    /// reporting errors about it.
+    pub fn evaluate_call(
+        &self,
+        expr: ConstValue,
+        args: Vec<(ConstValue, Range)>,
+        args_range: Range,
+        range: Range,
+        index: usize,
+    ) -> ConstValue {
+        match (expr.ty, expr.kind) {
+            (
+                _,
+                ConstValueKind::Native {
+                    arity, callback, ..
+                },
+            ) => {
+                if let Some(arity) = arity.filter(|arity| *arity != args.len()) {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, arity as _),
+                        range: args_range,
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+
+                // Nothing more is evaluated while a panic unwinds
+                if self.rstate().flow.is_some() {
+                    return ConstValue::empty();
+                }
+
+                let args: Vec<_> = args.into_iter().map(|(arg, _)| arg).collect();
+                callback(&args)
+            }
+            // Function is called
+            (
+                Type::Function {
+                    parameters: ptypes,
+                    return_parameters: rptypes,
+                },
+                ConstValueKind::Function {
+                    body,
+                    rf,
+                    requires,
+                    patterns,
+                    ..
+                },
+            ) => {
+                if args.len() != ptypes.len() {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::ArgCountMismatch(
+                            args.len() as _,
+                            ptypes.len() as _,
+                        ),
+                        range: args_range,
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+
+                // Nothing more is evaluated while a panic unwinds
+                if self.rstate().flow.is_some() {
+                    return ConstValue::empty();
+                }
+
+                let max_call_depth = self.rstate().max_call_depth;
+                if self.rstate().call_depth >= max_call_depth {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::RecursionLimitExceeded(max_call_depth),
+                        range,
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+
+                // Parameters and locals go in a scope of their own for each call, so a recursive
+                // call doesn't overwrite the ones of the call it was made from. Return parameters
+                // start out with the values the function's scope holds for them.
+                let frame = Rf::new(Scope::new(ScopeValue::Root, index));
+                for name in rptypes.keys() {
+                    if let Some(sym) = rf.borrow().children.get(name) {
+                        let value = sym.borrow().value.clone();
+                        frame
+                            .borrow_mut()
+                            .children
+                            .insert(name.clone(), Rf::new(Scope::new(value, index)));
+                    }
+                }
+                self.wstate().scope.push_scope(rf.clone());
+                self.wstate().scope.push_scope(frame);
+
+                let has_args: Option<Vec<_>> = args
+                    .into_iter()
+                    .zip(ptypes.into_iter())
+                    .map(|((arg, arg_range), (name, ty))| {
+                        if !self.check_array_length(&arg, &ty, arg_range) {
+                            return None;
+                        }
+                        let arg = arg.try_implicit_cast(&ty).unwrap_or(arg);
+
+                        // Empty values have already reported why they're empty
+                        if arg.ty == Type::Empty {
+                            return None;
+                        }
+                        if arg.ty != ty {
+                            self.add_error(EvaluationError {
+                                kind: EvaluationErrorKind::TypeMismatch(
+                                    arg.ty,
+                                    ty,
+                                    TypeHint::Parameter,
+                                ),
+                                range: arg_range,
+                                backtrace: None,
+                            });
+                            return None;
+                        }
+                        if let Some((_, pattern)) = patterns.iter().find(|(key, _)| *key == name) {
+                            for (binding, value) in
+                                self.destructure_argument(pattern, &arg, arg_range)?
+                            {
+                                self.wstate().scope.insert_value(
+                                    &binding,
+                                    ScopeValue::ConstValue(value),
+                                    index,
+                                );
+                            }
+                        }
+                        self.wstate()
+                            .scope
+                            .insert_value(&name, ScopeValue::ConstValue(arg), index);
+
+                        Some(())
+                    })
+                    .collect();
+
+                if has_args.is_none() {
+                    self.pop_scope(index);
+                    self.wstate().scope.pop_scope();
+                    return ConstValue::empty();
+                }
+
+                self.wstate().call_depth += 1;
+                if let Some(requires) = &requires {
+                    if !self.evaluate_precondition(requires, index) {
+                        self.wstate().call_depth -= 1;
+                        self.pop_scope(index);
+                        self.wstate().scope.pop_scope();
+                        return ConstValue::empty();
+                    }
+                }
+
+                // Each call takes a fair amount of stack, debug builds especially, so the body
+                // moves to a new segment when the current one is running out
+                stacker::maybe_grow(CALL_STACK_RED_ZONE, CALL_STACK_SEGMENT, || {
+                    self.evaluate_statement(&body, index)
+                });
+                self.wstate().call_depth -= 1;
+
+                let flow = self
+                    .wstate()
+                    .flow
+                    .take_if(|flow| matches!(flow, Flow::Return { .. }));
+                if let Some(Flow::Return {
+                    value: Some(value),
+                    range,
+                }) = flow
+                {
+                    match rptypes.keys().next() {
+                        Some(name) if rptypes.len() == 1 => {
+                            self.wstate().scope.insert_value(
+                                name,
+                                ScopeValue::ConstValue(*value),
+                                index,
+                            );
+                        }
+                        _ => self.add_error(EvaluationError {
+                            kind: EvaluationErrorKind::UnexpectedReturnValue(rptypes.len()),
+                            range,
+                            backtrace: None,
+                        }),
+                    }
+                }
+
+                let return_values: LinkedHashMap<_, _> = rptypes
+                    .into_iter()
+                    .map(|(name, ty)| {
+                        let sym = self.rstate().scope.find_symbol_local(&name);
+                        let vl = if let Some(sym) = sym {
+                            let sym = sym.borrow();
+                            if let ScopeValue::ConstValue(cv) = &sym.value {
+                                let valid_length = self.check_array_length(cv, &ty, range);
+                                let cv = cv.try_implicit_cast(&ty).unwrap_or_else(|| cv.clone());
+                                if valid_length && cv.ty == ty {
+                                    cv
+                                } else {
+                                    // Return values have no range of their own, so mismatches
+                                    // are reported at the call. Empty values and types that
+                                    // didn't resolve have already been reported.
+                                    if valid_length && cv.ty != Type::Empty && ty != Type::Empty {
+                                        self.add_error(EvaluationError {
+                                            kind: EvaluationErrorKind::TypeMismatch(
+                                                cv.ty,
+                                                ty.clone(),
+                                                TypeHint::ReturnParameter,
+                                            ),
+                                            range,
+                                            backtrace: None,
+                                        });
+                                    }
+                                    ConstValue::empty()
+                                }
+                            } else {
+                                // TODO: error handling
+                                ConstValue::empty()
+                            }
+                        } else {
+                            self.add_error(EvaluationError {
+                                kind: EvaluationErrorKind::NotInitialized {
+                                    hint: TypeHint::ReturnParameter,
+                                },
+                                range,
+                                backtrace: None,
+                            });
+                            ConstValue::default_for(&ty).unwrap_or_else(|| {
+                                self.add_error(EvaluationError {
+                                    kind: EvaluationErrorKind::NoDefaultValue(ty.clone()),
+                                    range,
+                                    backtrace: None,
+                                });
+                                ConstValue::empty()
+                            })
+                        };
+                        (name, vl)
+                    })
+                    .collect();
+
+                let value = ConstValue::record_instance(rf, return_values);
+
+                self.pop_scope(index);
+                self.wstate().scope.pop_scope();
+
+                value
+            }
+            (
+                Type::Function {
+                    parameters: ptypes, ..
+                },
+                ConstValueKind::NativeFunction { rf, callback },
+            ) => {
+                let arglen = args.len();
+                let plen = ptypes.len();
+
+                let has_args: Option<LinkedHashMap<_, _>> = args
+                    .into_iter()
+                    .zip(ptypes.into_iter())
+                    .map(|((arg, arg_range), (name, ty))| {
+                        if !self.check_array_length(&arg, &ty, arg_range) {
+                            return None;
+                        }
+                        let arg = arg.try_implicit_cast(&ty).unwrap_or(arg);
+
+                        // Empty values have already reported why they're empty
+                        if arg.ty == Type::Empty {
+                            return None;
+                        }
+                        if arg.ty != ty {
+                            self.add_error(EvaluationError {
+                                kind: EvaluationErrorKind::TypeMismatch(
+                                    arg.ty,
+                                    ty,
+                                    TypeHint::Parameter,
+                                ),
+                                range: arg_range,
+                                backtrace: None,
+                            });
+                            return None;
+                        }
+
+                        Some((name, arg))
+                    })
+                    .collect();
+
+                if has_args.is_none() {
+                    return ConstValue::empty();
+                } else if arglen != plen {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::ArgCountMismatch(arglen as _, plen as _),
+                        range: args_range,
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+
+                let return_vals = callback(has_args.as_ref().unwrap());
+
+                ConstValue::record_instance(rf, return_vals)
+            }
+            // Record is instantiated
+            (Type::Symbol(sym), _) => {
+                let (members, defaults) = match &sym.borrow().value {
+                    ScopeValue::Record {
+                        members, defaults, ..
+                    } => (members.clone(), defaults.clone()),
+                    _ => return ConstValue::empty(),
+                };
+                let arglen = args.len();
+                let mut args_vals: LinkedHashMap<_, _> = members
+                    .iter()
+                    .zip(args.into_iter())
+                    .filter_map(|((name, ty), (arg, arg_range))| {
+                        if !self.check_array_length(&arg, ty, arg_range) {
+                            return None;
+                        }
+                        let arg = arg.try_implicit_cast(ty).unwrap_or(arg);
+                        if &arg.ty == ty {
+                            Some((name.clone(), arg))
+                        } else {
+                            self.add_error(EvaluationError {
+                                kind: EvaluationErrorKind::TypeMismatch(
+                                    arg.ty,
+                                    ty.clone(),
+                                    TypeHint::Parameter,
+                                ),
+                                range: arg_range,
+                                backtrace: None,
+                            });
+                            None
+                        }
+                    })
+                    .collect();
+
+                // Members after the arguments that are given must all have a default
+                let mut left_out = members.keys().skip(arglen);
+                if arglen > members.len() || !left_out.all(|name| defaults.contains_key(name)) {
+                    // If the number of arguments doesn't match the record
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::ArgCountMismatch(
+                            arglen as _,
+                            members.len() as _,
+                        ),
+                        range: args_range,
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                } else if args_vals.len() != arglen {
+                    return ConstValue::empty();
+                }
+
+                for (name, ty) in members.iter().skip(arglen) {
+                    let default = &defaults[name];
+                    let value = self.evaluate_default(default, ty, index);
+                    if value.ty == Type::Empty {
+                        return ConstValue::empty();
+                    }
+                    args_vals.insert(name.clone(), value);
+                }
+
+                // Everything good!
+                ConstValue::record_instance(sym.clone(), args_vals)
+            }
+            // Empty values have already reported why they're empty
+            (Type::Empty, _) => ConstValue::empty(),
+            (ty, _) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NotCallable(ty),
+                    range,
+                    backtrace: None,
+                });
+                ConstValue::empty()
+            }
+        }
+    }
+
+    /// Evaluates the default value of a record member of type `ty`. Defaults are evaluated each
+    /// time the record is instantiated without the member, so they can't be shared by instances.
+    fn evaluate_default(&self, default: &Expression, ty: &Type, index: usize) -> ConstValue {
+        let value = self.evaluate_expression(default, index);
+        // Empty values have already reported why they're empty
+        if value.ty == Type::Empty || !self.check_array_length(&value, ty, default.get_range()) {
+            return ConstValue::empty();
+        }
+        // Untyped integers have to fit the member's integer type
+        if let (Type::CoercibleInteger, Type::Integer { width, signed }) = (&value.ty, ty) {
+            let (min, max) = integer_bounds(*width, *signed);
+            if let Some(n) = value.integer_value().filter(|n| !(min..=max).contains(n)) {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::IntegerOverflow(n.to_string(), ty.clone()),
+                    range: default.get_range(),
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+        }
+        let value = value.try_implicit_cast(ty).unwrap_or(value);
+        if &value.ty != ty {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(value.ty, ty.clone(), TypeHint::Variable),
+                range: default.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+        value
+    }
+
+    /// Evaluates the `requires` condition of a function, returning whether it holds
+    fn evaluate_precondition(&self, requires: &Expression, index: usize) -> bool {
+        let cond = self.evaluate_expression(requires, index);
+        match cond.kind {
+            ConstValueKind::Bool(true) => true,
+            ConstValueKind::Bool(false) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::PreconditionFailed,
+                    range: requires.get_range(),
+                    backtrace: None,
+                });
+                false
+            }
+            // Empty values have already reported why they're empty
+            ConstValueKind::Empty => false,
+            _ => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        cond.ty,
+                        Type::Boolean,
+                        TypeHint::Condition,
+                    ),
+                    range: requires.get_range(),
+                    backtrace: None,
+                });
+                false
+            }
+        }
+    }
+
+    /// Evaluates a call to a builtin function, returning `None` if `name` isn't a builtin
+    fn evaluate_builtin(
+        &self,
+        name: &str,
+        raw_args: &ArgList,
+        range: Range,
+        index: usize,
+    ) -> Option<ConstValue> {
+        let value = match name {
+            "lazy" => self.evaluate_lazy(raw_args),
+            "reduce" => self.evaluate_reduce(raw_args, index),
+            "all" | "any" => self.evaluate_all_any(name, raw_args, index),
+            "min" | "max" => self.evaluate_min_max(name, raw_args, index),
+            "merge" => self.evaluate_merge(raw_args, index),
+            "zip" => self.evaluate_zip(raw_args, index),
+            "contains" | "index_of" => self.evaluate_contains(name, raw_args, index),
+            "join" => self.evaluate_join(raw_args, index),
+            "take" | "drop" => self.evaluate_take_drop(name, raw_args, index),
+            "convert" => self.evaluate_convert(raw_args, index),
+            "swap" => self.evaluate_swap(raw_args, index),
+            "abs_diff" | "saturating_add" | "saturating_sub" => {
+                self.evaluate_integer_pair(name, raw_args, index)
+            }
+            "fill" => self.evaluate_fill(raw_args, index),
+            "generate" => self.evaluate_generate(raw_args, index),
+            "parse_int_radix" => self.evaluate_parse_int_radix(raw_args, index),
+            "panic" | "unreachable" => self.evaluate_panic(name, raw_args, range, index),
+            "cast" | "bitcast" => self.evaluate_integer_cast(name, raw_args, index),
+            "assert_eq" => self.evaluate_assert_eq(raw_args, range, index),
+            "count_ones" | "leading_zeros" | "trailing_zeros" | "parse_int" | "parse_float"
+            | "to_string" | "debug" | "force" | "freeze" | "clone" | "cfg" | "reverse"
+            | "sorted" | "sum" | "product" | "enumerate" => {
+                self.evaluate_unary_builtin(name, raw_args, range, index)
+            }
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Evaluates a call to a builtin that takes a single argument
+    fn evaluate_unary_builtin(
+        &self,
+        name: &str,
+        raw_args: &ArgList,
+        range: Range,
+        index: usize,
+    ) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(arg, arg_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 1),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        match name {
+            "parse_int" | "parse_float" => self.evaluate_parse(name, arg, *arg_range),
+            "to_string" => ConstValue::string(arg.to_display_string(self.float_precision())),
+            "force" => self.evaluate_force(arg.clone(), index),
+            "enumerate" => self.evaluate_enumerate(arg.clone(), *arg_range),
+            "sum" | "product" => self.evaluate_sum_product(name, arg.clone(), *arg_range, range),
+            "reverse" | "sorted" => self.evaluate_reverse_sorted(name, arg.clone(), *arg_range),
+            "freeze" => self.evaluate_freeze(arg, *arg_range),
+            // `clone(value)` copies any value, including the records, tuples and arrays in it, so
+            // assigning to the copy never changes the original. Functions share their body, and a
+            // reference still refers to the same place. Values are already copied whenever
+            // they're bound or passed, so there's nothing more to do.
+            "clone" => arg.clone(),
+            "cfg" => self.evaluate_cfg(arg, *arg_range),
+            "debug" => self.evaluate_debug(arg),
+            _ => self.evaluate_bit_count(name, arg, *arg_range),
+        }
+    }
+
+    /// `parse_int(s)` and `parse_float(s)` return an optional untyped number, which is null when
+    /// the string can't be parsed. `parse_int` fails for anything that doesn't fit in an `i64`
+    /// but is not range checked when it's later given a smaller width, same as an integer
+    /// literal.
+    fn evaluate_parse(&self, name: &str, arg: &ConstValue, arg_range: Range) -> ConstValue {
+        let ConstValueKind::String { string } = &arg.kind else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(
+                    arg.ty.clone(),
+                    Type::String,
+                    TypeHint::Parameter,
+                ),
+                range: arg_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let (parsed, ty) = if name == "parse_int" {
+            let parsed = string
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .map(|value| ConstValue::cinteger(value as u64));
+            (parsed, Type::CoercibleInteger)
+        } else {
+            let parsed = string.trim().parse().ok().map(ConstValue::cfloat);
+            (parsed, Type::CoercibleFloat)
+        };
+        match parsed {
+            Some(value) => ConstValue {
+                ty: Type::Optional(Box::new(ty)),
+                ..value
+            },
+            None => ConstValue::null(ty),
+        }
+    }
+
+    /// `freeze(record)` copies a record instance so that its members, and those of records nested
+    /// in it, can't be assigned to. The binding holding it can still be given a new value.
+    fn evaluate_freeze(&self, arg: &ConstValue, arg_range: Range) -> ConstValue {
+        match arg.freeze() {
+            Some(frozen) => frozen,
+            None => {
+                // Empty values have already reported why they're empty
+                if arg.ty != Type::Empty {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::ExpectedRecord(arg.ty.clone()),
+                        range: arg_range,
+                        backtrace: None,
+                    });
+                }
+                ConstValue::empty()
+            }
+        }
+    }
+
+    /// `cfg(name)` is whether the host turned on the flag `name`, and false for flags it doesn't
+    /// know about
+    fn evaluate_cfg(&self, arg: &ConstValue, arg_range: Range) -> ConstValue {
+        match &arg.kind {
+            ConstValueKind::String { string } => {
+                ConstValue::bool(self.rstate().features.contains(string))
+            }
+            // Empty values have already reported why they're empty
+            ConstValueKind::Empty => ConstValue::empty(),
+            _ => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        arg.ty.clone(),
+                        Type::String,
+                        TypeHint::Parameter,
+                    ),
+                    range: arg_range,
+                    backtrace: None,
+                });
+                ConstValue::empty()
+            }
+        }
+    }
+
+    /// `debug(value)` writes the tree of `value` to the output. Unlike `print`, it shows the type
+    /// and structure of the whole value.
+    fn evaluate_debug(&self, arg: &ConstValue) -> ConstValue {
+        let output = self.rstate().output.clone();
+        let mut output = output.lock().unwrap();
+        let _ = write!(output, "{}", arg.format());
+        let _ = output.flush();
+        ConstValue::empty()
+    }
+
+    /// `enumerate(values)` pairs each element of an array or range with its index, producing an
+    /// array of `(index, element)` tuples
+    fn evaluate_enumerate(&self, values: ConstValue, range: Range) -> ConstValue {
+        let ty = match &values.ty {
+            Type::Array(ty, _) | Type::Range(ty) => Type::clone(ty),
+            ty => ty.clone(),
+        };
+
+        let Some(elements) = values.into_elements() else {
+            if ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NotIterable(ty),
+                    range,
+                    backtrace: None,
+                });
+            }
+            return ConstValue::empty();
+        };
+        if !self.check_iteration_limit(elements.size_hint().0, range) {
+            return ConstValue::empty();
+        }
+
+        let pairs = elements
+            .enumerate()
+            .map(|(i, element)| ConstValue::tuple(vec![ConstValue::cinteger(i as _), element]))
+            .collect();
+
+        ConstValue::array(pairs, Type::Tuple(vec![Type::CoercibleInteger, ty]))
+    }
+
+    /// `reverse(values)` and `sorted(values)` copy the elements of an array or range into a new
+    /// array, reversed or in ascending order, leaving the original as it was. `sorted` orders the
+    /// elements with [`ConstValue::compare`] and keeps equal ones in the order they were in.
+    fn evaluate_reverse_sorted(&self, name: &str, values: ConstValue, range: Range) -> ConstValue {
+        let ty = match &values.ty {
+            Type::Range(ty) => Type::Array(ty.clone(), None),
+            ty => ty.clone(),
+        };
+
+        let Some(elements) = values.into_elements() else {
+            // Empty values have already reported why they're empty
+            if ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NotIterable(ty),
+                    range,
+                    backtrace: None,
+                });
+            }
+            return ConstValue::empty();
+        };
+        if !self.check_iteration_limit(elements.size_hint().0, range) {
+            return ConstValue::empty();
+        }
+
+        let mut elements: Vec<_> = elements.collect();
+        if name == "reverse" {
+            elements.reverse();
+        } else {
+            let mut incomparable = None;
+            elements.sort_by(|l, r| {
+                l.compare(r).unwrap_or_else(|| {
+                    incomparable.get_or_insert((l.ty.clone(), r.ty.clone()));
+                    Ordering::Equal
+                })
+            });
+            if let Some((l, r)) = incomparable {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::IncomparableTypes(l, r),
+                    range,
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+        }
+
+        ConstValue {
+            ty,
+            kind: ConstValueKind::Array(Arc::new(elements)),
+        }
+    }
+
+    /// `sum(values)` and `product(values)` add or multiply the numbers of an array or range,
+    /// giving a number of the element type. They're `0` and `1` when there are no elements.
+    /// Integers are checked for overflow like `+` and `*`, reported at the call, `range`.
+    fn evaluate_sum_product(
+        &self,
+        name: &str,
+        values: ConstValue,
+        values_range: Range,
+        range: Range,
+    ) -> ConstValue {
+        let ty = match &values.ty {
+            Type::Array(ty, _) | Type::Range(ty) => Type::clone(ty),
+            ty => ty.clone(),
+        };
+        let (op, identity) = match name {
+            "sum" => (Operator::Plus, 0),
+            _ => (Operator::Multiply, 1),
+        };
+
+        let Some(elements) = values.into_elements() else {
+            if ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NotIterable(ty),
+                    range: values_range,
+                    backtrace: None,
+                });
+            }
+            return ConstValue::empty();
+        };
+        if !self.check_iteration_limit(elements.size_hint().0, values_range) {
+            return ConstValue::empty();
+        }
+
+        let mut result = match ty {
+            Type::Integer { width, signed } => ConstValue::integer(identity, width, signed),
+            // The elements of an empty array literal have no type
+            Type::CoercibleInteger | Type::Empty => ConstValue::cinteger(identity),
+            Type::Float { width } => ConstValue::float(identity as f64, width),
+            Type::CoercibleFloat => ConstValue::cfloat(identity as f64),
+            ty => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::BinExpMismatch(op, ty.clone(), ty),
+                    range: values_range,
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+        };
+
+        for element in elements {
+            let value = match (&result.ty, &result.kind, &element.kind) {
+                (_, ConstValueKind::Float { value: l }, ConstValueKind::Float { value: r }) => {
+                    match op {
+                        Operator::Plus => l + r,
+                        _ => l * r,
+                    }
+                }
+                (ty, ConstValueKind::Integer { .. }, ConstValueKind::Integer { .. }) => {
+                    let (width, signed) = match ty {
+                        Type::Integer { width, signed } => (*width, *signed),
+                        _ => (64, true),
+                    };
+                    match integer_operation(&op, &result, &element, width, signed) {
+                        Some(Ok(value)) => {
+                            result.kind = ConstValueKind::Integer { value };
+                            continue;
+                        }
+                        Some(Err(value)) => {
+                            self.add_error(EvaluationError {
+                                kind: EvaluationErrorKind::IntegerOverflow(value, result.ty),
+                                range,
+                                backtrace: None,
+                            });
+                            return ConstValue::empty();
+                        }
+                        None => return ConstValue::empty(),
+                    }
+                }
+                // Elements have already been checked against the element type
+                _ => return ConstValue::empty(),
+            };
+            result.kind = ConstValueKind::Float { value };
+        }
+
+        result
+    }
+
+    /// `min(a, b)` and `max(a, b)` return the lesser or greater of two values of any ordered type,
+    /// as ordered by [`ConstValue::compare`]. If they're equal, the first is returned.
+    fn evaluate_min_max(&self, name: &str, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(left, _), (right, _)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let Some(ordering) = left.compare(right) else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::IncomparableTypes(left.ty.clone(), right.ty.clone()),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        match (name, ordering) {
+            ("min", Ordering::Greater) | ("max", Ordering::Less) => right.clone(),
+            _ => left.clone(),
+        }
+    }
+
+    /// `zip(left, right)` pairs the elements of two arrays or ranges, producing an array of
+    /// `(left, right)` tuples. It stops at the end of the shorter one, so the elements left over
+    /// in the longer one are dropped.
+    fn evaluate_zip(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(left, left_range), (right, right_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let elements = |values: &ConstValue, range: Range| {
+            let ty = match &values.ty {
+                Type::Array(ty, _) | Type::Range(ty) => Type::clone(ty),
+                ty => ty.clone(),
+            };
+            let elements = values.clone().into_elements();
+            // Empty values have already reported why they're empty
+            if elements.is_none() && ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NotIterable(ty.clone()),
+                    range,
+                    backtrace: None,
+                });
+            }
+            Some((elements?, ty))
+        };
+        let (Some((left, left_ty)), Some((right, right_ty))) =
+            (elements(left, *left_range), elements(right, *right_range))
+        else {
+            return ConstValue::empty();
+        };
+        let len = left.size_hint().0.min(right.size_hint().0);
+        if !self.check_iteration_limit(len, raw_args.get_range()) {
+            return ConstValue::empty();
+        }
+
+        let pairs = left
+            .zip(right)
+            .map(|(left, right)| ConstValue::tuple(vec![left, right]))
+            .collect();
+
+        ConstValue::array(pairs, Type::Tuple(vec![left_ty, right_ty]))
+    }
+
+    /// `take(values, n)` is an array of the first `n` elements of an array or range, and
+    /// `drop(values, n)` is an array of the ones after them. `n` is clamped to the number of
+    /// elements, and only the elements that end up in the result are produced from a range.
+    fn evaluate_take_drop(&self, name: &str, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(values, values_range), (count, count_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let ty = match &values.ty {
+            Type::Array(ty, _) | Type::Range(ty) => Type::clone(ty),
+            ty => ty.clone(),
+        };
+        let Some(elements) = values.clone().into_elements() else {
+            // Empty values have already reported why they're empty
+            if ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NotIterable(ty),
+                    range: *values_range,
+                    backtrace: None,
+                });
+            }
+            return ConstValue::empty();
+        };
+        let Some(count) = self.evaluate_count(count, *count_range) else {
+            return ConstValue::empty();
+        };
+        // `take` is bounded by the count, but `drop` produces the rest of a range
+        if name == "drop"
+            && !self
+                .check_iteration_limit(elements.size_hint().0.saturating_sub(count), *values_range)
+        {
+            return ConstValue::empty();
+        }
+
+        let elements = if name == "take" {
+            elements.take(count).collect()
+        } else {
+            elements.skip(count).collect()
+        };
+        ConstValue::array(elements, ty)
+    }
+
+    /// `contains(values, value)` is whether an array or range has an element equal to `value`,
+    /// and `index_of(values, value)` is the index of the first one, or null if there's none.
+    /// Elements are compared with [`ConstValue::eq_value`], after `value` is implicitly cast to
+    /// the element type, which it has to have. Ranges are checked against their bounds.
+    fn evaluate_contains(&self, name: &str, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(values, values_range), (value, value_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let ty = match &values.ty {
+            Type::Array(ty, _) | Type::Range(ty) => Type::clone(ty),
+            ty => ty.clone(),
+        };
+        let Some(elements) = values.clone().into_elements() else {
+            // Empty values have already reported why they're empty
+            if ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NotIterable(ty),
+                    range: *values_range,
+                    backtrace: None,
+                });
+            }
+            return ConstValue::empty();
+        };
+        if value.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        // Untyped elements take the type of the value instead
+        let value = value
+            .try_implicit_cast(&ty)
+            .unwrap_or_else(|| value.clone());
+        let comparable = match (&ty, &value.ty) {
+            // Nothing to compare with in an empty array
+            (Type::Empty, _) => true,
+            (Type::CoercibleInteger, Type::Integer { .. })
+            | (Type::CoercibleFloat, Type::Float { .. }) => true,
+            (ty, value_ty) => ty == value_ty,
+        };
+        if !comparable {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::IncomparableTypes(ty, value.ty),
+                range: *value_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        // A range holds every integer between its bounds, so it isn't iterated
+        let position = match (values.range_bounds(), value.integer_value()) {
+            (Some((start, end)), Some(n)) => {
+                (start..end).contains(&n).then(|| (n - start) as usize)
+            }
+            _ => elements
+                .into_iter()
+                .position(|element| element.eq_value(&value)),
+        };
+        match (name, position) {
+            ("contains", position) => ConstValue::bool(position.is_some()),
+            (_, Some(position)) => ConstValue {
+                ty: Type::Optional(Box::new(Type::CoercibleInteger)),
+                ..ConstValue::cinteger(position as u64)
+            },
+            (_, None) => ConstValue::null(Type::CoercibleInteger),
+        }
+    }
+
+    /// `join(strings, separator)` concatenates an array of strings with `separator` between
+    /// each of them, undoing `split`. The elements have to be strings, other values aren't
+    /// converted, so use `to_string` on them first. Joining no strings gives an empty string.
+    fn evaluate_join(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(strings, strings_range), (separator, separator_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let mismatch = |value: &ConstValue, expected: Type, range: Range| {
+            // Empty values have already reported why they're empty
+            if value.ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        value.ty.clone(),
+                        expected,
+                        TypeHint::Parameter,
+                    ),
+                    range,
+                    backtrace: None,
+                });
+            }
+            ConstValue::empty()
+        };
+
+        let (ConstValueKind::Array(values), Type::Array(ty, _)) = (&strings.kind, &strings.ty)
+        else {
+            let expected = Type::Array(Box::new(Type::String), None);
+            return mismatch(strings, expected, *strings_range);
+        };
+        // The elements of an empty array literal have no type
+        if !matches!(ty.as_ref(), Type::String | Type::Empty) {
+            let expected = Type::Array(Box::new(Type::String), None);
+            return mismatch(strings, expected, *strings_range);
+        }
+        let ConstValueKind::String { string: separator } = &separator.kind else {
+            return mismatch(separator, Type::String, *separator_range);
+        };
+
+        let joined = values
+            .iter()
+            .map(|value| value.kind.as_string())
+            .collect::<Vec<_>>()
+            .join(separator);
+        ConstValue::string(joined)
+    }
+
+    /// `merge(base, override)` combines two record instances with [`ConstValue::merge`]
+    fn evaluate_merge(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(base, base_range), (other, other_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        match base.merge(other).map_err(|err| *err) {
+            Ok(value) => value,
+            Err(MergeError::NotRecord(ty)) => {
+                let range = if base.ty == ty {
+                    base_range
+                } else {
+                    other_range
+                };
+                // Empty values have already reported why they're empty
+                if ty != Type::Empty {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::ExpectedRecord(ty),
+                        range: *range,
+                        backtrace: None,
+                    });
+                }
+                ConstValue::empty()
+            }
+            Err(MergeError::FieldMismatch { base, other, .. }) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(other, base, TypeHint::Record),
+                    range: *other_range,
+                    backtrace: None,
+                });
+                ConstValue::empty()
+            }
+        }
+    }
+
+    /// `abs_diff(a, b)`, `saturating_add(a, b)` and `saturating_sub(a, b)` on sized integers of
+    /// the same type, where an untyped integer takes the type of the other argument. The
+    /// saturating operations clamp to the range of the type instead of overflowing. Like in Rust,
+    /// `abs_diff` of signed integers is unsigned, since the difference may not fit the signed type.
+    fn evaluate_integer_pair(&self, name: &str, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(left, left_range), (right, right_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let (width, signed) = match (&left.ty, &right.ty) {
+            // Empty values have already reported why they're empty
+            (Type::Empty, _) | (_, Type::Empty) => return ConstValue::empty(),
+            (Type::Integer { width, signed }, Type::CoercibleInteger)
+            | (Type::CoercibleInteger, Type::Integer { width, signed }) => (*width, *signed),
+            (Type::Integer { width, signed }, ty) if *ty == left.ty => (*width, *signed),
+            (Type::Integer { .. }, Type::Integer { .. }) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        right.ty.clone(),
+                        left.ty.clone(),
+                        TypeHint::Parameter,
+                    ),
+                    range: *right_range,
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+            (Type::Integer { .. }, ty) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ExpectedSizedInteger(ty.clone()),
+                    range: *right_range,
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+            (ty, _) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ExpectedSizedInteger(ty.clone()),
+                    range: *left_range,
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+        };
+
+        let widen = |value: &ConstValue| {
+            let value = value.kind.as_integer();
+            if signed {
+                value as i64 as i128
+            } else {
+                value as i128
+            }
+        };
+        let (l, r) = (widen(left), widen(right));
+        let (min, max) = integer_bounds(width, signed);
+
+        match name {
+            "abs_diff" => ConstValue::integer((l - r).unsigned_abs() as u64, width, false),
+            "saturating_add" => ConstValue::integer((l + r).clamp(min, max) as u64, width, signed),
+            _ => ConstValue::integer((l - r).clamp(min, max) as u64, width, signed),
+        }
+    }
+
+    /// `swap(a, b)` exchanges the values of two bindings, fields or array elements, which must be
+    /// of the same type after implicit casts
+    fn evaluate_swap(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let mut args = raw_args.iter_items();
+        let (Some(raw_a), Some(raw_b), None) = (args.next(), args.next(), args.next()) else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(raw_args.iter_items().count() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let a = self.swap_operand(raw_a, index);
+        let b = self.swap_operand(raw_b, index);
+        let (Some((a_value, a)), Some((b_value, b))) = (a, b) else {
+            // Places that can't be referenced have already been reported
+            return ConstValue::empty();
+        };
+
+        let a_cast = a_value
+            .try_implicit_cast(&b_value.ty)
+            .unwrap_or(a_value.clone());
+        let b_cast = b_value
+            .try_implicit_cast(&a_value.ty)
+            .unwrap_or(b_value.clone());
+        if b_cast.ty != a_value.ty || a_cast.ty != b_value.ty {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(
+                    b_value.ty,
+                    a_value.ty,
+                    TypeHint::Parameter,
+                ),
+                range: raw_b.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        if !self.write_swap_operand(raw_a, a.as_ref(), b_cast, index) {
+            return ConstValue::empty();
+        }
+        if !self.write_swap_operand(raw_b, b.as_ref(), a_cast, index) {
+            // Put the first value back so a swap that fails leaves both places as they were
+            self.write_swap_operand(raw_a, a.as_ref(), a_value, index);
+        }
+        ConstValue::empty()
+    }
+
+    /// The value at an argument of `swap`, and a reference to it unless it's an array element.
+    /// Elements are read like any index, and written like `place[i] = value`.
+    fn swap_operand(
+        &self,
+        place: &Expression,
+        index: usize,
+    ) -> Option<(ConstValue, Option<ConstValue>)> {
+        if let Expression::Index { index: Some(_), .. } = place {
+            let value = self.evaluate_expression(place, index);
+            // Empty values have already reported why they're empty
+            return (value.ty != Type::Empty).then_some((value, None));
+        }
+
+        let reference = self.evaluate_reference(place, index);
+        Some((reference.read_ref()?, Some(reference)))
+    }
+
+    /// Writes `value` to an argument of `swap`, returning whether it could be written
+    fn write_swap_operand(
+        &self,
+        place: &Expression,
+        reference: Option<&ConstValue>,
+        value: ConstValue,
+        index: usize,
+    ) -> bool {
+        match (reference, place) {
+            (Some(reference), _) => reference.write_ref(value),
+            (
+                None,
+                Expression::Index {
+                    expr,
+                    index: Some(raw_index),
+                    ..
+                },
+            ) => {
+                let range = place.get_range();
+                let value = self.evaluate_element_assignment(expr, raw_index, value, range, index);
+                value.ty != Type::Empty
+            }
+            _ => false,
+        }
+    }
+
+    /// `convert(value, Record)` converts a record instance to `Record` with [`ConstValue::convert`]
+    fn evaluate_convert(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(value, value_range), (target, target_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        // Empty values have already reported why they're empty
+        if value.ty == Type::Empty || target.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        let Type::Symbol(sym) = &target.ty else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ExpectedRecord(target.ty.clone()),
+                range: *target_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let (kind, range) = match value.convert(sym).map_err(|err| *err) {
+            Ok(value) => return value,
+            Err(ConvertError::NotRecord(ty)) if ty == value.ty => {
+                (EvaluationErrorKind::ExpectedRecord(ty), value_range)
+            }
+            Err(ConvertError::NotRecord(ty)) => {
+                (EvaluationErrorKind::ExpectedRecord(ty), target_range)
+            }
+            Err(ConvertError::FieldMismatch {
+                expected, found, ..
+            }) => (
+                EvaluationErrorKind::TypeMismatch(found, expected, TypeHint::Record),
+                value_range,
+            ),
+            Err(ConvertError::NoDefaultValue(ty)) => {
+                (EvaluationErrorKind::NoDefaultValue(ty), target_range)
+            }
+        };
+        self.add_error(EvaluationError {
+            kind,
+            range: *range,
+            backtrace: None,
+        });
+        ConstValue::empty()
+    }
+
+    /// `panic(msg)` and `unreachable()` report an error at the call, `range`, and unwind out of
+    /// the module statement being evaluated
+    fn evaluate_panic(
+        &self,
+        name: &str,
+        raw_args: &ArgList,
+        range: Range,
+        index: usize,
+    ) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let message = match (name, args.as_slice()) {
+            ("unreachable", []) => "entered unreachable code".to_string(),
+            ("panic", [(message, _)]) => message.to_display_string(self.float_precision()),
+            (_, args) => {
+                let expected = if name == "panic" { 1 } else { 0 };
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, expected),
+                    range: raw_args.get_range(),
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+        };
+
+        self.add_error(EvaluationError {
+            kind: EvaluationErrorKind::Panic(message),
+            range,
+            backtrace: None,
+        });
+        self.wstate().flow = Some(Flow::Panic);
+        ConstValue::empty()
+    }
+
+    /// `cast(value, T)` converts an integer to the integer type `T` keeping its value, and fails
+    /// if it doesn't fit. `bitcast(value, T)` keeps the bits instead, so `T` has to have the
+    /// same width as the value and only the signedness changes. An untyped integer is taken to
+    /// have the width of `T` and the other signedness.
+    fn evaluate_integer_cast(&self, name: &str, raw_args: &ArgList, index: usize) -> ConstValue {
+        let raw: Vec<_> = raw_args.iter_items().collect();
+        let [raw_value, raw_target] = raw.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(raw.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let value = self.evaluate_expression(raw_value, index);
+        let target = match raw_target {
+            Expression::Ident(SpannedToken(_, Token::Ident(id))) => integer_type_named(id),
+            _ => None,
+        };
+        let Some(target @ Type::Integer { width, signed }) = target else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ExpectedIntegerType,
+                range: raw_target.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        // Empty values have already reported why they're empty
+        if value.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        // What the value has to be for the conversion, and the range of values that fit in it
+        let source = match (name, &value.ty) {
+            ("cast", Type::Integer { .. } | Type::CoercibleInteger) => None,
+            ("bitcast", Type::Integer { width: w, .. }) if *w == width => None,
+            ("bitcast", Type::CoercibleInteger) => Some(Type::Integer {
+                width,
+                signed: !signed,
+            }),
+            (_, ty) => {
+                let expected = match name {
+                    "cast" => Type::CoercibleInteger,
+                    _ => Type::Integer {
+                        width,
+                        signed: !signed,
+                    },
+                };
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        ty.clone(),
+                        expected,
+                        TypeHint::Parameter,
+                    ),
+                    range: raw_value.get_range(),
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+        };
+
+        let Some(n) = value.integer_value() else {
+            return ConstValue::empty();
+        };
+        let fits = match &source {
+            Some(Type::Integer { width, signed }) => integer_bounds(*width, *signed),
+            _ if name == "bitcast" => (i128::MIN, i128::MAX),
+            _ => integer_bounds(width, signed),
+        };
+        if !(fits.0..=fits.1).contains(&n) {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::IntegerOverflow(n.to_string(), source.unwrap_or(target)),
+                range: raw_value.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        // Signed integers are stored sign extended to 64 bits
+        let shift = 64 - width as u32;
+        let bits = (n as u64) << shift;
+        let value = if signed {
+            ((bits as i64) >> shift) as u64
+        } else {
+            bits >> shift
+        };
+        ConstValue::integer(value, width, signed)
+    }
+
+    /// `assert_eq(left, right)` panics like `panic` when its arguments aren't equal, reporting
+    /// each place in them that differs
+    fn evaluate_assert_eq(&self, raw_args: &ArgList, range: Range, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(left, _), (right, _)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        // Empty values have already reported why they're empty
+        if left.ty == Type::Empty || right.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        let differences = left.differences(right);
+        if differences.is_empty() {
+            return ConstValue::empty();
+        }
+
+        let precision = self.float_precision();
+        let differences = differences
+            .into_iter()
+            .map(|(path, left, right)| {
+                (
+                    path,
+                    left.to_display_string(precision),
+                    right.to_display_string(precision),
+                )
+            })
+            .collect();
+        self.add_error(EvaluationError {
+            kind: EvaluationErrorKind::AssertionFailed(differences),
+            range,
+            backtrace: None,
+        });
+        self.wstate().flow = Some(Flow::Panic);
+        ConstValue::empty()
+    }
+
+    /// `parse_int_radix(s, radix)` parses an integer in a base from 2 to 36. Like `parse_int`,
+    /// it's null when a digit isn't valid for the base.
+    fn evaluate_parse_int_radix(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(string, string_range), (radix, radix_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let ConstValueKind::String { string } = &string.kind else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(
+                    string.ty.clone(),
+                    Type::String,
+                    TypeHint::Parameter,
+                ),
+                range: *string_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        let Some(radix) = radix.integer_value() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(
+                    radix.ty.clone(),
+                    Type::CoercibleInteger,
+                    TypeHint::Parameter,
+                ),
+                range: *radix_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        if !(2..=36).contains(&radix) {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::InvalidRadix(radix as i64),
+                range: *radix_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        match u64::from_str_radix(string.trim(), radix as u32) {
+            Ok(value) => ConstValue {
+                ty: Type::Optional(Box::new(Type::CoercibleInteger)),
+                ..ConstValue::cinteger(value)
+            },
+            Err(_) => ConstValue::null(Type::CoercibleInteger),
+        }
+    }
+
+    /// `fill(value, n)` is an array of `n` copies of `value`
+    fn evaluate_fill(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let args = self.evaluate_args(raw_args, index);
+        let [(value, _), (count, count_range)] = args.as_slice() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let Some(count) = self.evaluate_count(count, *count_range) else {
+            return ConstValue::empty();
+        };
+
+        ConstValue::array(vec![value.clone(); count], value.ty.clone())
+    }
+
+    /// `generate(n, f)` is an array of `n` elements where element `i` is `f(i)`. Like `reduce`, a
+    /// function with a single return parameter produces that value rather than a record.
+    fn evaluate_generate(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let raw_func = raw_args.iter_items().nth(1);
+        let args = self.evaluate_args(raw_args, index);
+        let (Some(raw_func), [(count, count_range), (func, _)]) = (raw_func, args.as_slice())
+        else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 2),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let Some(count) = self.evaluate_count(count, *count_range) else {
+            return ConstValue::empty();
+        };
+
+        let range = raw_args.get_range();
+        let mut values: Vec<ConstValue> = Vec::with_capacity(count);
+        for i in 0..count {
+            let args = vec![(ConstValue::cinteger(i as _), *count_range)];
+            let result = self.with_frame(raw_func, range, || {
+                self.evaluate_call(func.clone(), args, range, range, index)
+            });
+            let result = match result.kind {
+                ConstValueKind::RecordInstance { members, .. } if members.len() == 1 => {
+                    Arc::unwrap_or_clone(members).pop_front().unwrap().1
+                }
+                _ => result,
+            };
+
+            // The call has already reported why it failed
+            if let Type::Empty = result.ty {
+                return ConstValue::empty();
+            }
+
+            // Every element takes the type of the first
+            let result = match values.first() {
+                Some(first) => result.try_implicit_cast(&first.ty).unwrap_or(result),
+                None => result,
+            };
+            if let Some(first) = values.first().filter(|first| first.ty != result.ty) {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        result.ty,
+                        first.ty.clone(),
+                        TypeHint::ReturnParameter,
+                    ),
+                    range: raw_func.get_range(),
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+            values.push(result);
+        }
+
+        let ty = values
+            .first()
+            .map(|value| value.ty.clone())
+            .unwrap_or(Type::Empty);
+        ConstValue::array(values, ty)
+    }
+
+    /// The number of elements asked for by `fill`, `generate`, `take` and `drop`, or iterations
+    /// by `repeat`. Counts over the iteration limit are errors, so they're rejected before
+    /// anything is allocated or run.
+    fn evaluate_count(&self, count: &ConstValue, range: Range) -> Option<usize> {
+        // Untyped integers are signed too, so `-1` is negative rather than a huge count
+        match (&count.ty, count.integer_value()) {
+            (_, Some(value)) if value < 0 => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::NegativeCount(value as i64),
+                    range,
+                    backtrace: None,
+                });
+                None
+            }
+            (_, Some(value)) => {
+                let count = usize::try_from(value).unwrap_or(usize::MAX);
+                self.check_iteration_limit(count, range).then_some(count)
+            }
+            // Empty values have already reported why they're empty
+            (Type::Empty, _) => None,
+            (ty, _) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        ty.clone(),
+                        Type::CoercibleInteger,
+                        TypeHint::Parameter,
+                    ),
+                    range,
+                    backtrace: None,
+                });
+                None
+            }
+        }
+    }
+
+    /// `reduce(values, init, f)` folds the elements of an array or range into an accumulator,
+    /// starting from `init`. A function with a single return parameter produces that value
+    /// rather than a record, so it can be passed back in as the next accumulator.
+    fn evaluate_reduce(&self, raw_args: &ArgList, index: usize) -> ConstValue {
+        let raw_func = raw_args.iter_items().nth(2);
+        let args = self.evaluate_args(raw_args, index);
+        let (Some(raw_func), [(values, values_range), (init, _), (func, _)]) =
+            (raw_func, args.as_slice())
+        else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, 3),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        // Empty values have already reported why they're empty
+        if values.ty == Type::Empty || init.ty == Type::Empty {
+            return ConstValue::empty();
+        }
+
+        let Some(elements) = values.clone().into_elements() else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::TypeMismatch(
+                    values.ty.clone(),
+                    Type::Array(Box::new(init.ty.clone()), None),
+                    TypeHint::Parameter,
+                ),
+                range: *values_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+        if !self.check_iteration_limit(elements.size_hint().0, *values_range) {
+            return ConstValue::empty();
+        }
+
+        let range = raw_args.get_range();
+        let mut acc = init.clone();
+        for element in elements {
+            let args = vec![(acc.clone(), range), (element, *values_range)];
+            let result = self.with_frame(raw_func, range, || {
+                self.evaluate_call(func.clone(), args, range, range, index)
+            });
+            let result = match result.kind {
+                ConstValueKind::RecordInstance { members, .. } if members.len() == 1 => {
+                    Arc::unwrap_or_clone(members).pop_front().unwrap().1
+                }
+                _ => result,
+            };
+
+            // The call has already reported why it failed
+            if let Type::Empty = result.ty {
+                return ConstValue::empty();
+            }
+
+            // An untyped initial value takes the type of the first result
+            let result = result.try_implicit_cast(&acc.ty).unwrap_or(result);
+            if result.ty != acc.ty
+                && !matches!(acc.ty, Type::CoercibleInteger | Type::CoercibleFloat)
+            {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        result.ty,
+                        acc.ty,
+                        TypeHint::ReturnParameter,
+                    ),
+                    range: raw_func.get_range(),
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+            acc = result;
+        }
+
+        acc
+    }
+
+    /// `all(checks)` and `any(checks)` combine an array of booleans, or of thunks producing them.
+    /// Checks are evaluated in order up to the first one that decides the result, which is the
+    /// first false for `all` and the first true for `any`. The elements of an array literal are
+    /// only evaluated as they're checked, so `all([a(), b()])` doesn't call `b` if `a` fails.
+    fn evaluate_all_any(&self, name: &str, raw_args: &ArgList, index: usize) -> ConstValue {
+        let mut args = raw_args.iter_items();
+        let (Some(arg), None) = (args.next(), args.next()) else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(raw_args.iter_items().count() as _, 1),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        let checks: Box<dyn Iterator<Item = (ConstValue, Range)>> = match arg {
+            Expression::Array { values, .. } => Box::new(
+                values
+                    .iter_items()
+                    .map(|expr| (self.evaluate_expression(expr, index), expr.get_range())),
+            ),
+            _ => {
+                let value = self.evaluate_expression(arg, index);
+                let range = arg.get_range();
+                match value.kind {
+                    ConstValueKind::Array(values) => {
+                        let values = Arc::unwrap_or_clone(values);
+                        Box::new(values.into_iter().map(move |value| (value, range)))
+                    }
+                    // Empty values have already reported why they're empty
+                    _ if value.ty == Type::Empty => return ConstValue::empty(),
+                    _ => {
+                        self.add_error(EvaluationError {
+                            kind: EvaluationErrorKind::TypeMismatch(
+                                value.ty,
+                                Type::Array(Box::new(Type::Boolean), None),
+                                TypeHint::Parameter,
+                            ),
+                            range,
+                            backtrace: None,
+                        });
+                        return ConstValue::empty();
+                    }
+                }
+            }
+        };
+
+        // `all` stops at the first false and `any` at the first true
+        let decisive = name == "any";
+        for (value, range) in checks {
+            let value = self.evaluate_force(value, index);
+            match value.kind {
+                ConstValueKind::Bool(value) if value == decisive => {
+                    return ConstValue::bool(decisive)
+                }
+                ConstValueKind::Bool(_) => (),
+                _ if value.ty == Type::Empty => return ConstValue::empty(),
+                _ => {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::TypeMismatch(
+                            value.ty,
+                            Type::Boolean,
+                            TypeHint::Element,
+                        ),
+                        range,
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+            }
+        }
+
+        ConstValue::bool(!decisive)
+    }
+
+    /// `lazy(expr)` captures `expr` unevaluated along with the current scopes
+    fn evaluate_lazy(&self, raw_args: &ArgList) -> ConstValue {
+        let mut args = raw_args.iter_items();
+        let (Some(expr), None) = (args.next(), args.next()) else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(raw_args.iter_items().count() as _, 1),
+                range: raw_args.get_range(),
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        ConstValue::thunk(expr.clone(), self.rstate().scope.current_scopes())
+    }
+
+    /// Evaluates a thunk in the scopes it captured. Other values are already evaluated.
+    fn evaluate_force(&self, value: ConstValue, index: usize) -> ConstValue {
+        let ConstValueKind::Thunk { expr, scope } = value.kind else {
+            return value;
+        };
+
+        let outer = self.wstate().scope.swap_scopes(scope);
+        let value = self.evaluate_expression(&expr, index);
+        self.wstate().scope.swap_scopes(outer);
+
+        value
+    }
+
+    /// Evaluates `recv.name(args)` where `name` is one of the string methods: `len`, `upper`,
+    /// `lower`, `contains(sub)` and `split(sep)`. `len` counts the positions of the evaluator's
+    /// [`StringMode`], which are bytes, characters or graphemes, and `split` produces an array of
+    /// strings.
+    fn evaluate_string_method(
+        &self,
+        name: &str,
+        recv: ConstValue,
+        recv_range: Range,
+        args: Vec<(ConstValue, Range)>,
+        args_range: Range,
+    ) -> ConstValue {
+        let ConstValueKind::String { string } = &recv.kind else {
+            // Empty values have already reported why they're empty
+            if recv.ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        recv.ty,
+                        Type::String,
+                        TypeHint::Parameter,
+                    ),
+                    range: recv_range,
+                    backtrace: None,
+                });
+            }
+            return ConstValue::empty();
+        };
+
+        let expected = if matches!(name, "contains" | "split") {
+            1
+        } else {
+            0
+        };
+        if args.len() != expected {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ArgCountMismatch(args.len() as _, expected as _),
+                range: args_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        let arg = match args.first() {
+            Some((
+                ConstValue {
+                    kind: ConstValueKind::String { string },
+                    ..
+                },
+                _,
+            )) => string.as_str(),
+            Some((arg, arg_range)) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::TypeMismatch(
+                        arg.ty.clone(),
+                        Type::String,
+                        TypeHint::Parameter,
+                    ),
+                    range: *arg_range,
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+            None => "",
+        };
+
+        match name {
+            "len" => ConstValue::cinteger(self.rstate().string_mode.len(string) as _),
+            "upper" => ConstValue::string(string.to_uppercase()),
+            "lower" => ConstValue::string(string.to_lowercase()),
+            "contains" => ConstValue::bool(string.contains(arg)),
+            _ => ConstValue::array(
+                string
+                    .split(arg)
+                    .map(|part| ConstValue::string(part.to_string()))
+                    .collect(),
+                Type::String,
+            ),
+        }
+    }
+
+    /// `count_ones(value)`, `leading_zeros(value)` and `trailing_zeros(value)` count bits of an
+    /// integer, giving a `u32`. They only accept integers with a declared width, since the result
+    /// depends on it.
+    fn evaluate_bit_count(&self, name: &str, arg: &ConstValue, arg_range: Range) -> ConstValue {
+        let (ConstValueKind::Integer { value }, Type::Integer { width, .. }) = (&arg.kind, &arg.ty)
+        else {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::ExpectedSizedInteger(arg.ty.clone()),
+                range: arg_range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        };
+
+        // Signed values are stored sign extended, so only look at the declared bits
+        let value = if *width >= 64 {
+            *value
+        } else {
+            value & ((1 << width) - 1)
+        };
+
+        let count = match name {
+            "count_ones" => value.count_ones(),
+            "leading_zeros" => value.leading_zeros() - (64 - *width as u32),
+            _ => value.trailing_zeros().min(*width as u32),
+        };
+
+        ConstValue::integer(count as _, 32, false)
+    }
+
+    /// Evaluates `left op right`. Integers of different types are promoted to the narrowest type
+    /// that holds every value of both before they're operated on or compared:
+    ///
+    /// | operands                          | promoted to                   | e.g.                 |
+    /// |-----------------------------------|-------------------------------|----------------------|
+    /// | same signedness                   | the wider                     | `i32 + i64` is `i64` |
+    /// | signed, and narrower unsigned     | the signed                    | `i32 + u8` is `i32`  |
+    /// | unsigned, and signed no wider     | signed, twice unsigned width  | `u32 + i32` is `i64` |
+    /// | `u64`, and any signed             | none, an error                | `u64 + i64`          |
+    pub fn evaluate_binary_expression(
+        &self,
+        raw_left: &Expression,
         op: &Operator,
         raw_right: &Expression,
         index: usize,
     ) -> ConstValue {
         match (op, raw_left) {
-            (Operator::Equals, Expression::Ident(SpannedToken(_, Token::Ident(name)))) => {
+            (Operator::Equals, Expression::Ident(tok @ SpannedToken(_, Token::Ident(name)))) => {
+                if !self.check_assignable(name, tok.get_range()) {
+                    return ConstValue::empty();
+                }
                 let right = self.evaluate_expression(raw_right, index);
                 self.wstate().scope.update_value(
                     name,
@@ -422,123 +4253,355 @@ impl Evaluator {
             }
             (
                 Operator::Equals,
-                Expression::BinaryExpression {
-                    op_token: Some(SpannedToken(_, Token::Operator(Operator::Dot))),
-                    left: Some(dleft),
-                    right: Some(dright),
+                Expression::BinaryExpression {
+                    op_token: Some(SpannedToken(_, Token::Operator(Operator::Dot))),
+                    left: Some(dleft),
+                    right: Some(dright),
+                },
+            ) => {
+                // Assigning to a member assigns to the binding it's accessed from
+                let mut target = dleft.as_ref();
+                while let Expression::BinaryExpression {
+                    left: Some(left), ..
+                } = target
+                {
+                    target = left;
+                }
+                if let Expression::Ident(tok @ SpannedToken(_, Token::Ident(name))) = target {
+                    if !self.check_assignable(name, tok.get_range()) {
+                        return ConstValue::empty();
+                    }
+                    let (_, path) = place_path(raw_left);
+                    if !self.check_mutable_member(name, &path, raw_left.get_range()) {
+                        return ConstValue::empty();
+                    }
+                }
+
+                let right = self.evaluate_expression(raw_right, index);
+                let scope = &mut self.wstate().scope;
+                let updated_value = scope.follow_member_access_mut(dleft, dright, |cv| {
+                    *cv = right.clone();
+                });
+                if !updated_value {
+                    return ConstValue::empty();
+                };
+                return right;
+            }
+            (
+                Operator::Equals,
+                Expression::BinaryExpression {
+                    op_token: Some(SpannedToken(_, Token::Operator(Operator::Multiply))),
+                    left: None,
+                    right: Some(raw_reference),
+                },
+            ) => {
+                let reference = self.evaluate_expression(raw_reference, index);
+                let right = self.evaluate_expression(raw_right, index);
+
+                let Type::Ref(ty) = &reference.ty else {
+                    self.not_a_reference(reference, raw_reference.get_range());
+                    return ConstValue::empty();
+                };
+                // Empty values have already reported why they're empty
+                if right.ty == Type::Empty {
+                    return ConstValue::empty();
+                }
+                let right = right.try_implicit_cast(ty).unwrap_or(right);
+                if right.ty != **ty {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::TypeMismatch(
+                            right.ty,
+                            (**ty).clone(),
+                            TypeHint::Variable,
+                        ),
+                        range: raw_right.get_range(),
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+                // The referenced binding or field may no longer hold a value
+                if !reference.write_ref(right.clone()) {
+                    self.not_a_reference(reference, raw_reference.get_range());
+                    return ConstValue::empty();
+                }
+
+                return right;
+            }
+            (
+                Operator::Equals,
+                Expression::Index {
+                    expr: place,
+                    index: Some(raw_index),
+                    ..
                 },
             ) => {
                 let right = self.evaluate_expression(raw_right, index);
-                let scope = &mut self.wstate().scope;
-                let updated_value = scope.follow_member_access_mut(dleft, dright, |cv| {
-                    *cv = right.clone();
-                });
-                if !updated_value {
+                return self.evaluate_element_assignment(
+                    place,
+                    raw_index,
+                    right,
+                    raw_right.get_range(),
+                    index,
+                );
+            }
+            (Operator::LogicalAnd | Operator::LogicalOr, _) => {
+                let left = self.evaluate_expression(raw_left, index);
+
+                // Short circuit before evaluating the right side
+                match (op, &left.kind) {
+                    (Operator::LogicalAnd, ConstValueKind::Bool(false)) => return left,
+                    (Operator::LogicalOr, ConstValueKind::Bool(true)) => return left,
+                    (_, ConstValueKind::Bool(_)) => (),
+                    _ => {
+                        self.not_a_bool(left, raw_left.get_range());
+                        return ConstValue::empty();
+                    }
+                }
+
+                let right = self.evaluate_expression(raw_right, index);
+                if right.ty != Type::Boolean {
+                    self.not_a_bool(right, raw_right.get_range());
                     return ConstValue::empty();
-                };
+                }
                 return right;
             }
+            (Operator::Pipe, _) => {
+                // `x |> f(args)` is `f(x, args)`, and `x |> f` is `f(x)`
+                let left = (
+                    self.evaluate_expression(raw_left, index),
+                    raw_left.get_range(),
+                );
+                let range = Range::from((&raw_left.get_range(), &raw_right.get_range()));
+
+                let (func, args, args_range) = match raw_right {
+                    Expression::FunctionCall {
+                        expr,
+                        args: raw_args,
+                    } => {
+                        let mut args = vec![left];
+                        args.extend(self.evaluate_args(raw_args, index));
+                        (expr.as_ref(), args, raw_args.get_range())
+                    }
+                    expr => (expr, vec![left], raw_left.get_range()),
+                };
+                let callee = func;
+                let func = self.evaluate_expression(callee, index);
+
+                return self.with_frame(callee, range, || {
+                    self.evaluate_call(func, args, args_range, range, index)
+                });
+            }
             (Operator::Dot, _) => {
                 let left = self.evaluate_expression(raw_left, index);
-                match (left.kind, raw_right) {
-                    (
-                        ConstValueKind::RecordInstance { members, .. },
-                        Expression::Ident(SpannedToken(_, Token::Ident(member))),
-                    ) => {
-                        if let Some(val) = members.get(member) {
-                            return val.clone();
-                        }
-                    }
-                    _ => (),
+                // Empty values have already reported why they're empty, and evaluating the left
+                // side again below would report it twice
+                if left.ty == Type::Empty {
+                    return ConstValue::empty();
+                }
+                // Optionals are unwrapped, unless they're null
+                if let ConstValueKind::Null = left.kind {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::NullAccess(left.ty),
+                        range: raw_left.get_range(),
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+                let left = left.unwrap_optional();
+                let ty = left.ty.clone();
+                let is_record = matches!(left.kind, ConstValueKind::RecordInstance { .. });
+                if let Some(value) = self.evaluate_member(left, raw_left, raw_right, index) {
+                    return value;
+                }
+                if let (true, Expression::Ident(tok @ SpannedToken(_, Token::Ident(name)))) =
+                    (is_record, raw_right)
+                {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::MissingField(name.to_string(), ty),
+                        range: tok.get_range(),
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                }
+            }
+            (Operator::QuestionDot, _) => {
+                // `a?.b` is null when `a` is, without evaluating `b`. Otherwise it's `a.b`, which is
+                // optional if `a` was.
+                let left = self.evaluate_expression(raw_left, index);
+                if let ConstValueKind::Null = left.kind {
+                    return ConstValue::null(Type::Empty);
                 }
+                let optional = matches!(left.ty, Type::Optional(_));
+
+                let Some(value) = self.evaluate_member(
+                    left.clone().unwrap_optional(),
+                    raw_left,
+                    raw_right,
+                    index,
+                ) else {
+                    let right = self.evaluate_expression(raw_right, index);
+                    // Empty values have already reported why they're empty
+                    if left.ty != Type::Empty && right.ty != Type::Empty {
+                        self.add_error(EvaluationError {
+                            kind: EvaluationErrorKind::BinExpMismatch(
+                                op.clone(),
+                                left.ty,
+                                right.ty,
+                            ),
+                            range: Range::from((&raw_left.get_range(), &raw_right.get_range())),
+                            backtrace: None,
+                        });
+                    }
+                    return ConstValue::empty();
+                };
+
+                return match value.ty {
+                    ty @ (Type::Optional(_) | Type::Empty) => ConstValue { ty, ..value },
+                    ty if optional => ConstValue {
+                        ty: Type::Optional(Box::new(ty)),
+                        ..value
+                    },
+                    ty => ConstValue { ty, ..value },
+                };
             }
             _ => (),
         }
         let left = self.evaluate_expression(raw_left, index);
         let right = self.evaluate_expression(raw_right, index);
 
+        // `%` is the remainder of truncating division, so it takes the sign of the left side like
+        // in Rust. This holds for floats too, rather than using `rem_euclid`. Integer division by
+        // zero is an error, while for floats it gives infinity or NaN.
         let res = match (&left.ty, &right.ty) {
-            (Type::CoercibleInteger, Type::CoercibleInteger) => match op {
-                Operator::Plus => {
-                    ConstValue::cinteger(left.kind.as_integer() + right.kind.as_integer())
-                }
-                Operator::Minus => {
-                    ConstValue::cinteger(left.kind.as_integer() - right.kind.as_integer())
+            (
+                Type::CoercibleInteger | Type::Integer { .. },
+                Type::CoercibleInteger | Type::Integer { .. },
+            ) if matches!(op, Operator::Divide | Operator::Modulo)
+                && right.kind.as_integer() == 0 =>
+            {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::DivisionByZero,
+                    range: raw_right.get_range(),
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+            (
+                Type::CoercibleInteger | Type::Integer { .. },
+                Type::CoercibleInteger | Type::Integer { .. },
+            ) if op.is_range() => {
+                let ty = match (&left.ty, &right.ty) {
+                    (Type::CoercibleInteger, ty) | (ty, Type::CoercibleInteger) => Some(ty),
+                    (l, r) => (l == r).then_some(l),
+                };
+                // Untyped bounds have to fit the other bound's integer type
+                if let Some(ty @ Type::Integer { width, signed }) = ty {
+                    let (min, max) = integer_bounds(*width, *signed);
+                    for (value, raw) in [(&left, raw_left), (&right, raw_right)] {
+                        let Some(n) = value.integer_value().filter(|n| {
+                            value.ty == Type::CoercibleInteger && !(min..=max).contains(n)
+                        }) else {
+                            continue;
+                        };
+                        self.add_error(EvaluationError {
+                            kind: EvaluationErrorKind::IntegerOverflow(n.to_string(), ty.clone()),
+                            range: raw.get_range(),
+                            backtrace: None,
+                        });
+                        return ConstValue::empty();
+                    }
                 }
-                Operator::Multiply => {
-                    ConstValue::cinteger(left.kind.as_integer() * right.kind.as_integer())
+                match ty {
+                    Some(ty) => ConstValue::range(
+                        left.kind.as_integer(),
+                        right.kind.as_integer(),
+                        *op == Operator::RangeInclusive,
+                        ty.clone(),
+                    ),
+                    None => ConstValue::empty(),
                 }
-                Operator::Divide => {
-                    ConstValue::cinteger(left.kind.as_integer() / right.kind.as_integer())
+            }
+            // Numbers can be compared when they could be added, so untyped numbers take the type of
+            // the other side but integers and floats aren't compared with each other
+            (
+                Type::CoercibleInteger
+                | Type::Integer { .. }
+                | Type::CoercibleFloat
+                | Type::Float { .. },
+                Type::CoercibleInteger
+                | Type::Integer { .. }
+                | Type::CoercibleFloat
+                | Type::Float { .. },
+            ) if op.is_comparison() => {
+                let compatible = match (&left.ty, &right.ty) {
+                    (Type::CoercibleInteger, Type::CoercibleInteger | Type::Integer { .. })
+                    | (Type::Integer { .. }, Type::CoercibleInteger)
+                    | (Type::CoercibleFloat, Type::CoercibleFloat | Type::Float { .. })
+                    | (Type::Float { .. }, Type::CoercibleFloat) => true,
+                    (l, r) => l == r || promoted_integer_type(l, r).is_some(),
+                };
+                if !compatible {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::TypeMismatch(
+                            right.ty,
+                            left.ty,
+                            TypeHint::Operand,
+                        ),
+                        range: raw_right.get_range(),
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
                 }
-                Operator::Exponent => {
-                    ConstValue::cinteger(left.kind.as_integer().pow(right.kind.as_integer() as _))
+
+                match left.compare(&right) {
+                    Some(ordering) => ConstValue::bool(ordering_satisfies(op, ordering)),
+                    // NaN isn't equal to anything, including itself
+                    None => ConstValue::bool(*op == Operator::NotEquals),
                 }
-                _ => ConstValue::empty(),
-            },
-            (Type::Integer { width, signed }, Type::CoercibleInteger)
-            | (Type::CoercibleInteger, Type::Integer { width, signed }) => match op {
-                Operator::Plus => ConstValue::integer(
-                    left.kind.as_integer() + right.kind.as_integer(),
-                    *width,
-                    *signed,
-                ),
-                Operator::Minus => ConstValue::integer(
-                    left.kind.as_integer() - right.kind.as_integer(),
-                    *width,
-                    *signed,
-                ),
-                Operator::Multiply => ConstValue::integer(
-                    left.kind.as_integer() * right.kind.as_integer(),
-                    *width,
-                    *signed,
-                ),
-                Operator::Divide => ConstValue::integer(
-                    left.kind.as_integer() / right.kind.as_integer(),
-                    *width,
-                    *signed,
-                ),
-                Operator::Exponent => ConstValue::integer(
-                    left.kind.as_integer().pow(right.kind.as_integer() as _),
-                    *width,
-                    *signed,
-                ),
-                _ => ConstValue::empty(),
-            },
+            }
             (
-                Type::Integer { width, signed },
-                Type::Integer {
-                    width: rw,
-                    signed: rs,
-                },
-            ) if width == rw && signed == rs => match op {
-                Operator::Plus => ConstValue::integer(
-                    left.kind.as_integer() + right.kind.as_integer(),
-                    *width,
-                    *signed,
-                ),
-                Operator::Minus => ConstValue::integer(
-                    left.kind.as_integer() - right.kind.as_integer(),
-                    *width,
-                    *signed,
-                ),
-                Operator::Multiply => ConstValue::integer(
-                    left.kind.as_integer() * right.kind.as_integer(),
-                    *width,
-                    *signed,
-                ),
-                Operator::Divide => ConstValue::integer(
-                    left.kind.as_integer() / right.kind.as_integer(),
-                    *width,
-                    *signed,
-                ),
-                Operator::Exponent => ConstValue::integer(
-                    left.kind.as_integer().pow(right.kind.as_integer() as _),
-                    *width,
-                    *signed,
-                ),
-                _ => ConstValue::empty(),
-            },
+                Type::CoercibleInteger | Type::Integer { .. },
+                Type::CoercibleInteger | Type::Integer { .. },
+            ) => {
+                // Untyped integers take the type of the other side, and are `i64`s otherwise
+                let ty = match (&left.ty, &right.ty) {
+                    (Type::CoercibleInteger, ty) | (ty, Type::CoercibleInteger) => ty.clone(),
+                    (l, r) => promoted_integer_type(l, r).unwrap_or(Type::Empty),
+                };
+                let (width, signed) = match ty {
+                    Type::Integer { width, signed } => (width, signed),
+                    _ => (64, true),
+                };
+
+                match integer_operation(op, &left, &right, width, signed) {
+                    _ if ty == Type::Empty => ConstValue::empty(),
+                    Some(Ok(value)) => ConstValue {
+                        ty,
+                        kind: ConstValueKind::Integer { value },
+                    },
+                    Some(Err(value)) => {
+                        self.add_error(EvaluationError {
+                            kind: EvaluationErrorKind::IntegerOverflow(value, ty),
+                            range: Range::from((&raw_left.get_range(), &raw_right.get_range())),
+                            backtrace: None,
+                        });
+                        return ConstValue::empty();
+                    }
+                    None => ConstValue::empty(),
+                }
+            }
+            (Type::Boolean, Type::Boolean) => {
+                let (l, r) = (left.kind.as_bool(), right.kind.as_bool());
+                match op {
+                    Operator::BitAnd => ConstValue::bool(l & r),
+                    Operator::BitOr => ConstValue::bool(l | r),
+                    // Logical xor
+                    Operator::BitXor => ConstValue::bool(l ^ r),
+                    Operator::EqualsEquals => ConstValue::bool(l == r),
+                    Operator::NotEquals => ConstValue::bool(l != r),
+                    _ => ConstValue::empty(),
+                }
+            }
             (Type::CoercibleFloat, Type::CoercibleFloat) => match op {
                 Operator::Plus => ConstValue::cfloat(left.kind.as_float() + right.kind.as_float()),
                 Operator::Minus => ConstValue::cfloat(left.kind.as_float() - right.kind.as_float()),
@@ -548,6 +4611,9 @@ impl Evaluator {
                 Operator::Divide => {
                     ConstValue::cfloat(left.kind.as_float() / right.kind.as_float())
                 }
+                Operator::Modulo => {
+                    ConstValue::cfloat(left.kind.as_float() % right.kind.as_float())
+                }
                 Operator::Exponent => {
                     ConstValue::cfloat(left.kind.as_float().powf(right.kind.as_float()))
                 }
@@ -567,11 +4633,87 @@ impl Evaluator {
                 Operator::Divide => {
                     ConstValue::float(left.kind.as_float() / right.kind.as_float(), *width)
                 }
+                Operator::Modulo => {
+                    ConstValue::float(left.kind.as_float() % right.kind.as_float(), *width)
+                }
                 Operator::Exponent => {
                     ConstValue::float(left.kind.as_float().powf(right.kind.as_float()), *width)
                 }
                 _ => ConstValue::empty(),
             },
+            (Type::Optional(_), _) | (_, Type::Optional(_)) if op.is_comparison() => {
+                let Some(ordering) = left.compare(&right) else {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::IncomparableTypes(left.ty, right.ty),
+                        range: Range::from((&raw_left.get_range(), &raw_right.get_range())),
+                        backtrace: None,
+                    });
+                    return ConstValue::empty();
+                };
+
+                ConstValue::bool(ordering_satisfies(op, ordering))
+            }
+            // Tuples of different lengths are unequal, but can't be ordered
+            (Type::Tuple(_), Type::Tuple(_)) if op.is_comparison() => match op {
+                Operator::EqualsEquals => ConstValue::bool(left.eq_value(&right)),
+                Operator::NotEquals => ConstValue::bool(!left.eq_value(&right)),
+                _ => {
+                    let Some(ordering) = left.compare(&right) else {
+                        self.add_error(EvaluationError {
+                            kind: EvaluationErrorKind::IncomparableTypes(left.ty, right.ty),
+                            range: Range::from((&raw_left.get_range(), &raw_right.get_range())),
+                            backtrace: None,
+                        });
+                        return ConstValue::empty();
+                    };
+
+                    ConstValue::bool(ordering_satisfies(op, ordering))
+                }
+            },
+            (Type::String, Type::String) => {
+                // Rust orders `str` by its UTF-8 bytes, which is the same as ordering by
+                // Unicode scalar values.
+                let (l, r) = (left.kind.as_string(), right.kind.as_string());
+                match op {
+                    Operator::Plus => ConstValue::string(format!("{l}{r}")),
+                    Operator::LessThan => ConstValue::bool(l < r),
+                    Operator::GreaterThan => ConstValue::bool(l > r),
+                    Operator::LessThanEqual => ConstValue::bool(l <= r),
+                    Operator::GreaterThanEqual => ConstValue::bool(l >= r),
+                    Operator::EqualsEquals => ConstValue::bool(l == r),
+                    Operator::NotEquals => ConstValue::bool(l != r),
+                    _ => ConstValue::empty(),
+                }
+            }
+            // Only strings are concatenated, anything else has to be converted with `to_string`
+            (Type::String, _) | (_, Type::String) if *op == Operator::Plus => {
+                let (value, range) = if left.ty == Type::String {
+                    (right, raw_right.get_range())
+                } else {
+                    (left, raw_left.get_range())
+                };
+                // Already reported
+                if value.ty != Type::Empty {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::TypeMismatch(
+                            value.ty,
+                            Type::String,
+                            TypeHint::Operand,
+                        ),
+                        range,
+                        backtrace: None,
+                    });
+                }
+                return ConstValue::empty();
+            }
+            (Type::String, _) | (_, Type::String) if op.is_comparison() => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::IncomparableTypes(left.ty, right.ty),
+                    range: Range::from((&raw_left.get_range(), &raw_right.get_range())),
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
             (Type::Float { width }, Type::Float { width: rw }) if width == rw => match op {
                 Operator::Plus => {
                     ConstValue::float(left.kind.as_float() + right.kind.as_float(), *width)
@@ -585,28 +4727,257 @@ impl Evaluator {
                 Operator::Divide => {
                     ConstValue::float(left.kind.as_float() / right.kind.as_float(), *width)
                 }
+                Operator::Modulo => {
+                    ConstValue::float(left.kind.as_float() % right.kind.as_float(), *width)
+                }
                 Operator::Exponent => {
                     ConstValue::float(left.kind.as_float().powf(right.kind.as_float()), *width)
                 }
                 _ => ConstValue::empty(),
             },
+            // Mixed integer and float exponents are promoted to the float's type
+            (
+                Type::Integer { .. } | Type::CoercibleInteger,
+                ty @ (Type::Float { .. } | Type::CoercibleFloat),
+            )
+            | (
+                ty @ (Type::Float { .. } | Type::CoercibleFloat),
+                Type::Integer { .. } | Type::CoercibleInteger,
+            ) if *op == Operator::Exponent => {
+                let as_float = |value: &ConstValue| match value.integer_value() {
+                    Some(value) => value as f64,
+                    None => value.kind.as_float(),
+                };
+                let value = as_float(&left).powf(as_float(&right));
+                match ty {
+                    Type::Float { width } => ConstValue::float(value, *width),
+                    _ => ConstValue::cfloat(value),
+                }
+            }
             _ => ConstValue::empty(),
         };
 
         if let Type::Empty = &res.ty {
-            self.add_error(EvaluationError {
-                kind: EvaluationErrorKind::BinExpMismatch(op.clone(), left.ty, right.ty),
-                range: Range::from((&raw_left.get_range(), &raw_right.get_range())),
-            });
+            // Empty values have already reported why they're empty
+            if left.ty != Type::Empty && right.ty != Type::Empty {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::BinExpMismatch(op.clone(), left.ty, right.ty),
+                    range: Range::from((&raw_left.get_range(), &raw_right.get_range())),
+                    backtrace: None,
+                });
+            }
             ConstValue::empty()
         } else {
             res
         }
     }
 
-    fn evaluate_args(&self, args: &ArgList, index: usize) -> Vec<ConstValue> {
+    /// Evaluates an integer literal with a type suffix, like `255u8` or `2f32`. A `negated`
+    /// literal is the operand of a prefix `-`, which it's evaluated with, so that the smallest
+    /// value of a signed type like `-128i8` can be written.
+    fn evaluate_suffixed_integer(
+        &self,
+        value: u64,
+        suffix: &xlang_core::ast::Type,
+        negated: bool,
+        range: Range,
+    ) -> ConstValue {
+        let ty = self.evaluate_type(suffix);
+        let max = match ty {
+            Type::Integer { signed: false, .. } if negated => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::UnaryExpMismatch(Operator::Minus, ty),
+                    range,
+                    backtrace: None,
+                });
+                return ConstValue::empty();
+            }
+            Type::Integer { width, signed } if width < 64 || signed => {
+                u64::MAX >> (64 - width as u32 + signed as u32)
+            }
+            Type::Integer { .. } => u64::MAX,
+            Type::Float { width } if negated => return ConstValue::float(-(value as f64), width),
+            Type::Float { width } => return ConstValue::float(value as f64, width),
+            _ => return ConstValue::empty(),
+        };
+
+        if value > max + negated as u64 {
+            let value = if negated {
+                format!("-{value}")
+            } else {
+                value.to_string()
+            };
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::IntegerOverflow(value, ty),
+                range,
+                backtrace: None,
+            });
+            return ConstValue::empty();
+        }
+
+        let value = if negated {
+            (value as i64).wrapping_neg() as u64
+        } else {
+            value
+        };
+        ConstValue {
+            ty,
+            kind: ConstValueKind::Integer { value },
+        }
+    }
+
+    /// Evaluates `left.right` for an already evaluated `left`: a method call, a record member or
+    /// a tuple index. Returns `None` if `right` isn't a member of `left`.
+    fn evaluate_member(
+        &self,
+        left: ConstValue,
+        raw_left: &Expression,
+        raw_right: &Expression,
+        index: usize,
+    ) -> Option<ConstValue> {
+        // Method call: `recv.method(args)` is `method(recv, args)`
+        if let Expression::FunctionCall {
+            expr: method,
+            args: raw_args,
+        } = raw_right
+        {
+            if let Expression::Ident(SpannedToken(_, Token::Ident(name))) = method.as_ref() {
+                // Like builtins, string methods can be shadowed by user symbols
+                if is_string_method(name) && self.rstate().scope.find_symbol(name).is_none() {
+                    let args = self.evaluate_args(raw_args, index);
+                    return Some(self.evaluate_string_method(
+                        name,
+                        left,
+                        raw_left.get_range(),
+                        args,
+                        raw_args.get_range(),
+                    ));
+                }
+            }
+
+            let func = self.evaluate_expression(method, index);
+
+            let mut args = vec![(left, raw_left.get_range())];
+            args.extend(self.evaluate_args(raw_args, index));
+
+            let range = Range::from((&raw_left.get_range(), &raw_right.get_range()));
+            return Some(self.with_frame(method, range, || {
+                self.evaluate_call(
+                    func,
+                    args,
+                    Range::from((&raw_left.get_range(), &raw_args.get_range())),
+                    range,
+                    index,
+                )
+            }));
+        }
+
+        if let Expression::Ident(SpannedToken(_, Token::Ident(name))) = raw_right {
+            let has_member = matches!(
+                &left.kind,
+                ConstValueKind::RecordInstance { members, .. } if members.contains_key(name)
+            );
+            let call = match self.rstate().property_calls {
+                PropertyCalls::Disabled => false,
+                PropertyCalls::MembersFirst => !has_member,
+                PropertyCalls::MethodsFirst => true,
+            };
+            if call {
+                if let Some(value) =
+                    self.evaluate_property_call(&left, name, raw_left, raw_right, index)
+                {
+                    return Some(value);
+                }
+            }
+        }
+
+        match (left.kind, raw_right) {
+            (
+                ConstValueKind::RecordInstance { members, .. },
+                Expression::Ident(SpannedToken(_, Token::Ident(member))),
+            ) => members.get(member).cloned(),
+            (ConstValueKind::Tuple(mut values, _), Expression::Integer(tindex, _, _, _))
+                if (*tindex as usize) < values.len() =>
+            {
+                Some(values.swap_remove(*tindex as usize))
+            }
+            (
+                ConstValueKind::Tuple(mut values, labels),
+                Expression::Ident(SpannedToken(_, Token::Ident(label))),
+            ) => match labels.iter().position(|l| l.as_ref() == Some(label)) {
+                Some(position) => Some(values.swap_remove(position)),
+                None => {
+                    self.add_error(EvaluationError {
+                        kind: EvaluationErrorKind::InvalidTupleLabel(label.clone(), left.ty),
+                        range: raw_right.get_range(),
+                        backtrace: None,
+                    });
+                    Some(ConstValue::empty())
+                }
+            },
+            (_, Expression::Integer(tindex, _, _, _)) => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::InvalidTupleIndex(*tindex, left.ty),
+                    range: raw_right.get_range(),
+                    backtrace: None,
+                });
+                Some(ConstValue::empty())
+            }
+            _ => None,
+        }
+    }
+
+    /// Calls `name` as a method of `left` with no arguments, for `left.name` written without
+    /// parentheses. Returns `None` if there's no such method, either a string method or a
+    /// function with a single parameter.
+    fn evaluate_property_call(
+        &self,
+        left: &ConstValue,
+        name: &str,
+        raw_left: &Expression,
+        raw_right: &Expression,
+        index: usize,
+    ) -> Option<ConstValue> {
+        let range = Range::from((&raw_left.get_range(), &raw_right.get_range()));
+        let sym = self.rstate().scope.find_symbol(name);
+        let Some(sym) = sym else {
+            if left.ty == Type::String && matches!(name, "len" | "upper" | "lower") {
+                return Some(self.evaluate_string_method(
+                    name,
+                    left.clone(),
+                    raw_left.get_range(),
+                    Vec::new(),
+                    range,
+                ));
+            }
+            return None;
+        };
+
+        let takes_one = match &sym.borrow().value {
+            ScopeValue::ConstValue(ConstValue {
+                ty: Type::Function { parameters, .. },
+                kind: ConstValueKind::Function { .. },
+            }) => parameters.len() == 1,
+            ScopeValue::ConstValue(ConstValue {
+                kind: ConstValueKind::Native { arity, .. },
+                ..
+            }) => *arity == Some(1),
+            _ => false,
+        };
+        if !takes_one {
+            return None;
+        }
+
+        let func = self.evaluate_expression(raw_right, index);
+        let args = vec![(left.clone(), raw_left.get_range())];
+        Some(self.with_frame(raw_right, range, || {
+            self.evaluate_call(func, args, range, range, index)
+        }))
+    }
+
+    fn evaluate_args(&self, args: &ArgList, index: usize) -> Vec<(ConstValue, Range)> {
         args.iter_items()
-            .map(|expr| self.evaluate_expression(expr, index))
+            .map(|expr| (self.evaluate_expression(expr, index), expr.get_range()))
             .collect()
     }
 
@@ -617,20 +4988,287 @@ impl Evaluator {
                 signed: *signed,
             },
             xlang_core::ast::Type::Float { width, .. } => Type::Float { width: *width },
-            xlang_core::ast::Type::Ident(id) => {
-                if let Some(sym) = self.rstate().scope.find_symbol(id.as_str()) {
-                    return Type::Symbol(sym);
+            xlang_core::ast::Type::Boolean(_) => Type::Boolean,
+            xlang_core::ast::Type::String(_) => Type::String,
+            xlang_core::ast::Type::Optional { ty, .. } => {
+                Type::Optional(Box::new(self.evaluate_type(ty)))
+            }
+            xlang_core::ast::Type::Array { ty, len, .. } => {
+                let len = match len {
+                    Some(SpannedToken(_, Token::Integer(len))) => Some(*len as usize),
+                    _ => None,
+                };
+                Type::Array(Box::new(self.evaluate_type(ty)), len)
+            }
+            xlang_core::ast::Type::Ref { ty, .. } => Type::Ref(Box::new(self.evaluate_type(ty))),
+            xlang_core::ast::Type::Ident(id, node) => {
+                let cached = self.rstate().type_cache.get(node).map(|(_, ty)| ty.clone());
+                if let Some(cached) = cached {
+                    return cached;
+                }
+
+                let sym = self.rstate().scope.find_symbol(id.as_str());
+                if let Some(sym) = sym {
+                    let ty = Type::Symbol(sym);
+                    self.wstate()
+                        .type_cache
+                        .insert(*node, (id.as_str().to_string(), ty.clone()));
+                    return ty;
                 }
                 self.add_error(EvaluationError {
                     kind: EvaluationErrorKind::SymbolNotFound(id.as_str().to_string()),
                     range: id.get_range(),
+                    backtrace: None,
                 });
                 Type::Empty
             }
         }
     }
 
-    fn add_error(&self, error: EvaluationError) {
-        self.wstate().errors.push(error)
+    /// Reports `count` iterations that are over the iteration limit, so they're rejected before
+    /// any are run. Returns whether `count` is within the limit.
+    fn check_iteration_limit(&self, count: usize, range: Range) -> bool {
+        let max_iterations = self.rstate().max_iterations;
+        if count > max_iterations {
+            self.add_error(EvaluationError {
+                kind: EvaluationErrorKind::IterationLimitExceeded(max_iterations),
+                range,
+                backtrace: None,
+            });
+        }
+        count <= max_iterations
+    }
+
+    /// Reports an array that's passed where a fixed length array of a different length is
+    /// expected. Returns whether the length is valid for `ty`.
+    fn check_array_length(&self, value: &ConstValue, ty: &Type, range: Range) -> bool {
+        match (&value.kind, ty) {
+            (ConstValueKind::Array(values), Type::Array(_, Some(len))) if values.len() != *len => {
+                self.add_error(EvaluationError {
+                    kind: EvaluationErrorKind::ArrayLengthMismatch {
+                        expected: *len,
+                        found: values.len(),
+                    },
+                    range,
+                    backtrace: None,
+                });
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Evaluates `f` with a frame for the call to `callee` on the call stack. The frame is popped
+    /// however `f` returns, so errors inside it don't leave stale frames behind.
+    fn with_frame<T>(&self, callee: &Expression, range: Range, f: impl FnOnce() -> T) -> T {
+        let name = match callee {
+            Expression::Ident(SpannedToken(_, Token::Ident(name))) => name.clone(),
+            _ => "<anonymous>".to_string(),
+        };
+        self.wstate().call_stack.push(CallFrame { name, range });
+        let value = f();
+        self.wstate().call_stack.pop();
+        value
+    }
+
+    fn add_error(&self, mut error: EvaluationError) {
+        let mut state = self.wstate();
+        if !state.call_stack.is_empty() {
+            error.backtrace = Some(state.call_stack.clone());
+        }
+        state.errors.push(error)
+    }
+}
+
+/// The binding a place like `a.b.c` is accessed from, and the fields accessed from it in order
+fn place_path(place: &Expression) -> (&Expression, Vec<String>) {
+    let mut path = Vec::new();
+    let mut root = place;
+    while let Expression::BinaryExpression {
+        op_token: Some(SpannedToken(_, Token::Operator(Operator::Dot))),
+        left: Some(left),
+        right: Some(right),
+    } = root
+    {
+        let Expression::Ident(field) = right.as_ref() else {
+            break;
+        };
+        path.push(field.as_str().to_string());
+        root = left;
+    }
+    path.reverse();
+
+    (root, path)
+}
+
+/// Applies an arithmetic or bitwise operator to two integers, giving an integer of the given
+/// width and signedness. Returns `None` if `op` isn't one, and the result as it would be written
+/// if it doesn't fit. Signed integers are stored sign extended, and so is the result.
+fn integer_operation(
+    op: &Operator,
+    left: &ConstValue,
+    right: &ConstValue,
+    width: u8,
+    signed: bool,
+) -> Option<Result<u64, String>> {
+    let (l, r) = (left.integer_value()?, right.integer_value()?);
+    let value = match op {
+        Operator::Plus => l.checked_add(r),
+        Operator::Minus => l.checked_sub(r),
+        Operator::Multiply => l.checked_mul(r),
+        Operator::Divide => l.checked_div(r),
+        Operator::Modulo => l.checked_rem(r),
+        Operator::Exponent => u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+        Operator::BitAnd => Some(l & r),
+        Operator::BitOr => Some(l | r),
+        Operator::BitXor => Some(l ^ r),
+        _ => return None,
+    };
+
+    let (min, max) = integer_bounds(width, signed);
+    Some(match value {
+        Some(value) if (min..=max).contains(&value) => Ok(value as u64),
+        Some(value) => Err(value.to_string()),
+        // Only powers can get too big for an `i128`, or have a negative exponent
+        None => Err(format!("{l} ** {r}")),
+    })
+}
+
+/// The type two integers of types `l` and `r` are promoted to when they're operated on, as
+/// described on [`Evaluator::evaluate_binary_expression`]
+fn promoted_integer_type(l: &Type, r: &Type) -> Option<Type> {
+    let (
+        &Type::Integer {
+            width: lw,
+            signed: ls,
+        },
+        &Type::Integer {
+            width: rw,
+            signed: rs,
+        },
+    ) = (l, r)
+    else {
+        return None;
+    };
+
+    let (width, signed) = match (ls, rs) {
+        _ if ls == rs => (lw.max(rw), ls),
+        (true, false) if lw > rw => (lw, true),
+        (false, true) if rw > lw => (rw, true),
+        // The unsigned side is at least as wide, so only a signed type twice its width holds both
+        (false, _) if lw < 64 => (lw * 2, true),
+        (true, _) if rw < 64 => (rw * 2, true),
+        _ => return None,
+    };
+    Some(Type::Integer { width, signed })
+}
+
+/// The expression for the offset of an index, and whether it counts from the end, as `value[-n]`
+/// indexes `n` elements from the end
+fn index_offset(raw_index: &Expression) -> (&Expression, bool) {
+    match raw_index {
+        Expression::BinaryExpression {
+            left: None,
+            right: Some(right),
+            op_token: Some(SpannedToken(_, Token::Operator(Operator::Minus))),
+        } => (right, true),
+        raw_index => (raw_index, false),
+    }
+}
+
+/// The smallest and largest values of an integer type
+fn integer_bounds(width: u8, signed: bool) -> (i128, i128) {
+    if signed {
+        (-(1i128 << (width - 1)), (1i128 << (width - 1)) - 1)
+    } else {
+        (0, (1i128 << width) - 1)
+    }
+}
+
+/// The integer type named `name`, like `u32`
+fn integer_type_named(name: &str) -> Option<Type> {
+    let (signed, width) = match name.split_at_checked(1)? {
+        ("i", width) => (true, width),
+        ("u", width) => (false, width),
+        _ => return None,
+    };
+    match width {
+        "8" | "16" | "32" | "64" => Some(Type::Integer {
+            width: width.parse().ok()?,
+            signed,
+        }),
+        _ => None,
+    }
+}
+
+/// Whether `ordering` between two values satisfies the comparison operator `op`
+fn ordering_satisfies(op: &Operator, ordering: Ordering) -> bool {
+    match op {
+        Operator::LessThan => ordering.is_lt(),
+        Operator::GreaterThan => ordering.is_gt(),
+        Operator::LessThanEqual => ordering.is_le(),
+        Operator::GreaterThanEqual => ordering.is_ge(),
+        Operator::EqualsEquals => ordering.is_eq(),
+        _ => ordering.is_ne(),
+    }
+}
+
+/// Whether `name` names `ty` in a type match arm
+fn type_has_name(ty: &Type, name: &str) -> bool {
+    match (name, ty) {
+        ("Integer", Type::Integer { .. } | Type::CoercibleInteger)
+        | ("Float", Type::Float { .. } | Type::CoercibleFloat)
+        | ("Bool", Type::Boolean)
+        | ("String", Type::String)
+        | ("Array", Type::Array(..))
+        | ("Tuple", Type::Tuple(_))
+        | ("Range", Type::Range(_))
+        | ("Optional", Type::Optional(_))
+        | ("Function", Type::Function { .. }) => true,
+        // Records match by the name they were declared with
+        (name, Type::RecordInstance { rf: Some(rf), .. }) => {
+            matches!(&rf.borrow().value, ScopeValue::Record { ident, .. } if ident == name)
+        }
+        (name, ty) => ty.to_string() == name,
+    }
+}
+
+fn is_string_method(name: &str) -> bool {
+    matches!(name, "len" | "upper" | "lower" | "contains" | "split")
+}
+
+/// Finds the first identifier in `expr` that refers to one of `names`. Members on the right of a
+/// dot aren't references.
+fn find_ident<'a>(expr: &'a Expression, names: &[&str]) -> Option<&'a SpannedToken> {
+    match expr {
+        Expression::Ident(tok @ SpannedToken(_, Token::Ident(id))) => {
+            names.contains(&id.as_str()).then_some(tok)
+        }
+        Expression::BinaryExpression {
+            left,
+            right,
+            op_token,
+        } => {
+            let left = left.as_deref().and_then(|left| find_ident(left, names));
+            if let Some(SpannedToken(_, Token::Operator(Operator::Dot))) = op_token {
+                return left;
+            }
+            left.or_else(|| right.as_deref().and_then(|right| find_ident(right, names)))
+        }
+        Expression::FunctionCall { expr, args } => find_ident(expr, names)
+            .or_else(|| args.iter_items().find_map(|arg| find_ident(arg, names))),
+        Expression::String(ParsedTemplateString(templates), _) => {
+            templates.iter().find_map(|template| match template {
+                ParsedTemplate::Template(expr, ..) => find_ident(expr, names),
+                ParsedTemplate::String(_) => None,
+            })
+        }
+        Expression::Tuple(values, _) => values.iter().find_map(|value| find_ident(value, names)),
+        Expression::Array { values, .. } => values
+            .iter_items()
+            .find_map(|value| find_ident(value, names)),
+        Expression::Index { expr, index, .. } => find_ident(expr, names)
+            .or_else(|| index.as_deref().and_then(|index| find_ident(index, names))),
+        _ => None,
     }
 }
@@ -60,7 +60,7 @@ pub fn run_file<P: AsRef<Path> + std::fmt::Display>(path: P) {
             error.print("std.xl", &lines);
         }
 
-        for error in &evaluator.state.read().unwrap().errors {
+        for error in &evaluator.errors() {
             error.print("std.xl", &lines);
         }
 
@@ -82,12 +82,13 @@ pub fn run_file<P: AsRef<Path> + std::fmt::Display>(path: P) {
         error.print(path.as_ref().as_os_str().to_str().unwrap(), &lines);
     }
 
-    for error in &evaluator.state.read().unwrap().errors {
+    for error in &evaluator.errors() {
         error.print(path.as_ref().as_os_str().to_str().unwrap(), &lines);
     }
 
+    let float_precision = evaluator.float_precision();
     for value in values {
-        println!("{value}");
+        println!("{}", value.to_display_string(float_precision));
     }
 
     println!("{}", symbol_tree.format());
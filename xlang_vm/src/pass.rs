@@ -9,7 +9,7 @@ use xlang_core::{
 use xlang_util::Rf;
 
 use crate::{
-    const_value::{ConstValue, Type},
+    const_value::{ConstValue, ConstValueKind, Type},
     error::EvaluationError,
     scope::{Scope, ScopeManager, ScopeValue},
 };
@@ -81,6 +81,7 @@ impl CodePass {
                             ScopeValue::Record {
                                 ident: id.to_string(),
                                 members: LinkedHashMap::default(),
+                                defaults: LinkedHashMap::default(),
                             },
                             index,
                         );
@@ -89,8 +90,12 @@ impl CodePass {
                         let emembers = self.evaluate_params(parameters);
                         if let Some(sym) = self.wstate().scope.find_symbol(id) {
                             let mut sym = sym.borrow_mut();
-                            if let ScopeValue::Record { members, .. } = &mut sym.value {
-                                *members = emembers
+                            if let ScopeValue::Record {
+                                members, defaults, ..
+                            } = &mut sym.value
+                            {
+                                *members = emembers;
+                                *defaults = LinkedHashMap::from_iter(parameters.defaults());
                             }
                         }
                     }
@@ -104,8 +109,10 @@ impl CodePass {
                 ident: SpannedToken(_, Token::Ident(id)),
                 expr:
                     Some(Expression::Function {
+                        pure,
                         parameters,
                         return_parameters,
+                        requires,
                         body: Some(body),
                         ..
                     }),
@@ -128,6 +135,9 @@ impl CodePass {
                             eparameters,
                             ereturn_parameters,
                             sym,
+                            pure.is_some(),
+                            requires.as_ref().map(|(_, cond)| Expression::clone(cond)),
+                            parameters.patterns(),
                         )),
                         index,
                     );
@@ -149,7 +159,12 @@ impl CodePass {
                             .map(|(name, ty)| {
                                 (
                                     name.clone(),
-                                    ScopeValue::ConstValue(ConstValue::default_for(ty)),
+                                    ScopeValue::ConstValue(ConstValue::default_for(ty).unwrap_or(
+                                        ConstValue {
+                                            ty: ty.clone(),
+                                            kind: ConstValueKind::Empty,
+                                        },
+                                    )),
                                 )
                             })
                             .collect();
@@ -159,7 +174,12 @@ impl CodePass {
                             .map(|(name, ty)| {
                                 (
                                     name.clone(),
-                                    ScopeValue::ConstValue(ConstValue::default_for(ty)),
+                                    ScopeValue::ConstValue(ConstValue::default_for(ty).unwrap_or(
+                                        ConstValue {
+                                            ty: ty.clone(),
+                                            kind: ConstValueKind::Empty,
+                                        },
+                                    )),
                                 )
                             })
                             .collect();
@@ -179,16 +199,18 @@ impl CodePass {
                     self.wstate().scope.pop_scope();
                 }
             },
-            Statement::Decleration { ident, .. } => match self.pass {
-                PassType::TypeOnly => {
-                    self.wstate().scope.insert_value(
-                        ident.as_str(),
-                        ScopeValue::ConstValue(ConstValue::empty()),
-                        index,
-                    );
+            Statement::Decleration { ident, .. } | Statement::Const { ident, .. } => {
+                match self.pass {
+                    PassType::TypeOnly => {
+                        self.wstate().scope.insert_value(
+                            ident.as_str(),
+                            ScopeValue::ConstValue(ConstValue::empty()),
+                            index,
+                        );
+                    }
+                    _ => (),
                 }
-                _ => (),
-            },
+            }
             Statement::UseStatement { args, .. } => match self.pass {
                 PassType::TypeOnly => {
                     let path = args
@@ -205,8 +227,8 @@ impl CodePass {
 
     pub fn evaluate_params(&self, params: &ParamaterList) -> LinkedHashMap<String, Type> {
         let iter = params.items.iter_items().filter_map(|f| {
-            if let (Some(ident), Some(ty)) = (&f.name, &f.ty) {
-                Some((ident.as_str().to_string(), self.evaluate_type(ty)))
+            if let (Some(ident), Some(ty)) = (f.binding(), &f.ty) {
+                Some((ident, self.evaluate_type(ty)))
             } else {
                 None
             }
@@ -221,7 +243,20 @@ impl CodePass {
                 signed: *signed,
             },
             xlang_core::ast::Type::Float { width, .. } => Type::Float { width: *width },
-            xlang_core::ast::Type::Ident(id) => {
+            xlang_core::ast::Type::Boolean(_) => Type::Boolean,
+            xlang_core::ast::Type::String(_) => Type::String,
+            xlang_core::ast::Type::Optional { ty, .. } => {
+                Type::Optional(Box::new(self.evaluate_type(ty)))
+            }
+            xlang_core::ast::Type::Array { ty, len, .. } => {
+                let len = match len {
+                    Some(SpannedToken(_, Token::Integer(len))) => Some(*len as usize),
+                    _ => None,
+                };
+                Type::Array(Box::new(self.evaluate_type(ty)), len)
+            }
+            xlang_core::ast::Type::Ref { ty, .. } => Type::Ref(Box::new(self.evaluate_type(ty))),
+            xlang_core::ast::Type::Ident(id, _) => {
                 if let Some(sym) = { self.rstate().scope.find_symbol(id.as_str()) } {
                     return Type::Symbol(sym);
                 }
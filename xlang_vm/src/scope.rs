@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
 use linked_hash_map::LinkedHashMap;
 use xlang_core::{
@@ -19,6 +22,8 @@ pub enum ScopeValue {
     Record {
         ident: String,
         members: LinkedHashMap<String, Type>,
+        /// Expressions for the members that can be left out, evaluated on each instantiation
+        defaults: LinkedHashMap<String, Expression>,
     },
     Use(Vec<String>),
     Module(Arc<Module>),
@@ -68,6 +73,12 @@ pub struct Scope {
     pub children: LinkedHashMap<String, Rf<Scope>>,
     pub uses: Vec<Vec<String>>,
     pub index: usize,
+    /// Expressions from `defer`, run in reverse order when the scope is popped
+    pub deferred: Vec<Expression>,
+    /// Set for bindings declared with `const`, which can't be assigned to
+    pub constant: bool,
+    /// Set for bindings the evaluator provides, like `pi`, rather than the module declaring them
+    pub prelude: bool,
 }
 
 impl Scope {
@@ -77,6 +88,9 @@ impl Scope {
             children: LinkedHashMap::new(),
             uses: Vec::new(),
             index,
+            deferred: Vec::new(),
+            constant: false,
+            prelude: false,
         }
     }
 
@@ -143,6 +157,10 @@ pub struct ScopeManager {
     root: Rf<Scope>,
     pub module: Rf<Scope>,
     current_scope: Vec<Rf<Scope>>,
+    /// Symbols already found by `find_symbol`, along with the depth in `current_scope` of the
+    /// scope they were found from. Only hits are cached, since a miss can be filled in by any
+    /// later insert.
+    resolved: RwLock<HashMap<String, (usize, Rf<Scope>)>>,
 }
 
 impl<'a> ScopeManager {
@@ -162,6 +180,7 @@ impl<'a> ScopeManager {
             root,
             module,
             current_scope: vec,
+            resolved: RwLock::new(HashMap::new()),
         }
     }
 
@@ -170,6 +189,8 @@ impl<'a> ScopeManager {
             let mut sym = sym.borrow_mut();
             sym.uses.push(path)
         }
+        // A new use can resolve any name, so nothing cached can be trusted
+        self.resolved.get_mut().unwrap().clear();
     }
 
     pub fn push_scope_chain<'b>(
@@ -201,11 +222,51 @@ impl<'a> ScopeManager {
     }
 
     pub fn push_scope(&mut self, rf: Rf<Scope>) {
+        // Only the names the new scope shadows are stale
+        let resolved = self.resolved.get_mut().unwrap();
+        {
+            let scope = rf.borrow();
+            if scope.uses.is_empty() {
+                resolved.retain(|name, _| !scope.children.contains_key(name));
+            } else {
+                resolved.clear();
+            }
+        }
         self.current_scope.push(rf);
     }
 
     pub fn pop_scope(&mut self) -> Rf<Scope> {
-        self.current_scope.remove(self.current_scope.len() - 1)
+        let depth = self.current_scope.len() - 1;
+        self.resolved
+            .get_mut()
+            .unwrap()
+            .retain(|_, (found_at, _)| *found_at < depth);
+        self.current_scope.remove(depth)
+    }
+
+    /// Registers `expr` to run when the current scope is popped
+    pub fn defer(&mut self, expr: Expression) {
+        if let Some(scope) = self.current_scope.last() {
+            scope.borrow_mut().deferred.push(expr);
+        }
+    }
+
+    /// Removes the deferred expressions of the current scope
+    pub fn take_deferred(&mut self) -> Vec<Expression> {
+        self.current_scope
+            .last()
+            .map(|scope| std::mem::take(&mut scope.borrow_mut().deferred))
+            .unwrap_or_default()
+    }
+
+    pub fn current_scopes(&self) -> Vec<Rf<Scope>> {
+        self.current_scope.clone()
+    }
+
+    /// Replaces the scope chain, returning the old one so it can be restored
+    pub fn swap_scopes(&mut self, scopes: Vec<Rf<Scope>>) -> Vec<Rf<Scope>> {
+        self.resolved.get_mut().unwrap().clear();
+        std::mem::replace(&mut self.current_scope, scopes)
     }
 
     fn follow_member_access_leaf(
@@ -229,7 +290,7 @@ impl<'a> ScopeManager {
                         return false
                     };
 
-                if let Some(m) = members.get_mut(right.as_str()) {
+                if let Some(m) = Arc::make_mut(members).get_mut(right.as_str()) {
                     cb(m);
                 }
                 return true;
@@ -261,7 +322,7 @@ impl<'a> ScopeManager {
                         return false
                     };
 
-                if let Some(m) = members.get_mut(right.as_str()) {
+                if let Some(m) = Arc::make_mut(members).get_mut(right.as_str()) {
                     cb(m);
                 }
                 return true;
@@ -282,7 +343,7 @@ impl<'a> ScopeManager {
                         return;
                     };
 
-                    if let Some(m) = members.get_mut(member_right.as_str()) {
+                    if let Some(m) = Arc::make_mut(members).get_mut(member_right.as_str()) {
                         cb(m);
                     }
                 });
@@ -307,22 +368,36 @@ impl<'a> ScopeManager {
     }
 
     pub fn find_symbol(&'a self, name: &str) -> Option<Rf<Scope>> {
-        if let Some(sym) = self.find_symbol_in_mod(name) {
-            return Some(sym);
+        if let Some((_, sym)) = self.resolved.read().unwrap().get(name) {
+            return Some(sym.clone());
         }
 
-        let use_found = self.current_scope.iter().rev().find_map(|scope| {
+        let (depth, sym) = self.find_symbol_uncached(name)?;
+        self.resolved
+            .write()
+            .unwrap()
+            .insert(name.to_string(), (depth, sym.clone()));
+        Some(sym)
+    }
+
+    /// Walks the scope chain for `name`, returning the depth of the scope it was found from
+    fn find_symbol_uncached(&self, name: &str) -> Option<(usize, Rf<Scope>)> {
+        let scopes = self.current_scope.iter().enumerate().rev();
+
+        if let Some(fnd) = scopes
+            .clone()
+            .find_map(|(depth, scope)| Some((depth, scope.borrow().children.get(name)?.clone())))
+        {
+            return Some(fnd);
+        }
+
+        scopes.into_iter().find_map(|(depth, scope)| {
             scope.borrow().uses.iter().find_map(|us| {
                 let node = self.resolve_use(us, |_| {})?;
                 let node = node.borrow();
-                node.children.get(name).cloned()
+                Some((depth, node.children.get(name)?.clone()))
             })
-        });
-        if let Some(fnd) = use_found {
-            return Some(fnd);
-        }
-
-        None
+        })
     }
 
     pub fn index_of_mod(&self, name: &str) -> Option<usize> {
@@ -496,7 +571,8 @@ impl<'a> ScopeManager {
         value: ScopeValue,
         index: usize,
     ) -> Option<ScopeValue> {
-        if let Some(sym) = self.find_symbol(name) {
+        // Bindings from the prelude are shadowed rather than updated
+        if let Some(sym) = self.find_symbol(name).filter(|sym| !sym.borrow().prelude) {
             let old_value = std::mem::replace(&mut sym.borrow_mut().value, value);
             return Some(old_value);
         }
@@ -505,6 +581,7 @@ impl<'a> ScopeManager {
             scp.borrow_mut()
                 .children
                 .insert(name.to_string(), Rf::new(Scope::new(value, index)));
+            self.resolved.get_mut().unwrap().remove(name);
         }
 
         None
@@ -516,6 +593,7 @@ impl<'a> ScopeManager {
             scp.borrow_mut()
                 .children
                 .insert(name.to_string(), rf.clone());
+            self.resolved.get_mut().unwrap().remove(name);
             return rf;
         }
         panic!()
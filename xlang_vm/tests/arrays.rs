@@ -0,0 +1,310 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, evaluate_with, export};
+use xlang_vm::{
+    const_value::Type,
+    error::{EvaluationErrorKind, TypeHint},
+};
+
+#[test]
+fn ranges_slice_arrays_and_strings() {
+    let evaluator = evaluate_ok(
+        "a: [10, 20, 30, 40]\nb: a[1..3]\nc: a[1..=3]\nr: 0..2\nd: a[r]\n\
+         s: \"hello\"\ne: s[1..3]\nf: s[0..=4]",
+    );
+    assert_eq!(display(&evaluator, "b"), "[20, 30]");
+    assert_eq!(display(&evaluator, "c"), "[20, 30, 40]");
+    assert_eq!(display(&evaluator, "d"), "[10, 20]");
+    assert_eq!(
+        export(&evaluator, "b").ty,
+        Type::Array(Box::new(Type::CoercibleInteger), None)
+    );
+    assert_eq!(display(&evaluator, "e"), "el");
+    assert_eq!(display(&evaluator, "f"), "hello");
+    assert_eq!(export(&evaluator, "e").ty, Type::String);
+}
+
+#[test]
+fn out_of_range_slices_are_errors() {
+    let evaluator =
+        evaluate("a: [10, 20, 30, 40]\nb: a[2..9]\ns: \"hello\"\nc: s[3..2]\nd: s[0..=5]");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IndexOutOfBounds(b, 4),
+            EvaluationErrorKind::IndexOutOfBounds(c, 5),
+            EvaluationErrorKind::IndexOutOfBounds(d, 5),
+        ] if b == "2..9" && c == "3..2" && d == "0..=5"
+    ));
+
+    let evaluator = evaluate("a: [10, 20, 30, 40]\nb: a[-3..-1]");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::IndexOutOfBounds(b, 4)] if b == "-3..-1"
+    ));
+}
+
+#[test]
+fn negative_indices_count_from_the_end_when_enabled() {
+    let source = "arr: [10, 20, 30]\ns: \"hello\"\na: arr[-1]\nb: arr[-3]\nc: s[-2]";
+    let evaluator = evaluate_with(source, |evaluator| evaluator.set_negative_indexing(true));
+    assert!(!evaluator.has_errors(), "{:?}", errors(&evaluator));
+    assert_eq!(display(&evaluator, "a"), "30");
+    assert_eq!(display(&evaluator, "b"), "10");
+    assert_eq!(display(&evaluator, "c"), "l");
+
+    let evaluator = evaluate_with("arr: [10, 20, 30]\na: arr[-4]", |evaluator| {
+        evaluator.set_negative_indexing(true)
+    });
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::IndexOutOfBounds(index, 3)] if index == "-4"
+    ));
+}
+
+#[test]
+fn negative_indices_are_errors_by_default() {
+    let evaluator = evaluate("arr: [10, 20, 30]\na: arr[-1]");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::IndexOutOfBounds(index, 3)] if index == "-1"
+    ));
+}
+
+#[test]
+fn fixed_size_arrays_check_the_length() {
+    let evaluator = evaluate(
+        "Buf: ([i32; 3] data)\nsum: ([i32; 3] v) -> (i32 out), out = v[0] + v[1] + v[2]\n\
+         a: Buf([1, 2])\nb: Buf([1, 2, 3]).data\nc: sum([1, 2, 3]).out\nd: sum([1, 2, 3, 4])\n\
+         g: sum([\"x\", \"y\", \"z\"])",
+    );
+    assert_eq!(display(&evaluator, "b"), "[1, 2, 3]");
+    assert_eq!(
+        export(&evaluator, "b").ty,
+        Type::Array(
+            Box::new(Type::Integer {
+                width: 32,
+                signed: true
+            }),
+            Some(3)
+        )
+    );
+    assert_eq!(display(&evaluator, "c"), "6");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::ArrayLengthMismatch {
+                expected: 3,
+                found: 2
+            },
+            EvaluationErrorKind::ArrayLengthMismatch {
+                expected: 3,
+                found: 4
+            },
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+        ]
+    ));
+}
+
+#[test]
+fn array_literals_and_indexing() {
+    let evaluator = evaluate_ok("a: [1i32, 2, 3]\nb: a[0]\nc: a[2]\nd: [[1, 2], [3]][1][0]");
+    assert_eq!(display(&evaluator, "a"), "[1, 2, 3]");
+    assert_eq!(
+        export(&evaluator, "a").ty,
+        Type::Array(
+            Box::new(Type::Integer {
+                width: 32,
+                signed: true
+            }),
+            None
+        )
+    );
+    assert_eq!(display(&evaluator, "b"), "1");
+    assert_eq!(display(&evaluator, "c"), "3");
+    assert_eq!(display(&evaluator, "d"), "3");
+}
+
+#[test]
+fn out_of_bounds_indexes_are_errors() {
+    let evaluator = evaluate("a: [1, 2, 3]\nb: a[3]");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::IndexOutOfBounds(_, 3)]
+    ));
+    assert_eq!(display(&evaluator, "b"), "()");
+}
+
+/// Indexing with something other than an integer or a range reports `InvalidIndex`, which names
+/// both types, rather than a plain `TypeMismatch`
+#[test]
+fn non_integer_indexes_are_errors() {
+    let evaluator = evaluate("a: [1, 2, 3]\nb: a[\"x\"]");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::InvalidIndex(
+            Type::Array(..),
+            Type::String
+        )]
+    ));
+}
+
+#[test]
+fn mixed_arrays_report_each_offending_element() {
+    let evaluator = evaluate("a: [1i32, \"x\", 2, true]");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(Type::String, _, TypeHint::Element),
+            EvaluationErrorKind::TypeMismatch(Type::Boolean, _, TypeHint::Element),
+        ]
+    ));
+}
+
+#[test]
+fn array_elements_can_be_assigned() {
+    let evaluator = evaluate_ok(
+        "arr: [1, 2, 3]\narr[1] = 10\nb: arr[1]\nk: 2u8\narr[k] = 42\n\
+         R: (i32 v, [i32] xs)\nr: R(1i32, [1i32, 2i32])\nr.xs[0] = 7\nm: [[1, 2], [3, 4]]\nm[1][0] = 5",
+    );
+    assert_eq!(display(&evaluator, "b"), "10");
+    assert_eq!(display(&evaluator, "arr"), "[1, 10, 42]");
+    assert_eq!(display(&evaluator, "r"), "{ v: 1, xs: [7, 2] }");
+    assert_eq!(display(&evaluator, "m"), "[[1, 2], [5, 4]]");
+}
+
+#[test]
+fn array_element_assignments_check_bounds_and_types() {
+    let evaluator = evaluate(
+        "arr: [1, 2, 3]\narr[5] = 1\narr[0] = \"x\"\nm: [[1, 2], [3, 4]]\nm[1][7] = 5\nconst C = [1, 2]\nC[0] = 5",
+    );
+    assert_eq!(display(&evaluator, "arr"), "[1, 2, 3]");
+    assert_eq!(display(&evaluator, "C"), "[1, 2]");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IndexOutOfBounds(_, 3),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Element),
+            EvaluationErrorKind::IndexOutOfBounds(_, 2),
+            EvaluationErrorKind::AssignToImmutable(..),
+        ]
+    ));
+}
+
+#[test]
+fn reverse_and_sorted_return_new_arrays() {
+    let evaluator = evaluate_ok(
+        "a: [(2, \"b\"), (1, \"z\"), (2, \"a\")]\nb: reverse(a)\nc: sorted(a)\n\
+         n: [3, 1, 2]\nm: sorted(n)\nf: sorted([\"b\", \"a\", \"c\"])\ng: reverse(1..4)\n\
+         l: reverse([4u8, 5u8])",
+    );
+    assert_eq!(display(&evaluator, "b"), "[2, a, 1, z, 2, b]");
+    assert_eq!(display(&evaluator, "c"), "[1, z, 2, a, 2, b]");
+    assert_eq!(display(&evaluator, "m"), "[1, 2, 3]");
+    // The originals are unchanged
+    assert_eq!(display(&evaluator, "a"), "[2, b, 1, z, 2, a]");
+    assert_eq!(display(&evaluator, "n"), "[3, 1, 2]");
+    assert_eq!(display(&evaluator, "f"), "[a, b, c]");
+    assert_eq!(display(&evaluator, "g"), "[3, 2, 1]");
+    assert_eq!(
+        export(&evaluator, "l").ty,
+        Type::Array(
+            Box::new(Type::Integer {
+                width: 8,
+                signed: false
+            }),
+            None
+        )
+    );
+}
+
+#[test]
+fn sorted_requires_orderable_elements() {
+    let evaluator = evaluate("i: reverse(5)\nj: sorted([[1], [2]])");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::NotIterable(..),
+            EvaluationErrorKind::IncomparableTypes(..),
+        ]
+    ));
+}
+
+#[test]
+fn zip_stops_at_the_shorter_array() {
+    let evaluator = evaluate_ok(
+        "a: [1i32, 2i32, 3i32]\nb: [\"x\", \"y\"]\nc: zip(a, b)\nd: zip(b, 0..5)\nh: c[1].1",
+    );
+    assert_eq!(display(&evaluator, "c"), "[1, x, 2, y]");
+    assert_eq!(display(&evaluator, "d"), "[x, 0, y, 1]");
+    assert_eq!(display(&evaluator, "h"), "y");
+    assert_eq!(
+        export(&evaluator, "c").ty,
+        Type::Array(
+            Box::new(Type::Tuple(vec![
+                Type::Integer {
+                    width: 32,
+                    signed: true
+                },
+                Type::String,
+            ])),
+            None
+        )
+    );
+}
+
+#[test]
+fn zip_takes_two_arrays() {
+    let evaluator = evaluate("a: [1, 2]\ne: zip(a, 5)\nf: zip(a)");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::NotIterable(..),
+            EvaluationErrorKind::ArgCountMismatch(1, 2),
+        ]
+    ));
+}
+
+#[test]
+fn contains_and_index_of_find_elements() {
+    let evaluator = evaluate_ok(
+        "a: [1, 2, 3]\nb: contains(a, 2)\nc: contains(a, 5)\nd: index_of(a, 3)\n\
+         f: index_of(a, 9)\ng: contains([\"x\", \"y\"], \"y\")\ni: index_of(0..10, 4)\n\
+         l: contains([(1, \"a\")], (1, \"a\"))\nn: index_of([], 1)",
+    );
+    assert_eq!(display(&evaluator, "b"), "true");
+    assert_eq!(display(&evaluator, "c"), "false");
+    assert_eq!(display(&evaluator, "d"), "2");
+    assert_eq!(
+        export(&evaluator, "d").ty,
+        Type::Optional(Box::new(Type::CoercibleInteger))
+    );
+    assert_eq!(display(&evaluator, "f"), "null");
+    assert_eq!(display(&evaluator, "g"), "true");
+    assert_eq!(display(&evaluator, "i"), "4");
+    assert_eq!(display(&evaluator, "l"), "true");
+    assert_eq!(display(&evaluator, "n"), "null");
+
+    // Ranges aren't iterated, so they can be larger than the iteration limit
+    let evaluator = evaluate_with(
+        "a: contains(0..100000000000, -1)\nb: index_of(-5..=100000000000, 99999999999)\n\
+         c: contains(0..100000000000, 100000000000)",
+        |evaluator| evaluator.set_max_iterations(100),
+    );
+    assert!(!evaluator.has_errors(), "{:?}", errors(&evaluator));
+    assert_eq!(display(&evaluator, "a"), "false");
+    assert_eq!(display(&evaluator, "b"), "100000000004");
+    assert_eq!(display(&evaluator, "c"), "false");
+}
+
+#[test]
+fn contains_requires_comparable_elements() {
+    let evaluator = evaluate("a: [1, 2]\nh: contains(a, \"y\")\nm: contains(5, 5)");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IncomparableTypes(..),
+            EvaluationErrorKind::NotIterable(..),
+        ]
+    ));
+}
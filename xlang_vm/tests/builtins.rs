@@ -0,0 +1,604 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, evaluate_with, export};
+use xlang_vm::{
+    const_value::Type,
+    error::{EvaluationErrorKind, TypeHint},
+};
+
+#[test]
+fn bit_counting_respects_the_width() {
+    let evaluator = evaluate_ok(
+        "a: 6u8\nb: 6u32\n\
+         c: count_ones(a)\nd: leading_zeros(a)\ne: trailing_zeros(a)\n\
+         f: count_ones(b)\ng: leading_zeros(b)\nh: trailing_zeros(b)\ni: trailing_zeros(0i8)",
+    );
+    for (name, expected) in [
+        ("c", "2"),
+        ("d", "5"),
+        ("e", "1"),
+        ("f", "2"),
+        ("g", "29"),
+        ("h", "1"),
+        ("i", "8"),
+    ] {
+        assert_eq!(display(&evaluator, name), expected, "{name}");
+        assert_eq!(
+            export(&evaluator, name).ty,
+            Type::Integer {
+                width: 32,
+                signed: false
+            }
+        );
+    }
+}
+
+#[test]
+fn bit_counting_needs_a_sized_integer() {
+    let evaluator = evaluate("a: leading_zeros(5)\nb: count_ones(1.5)\nc: count_ones(1u8, 2u8)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::ExpectedSizedInteger(Type::CoercibleInteger),
+            EvaluationErrorKind::ExpectedSizedInteger(Type::CoercibleFloat),
+            EvaluationErrorKind::ArgCountMismatch(2, 1),
+        ]
+    ));
+}
+
+#[test]
+fn to_string_round_trips_through_parse() {
+    let evaluator = evaluate_ok(
+        "a: to_string(123)\nb: parse_int(a)\nc: parse_float(to_string(2.5))\nd: to_string(true)",
+    );
+    assert_eq!(export(&evaluator, "a").ty, Type::String);
+    assert_eq!(display(&evaluator, "a"), "123");
+    assert_eq!(display(&evaluator, "b"), "123");
+    assert_eq!(display(&evaluator, "c"), "2.5");
+    assert_eq!(display(&evaluator, "d"), "true");
+}
+
+#[test]
+fn thunks_capture_their_scope_by_reference() {
+    let evaluator =
+        evaluate_ok("v: 1\nt: lazy(v * 10)\nv = 2\na: force(t)\nv = 3\nb: force(t)\nc: force(5)");
+    // Forcing reads the captured binding as it is at the time, not as it was when captured
+    assert_eq!(display(&evaluator, "a"), "20");
+    assert_eq!(display(&evaluator, "b"), "30");
+    // Forcing anything that isn't a thunk yields it unchanged
+    assert_eq!(display(&evaluator, "c"), "5");
+}
+
+#[test]
+fn reduce_folds_with_an_accumulator() {
+    let evaluator = evaluate_ok(
+        "add: (i32 a, i32 b) -> (i32 r), r = a + b\n\
+         a: reduce([1, 2, 3, 4], 0, add)\nb: reduce([], 7, add)\nc: reduce(1..=10, 0, add)",
+    );
+    assert_eq!(display(&evaluator, "a"), "10");
+    // An empty array leaves the initial value untouched
+    assert_eq!(display(&evaluator, "b"), "7");
+    assert_eq!(export(&evaluator, "b").ty, Type::CoercibleInteger);
+    assert_eq!(display(&evaluator, "c"), "55");
+}
+
+#[test]
+fn reduce_checks_the_accumulator_type() {
+    let evaluator = evaluate(
+        "cat: (i32 a, i32 b) -> (f32 r), r = 1.5f32\nadd: (i32 a, i32 b) -> (i32 r), r = a + b\n\
+         a: reduce([1, 2], 0i32, cat)\nb: reduce(5, 0, add)\nc: reduce([1], 0)",
+    );
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::ReturnParameter),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+            EvaluationErrorKind::ArgCountMismatch(2, 3),
+        ]
+    ));
+
+    // Undefined arguments are only reported once
+    let evaluator = evaluate(
+        "add: (i32 a, i32 b) -> (i32 r), r = a + b\na: reduce([1, 2], zz, add)\nb: reduce(yy, 0, add)",
+    );
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::SymbolNotFound(a),
+            EvaluationErrorKind::SymbolNotFound(b),
+        ] if a == "zz" && b == "yy"
+    ));
+}
+
+#[test]
+fn min_and_max_order_strings_and_mixed_numbers() {
+    let evaluator = evaluate_ok(
+        "a: min(\"pear\", \"apple\")\nb: max(\"pear\", \"apple\")\nc: min(300u16, 7u8)\n\
+         d: max(300u16, 7u8)\ne: min(5, 2i64)\nf: min(2.5, 3)",
+    );
+    assert_eq!(display(&evaluator, "a"), "apple");
+    assert_eq!(display(&evaluator, "b"), "pear");
+    // Mixed widths compare by value and keep the type of the one chosen
+    assert_eq!(display(&evaluator, "c"), "7");
+    assert_eq!(
+        export(&evaluator, "c").ty,
+        Type::Integer {
+            width: 8,
+            signed: false
+        }
+    );
+    assert_eq!(display(&evaluator, "d"), "300");
+    assert_eq!(display(&evaluator, "e"), "2");
+    assert_eq!(display(&evaluator, "f"), "2.5");
+}
+
+#[test]
+fn min_of_unordered_values_is_an_error() {
+    let evaluator = evaluate("a: min(\"a\", 1)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::IncomparableTypes(..)]
+    ));
+}
+
+#[test]
+fn merge_takes_the_union_of_fields() {
+    let evaluator = evaluate_ok(
+        "Base: (i32 port, i32 retries)\nOver: (i32 port, i32 timeout)\n\
+         m: merge(Base(80i32, 3i32), Over(8080i32, 30i32))",
+    );
+    // Fields from the second record replace those of the first, and the rest are kept
+    assert_eq!(
+        display(&evaluator, "m"),
+        "{ port: 8080, retries: 3, timeout: 30 }"
+    );
+}
+
+#[test]
+fn merge_of_conflicting_fields_or_non_records_are_errors() {
+    let evaluator = evaluate(
+        "Base: (i32 port, i32 retries)\nBad: (u8 port)\nb: Base(80i32, 3i32)\n\
+         x: merge(b, Bad(1u8))\ny: merge(b, 5)",
+    );
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(..),
+            EvaluationErrorKind::ExpectedRecord(..),
+        ]
+    ));
+}
+
+#[test]
+fn parse_int_radix_reads_other_bases() {
+    let evaluator = evaluate_ok(
+        "a: parse_int_radix(\"FF\", 16)\nb: parse_int_radix(\"ff\", 16)\nc: parse_int_radix(\"1011\", 2)\n\
+         d: parse_int_radix(\"12\", 2)\ne: parse_int_radix(\"zz\", 36)",
+    );
+    assert_eq!(display(&evaluator, "a"), "255");
+    assert_eq!(display(&evaluator, "b"), "255");
+    assert_eq!(display(&evaluator, "c"), "11");
+    // `2` isn't a binary digit
+    assert_eq!(display(&evaluator, "d"), "null");
+    assert_eq!(display(&evaluator, "e"), "1295");
+    assert_eq!(
+        export(&evaluator, "d").ty,
+        Type::Optional(Box::new(Type::CoercibleInteger))
+    );
+}
+
+#[test]
+fn parse_int_radix_out_of_range_is_an_error() {
+    let evaluator = evaluate("a: parse_int_radix(\"1\", 37)\nb: parse_int_radix(\"1\", 1)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::InvalidRadix(37),
+            EvaluationErrorKind::InvalidRadix(1)
+        ]
+    ));
+}
+
+#[test]
+fn saturating_arithmetic_clamps_to_the_width() {
+    let evaluator = evaluate_ok(
+        "a: saturating_add(250u8, 10)\nb: saturating_add(200u8, 20u8)\nc: saturating_sub(5u8, 10u8)\n\
+         f: saturating_add(100i8, 100i8)\ng: saturating_sub(-100i8, 100i8)",
+    );
+    assert_eq!(display(&evaluator, "a"), "255");
+    assert_eq!(
+        export(&evaluator, "a").ty,
+        Type::Integer {
+            width: 8,
+            signed: false
+        }
+    );
+    assert_eq!(display(&evaluator, "b"), "220");
+    assert_eq!(display(&evaluator, "c"), "0");
+    assert_eq!(display(&evaluator, "f"), "127");
+    assert_eq!(display(&evaluator, "g"), "-128");
+}
+
+#[test]
+fn abs_diff_is_unsigned() {
+    let evaluator =
+        evaluate_ok("a: abs_diff(3i32, 10i32)\nb: abs_diff(10u64, 3)\nc: abs_diff(-128i8, 127i8)");
+    assert_eq!(display(&evaluator, "a"), "7");
+    assert_eq!(
+        export(&evaluator, "a").ty,
+        Type::Integer {
+            width: 32,
+            signed: false
+        }
+    );
+    assert_eq!(display(&evaluator, "b"), "7");
+    assert_eq!(display(&evaluator, "c"), "255");
+}
+
+#[test]
+fn saturating_arithmetic_needs_sized_integers() {
+    let evaluator =
+        evaluate("i: saturating_add(1, 2)\nk: saturating_add(1.5, 2u8)\nj: abs_diff(1u8, 2i32)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::ExpectedSizedInteger(Type::CoercibleInteger),
+            EvaluationErrorKind::ExpectedSizedInteger(Type::CoercibleFloat),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+        ]
+    ));
+}
+
+#[test]
+fn all_and_any_short_circuit() {
+    let evaluator = evaluate_ok(
+        "log: \"\"\nyes: () -> (i32 r), log = \"{log}y\", r = 1i32\nno: () -> (i32 r), log = \"{log}n\", r = 0i32\n\
+         a: all([yes().r == 1i32, no().r == 1i32, yes().r == 1i32])\nla: log\nlog = \"\"\n\
+         b: any([no().r == 1i32, yes().r == 1i32, no().r == 1i32])\nlb: log\nlog = \"\"\n\
+         c: all([lazy(yes().r == 1i32), lazy(no().r == 1i32), lazy(yes().r == 1i32)])\nlc: log\n\
+         arr: [1 < 2, 2 < 3]\nd: all(arr)\ne: any([])\nf: all([])",
+    );
+    assert_eq!(display(&evaluator, "a"), "false");
+    // Elements after the first false are never evaluated
+    assert_eq!(display(&evaluator, "la"), "yn");
+    assert_eq!(display(&evaluator, "b"), "true");
+    assert_eq!(display(&evaluator, "lb"), "ny");
+    assert_eq!(display(&evaluator, "c"), "false");
+    assert_eq!(display(&evaluator, "lc"), "yn");
+    assert_eq!(display(&evaluator, "d"), "true");
+    assert_eq!(display(&evaluator, "e"), "false");
+    assert_eq!(display(&evaluator, "f"), "true");
+}
+
+#[test]
+fn all_and_any_need_booleans() {
+    let evaluator = evaluate("f: all([1 < 2, 5])\ng: all(5)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Element),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+        ]
+    ));
+}
+
+#[test]
+fn panic_aborts_with_its_message() {
+    let evaluator = evaluate(
+        "pick: (i32 v) -> (i32 r), r = match v { 1 => 10i32, 2 => panic(\"bad value\"), x => 0i32 }, r = 5i32\n\
+         a: pick(1i32).r\nb: pick(2i32).r\nc: 3i32\nd: unreachable()",
+    );
+    // Without a panic the function runs to the end
+    assert_eq!(display(&evaluator, "a"), "5");
+    assert_eq!(export(&evaluator, "b").ty, Type::Empty);
+    // Evaluation carries on after the call that panicked
+    assert_eq!(display(&evaluator, "c"), "3");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::Panic(a), EvaluationErrorKind::Panic(b)]
+            if a == "bad value" && b == "entered unreachable code"
+    ));
+}
+
+#[test]
+fn assert_eq_reports_where_values_differ() {
+    let evaluator = evaluate(
+        "I: (i32 v, u8 s)\nO: (I inner, i32 n)\na: O(I(1i32, 7u8), 2i32)\nb: O(I(5i32, 7u8), 2i32)\n\
+         assert_eq(a, a)\nassert_eq(1, 1.0)\nassert_eq(a, b)\nassert_eq((1, [1, 2, 3]), (1, [1, 4, 3]))\n\
+         assert_eq([1, 2], [1, 2, 3])",
+    );
+    let differences = errors(&evaluator)
+        .into_iter()
+        .map(|error| match error {
+            EvaluationErrorKind::AssertionFailed(differences) => differences,
+            error => panic!("unexpected error {error:?}"),
+        })
+        .collect::<Vec<_>>();
+    let difference =
+        |path: &str, left: &str, right: &str| vec![(path.into(), left.into(), right.into())];
+    assert_eq!(
+        differences,
+        [
+            difference(".inner.v", "1", "5"),
+            difference(".1[1]", "2", "4"),
+            // Arrays of different lengths differ as a whole
+            difference("", "[1, 2]", "[1, 2, 3]"),
+        ]
+    );
+}
+
+#[test]
+fn sum_and_product_fold_numeric_arrays() {
+    let evaluator = evaluate_ok(
+        "a: sum([1, 2, 3])\nb: product([1i32, 2i32, 3i32, 4i32])\nc: sum([])\nd: product([1.5, 2.0])\n\
+         e: sum(1..5)\ni: product([])\nj: sum([1u16, 2u16])",
+    );
+    assert_eq!(display(&evaluator, "a"), "6");
+    assert_eq!(display(&evaluator, "b"), "24");
+    assert_eq!(
+        export(&evaluator, "b").ty,
+        Type::Integer {
+            width: 32,
+            signed: true
+        }
+    );
+    // Empty arrays give the identity of the operation
+    assert_eq!(display(&evaluator, "c"), "0");
+    assert_eq!(display(&evaluator, "i"), "1");
+    assert_eq!(display(&evaluator, "d"), "3");
+    assert_eq!(display(&evaluator, "e"), "10");
+    assert_eq!(display(&evaluator, "j"), "3");
+}
+
+#[test]
+fn sum_and_product_check_overflow_and_element_types() {
+    let evaluator = evaluate("f: product([100u8, 3u8])\ng: sum([\"a\", \"b\"])\nh: sum(5)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IntegerOverflow(..),
+            EvaluationErrorKind::BinExpMismatch { .. },
+            EvaluationErrorKind::NotIterable(..),
+        ]
+    ));
+}
+
+#[test]
+fn cfg_checks_host_features() {
+    let source = "mode: if cfg(\"debug\") { \"debug\" } else { \"release\" }\n\
+                  unknown: cfg(\"trace\")";
+    let evaluator = evaluate_with(source, |ev| ev.set_feature("debug", true));
+    assert_eq!(display(&evaluator, "mode"), "debug");
+    assert_eq!(display(&evaluator, "unknown"), "false");
+    assert!(!evaluator.has_errors());
+
+    let evaluator = evaluate_with(source, |ev| {
+        ev.set_feature("debug", true);
+        ev.set_feature("debug", false);
+    });
+    assert_eq!(display(&evaluator, "mode"), "release");
+}
+
+#[test]
+fn cfg_takes_a_string() {
+    let evaluator = evaluate("x: cfg(1)");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::TypeMismatch(
+            _,
+            Type::String,
+            TypeHint::Parameter
+        )]
+    ));
+}
+
+#[test]
+fn prelude_defines_float_constants() {
+    let evaluator = evaluate_ok(
+        "a: pi\nb: e * 2.0\nc: 2.0f32 * pi\nd: inf > 1.0\nx: nan == nan\nr: 2.0\narea: pi * r ** 2",
+    );
+    assert_eq!(display(&evaluator, "a"), std::f64::consts::PI.to_string());
+    assert_eq!(
+        display(&evaluator, "b"),
+        (std::f64::consts::E * 2.0).to_string()
+    );
+    assert_eq!(export(&evaluator, "c").ty, Type::Float { width: 32 });
+    assert_eq!(display(&evaluator, "d"), "true");
+    assert_eq!(display(&evaluator, "x"), "false");
+    assert_eq!(
+        display(&evaluator, "area"),
+        (std::f64::consts::PI * 4.0).to_string()
+    );
+}
+
+#[test]
+fn prelude_constants_are_immutable_but_can_be_shadowed() {
+    let evaluator = evaluate("pi = 3.0\nc: pi");
+    assert_eq!(display(&evaluator, "c"), std::f64::consts::PI.to_string());
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::AssignToImmutable(name)] if name == "pi"
+    ));
+
+    let evaluator = evaluate_ok("e: 5\nf: e + 1");
+    assert_eq!(display(&evaluator, "f"), "6");
+}
+
+#[test]
+fn take_and_drop_split_an_array() {
+    let evaluator = evaluate_ok(
+        "a: [1, 2, 3, 4, 5]\nb: take(a, 2)\nc: drop(a, 2)\nd: take(0..1000000000, 3)\ne: drop(0..5, 3)",
+    );
+    assert_eq!(display(&evaluator, "b"), "[1, 2]");
+    assert_eq!(display(&evaluator, "c"), "[3, 4, 5]");
+    assert_eq!(display(&evaluator, "d"), "[0, 1, 2]");
+    assert_eq!(display(&evaluator, "e"), "[3, 4]");
+}
+
+#[test]
+fn take_and_drop_clamp_the_count() {
+    let evaluator = evaluate_ok("a: [1, 2, 3]\nb: take(a, 10)\nc: drop(a, 10)\nd: take(a, 0)");
+    assert_eq!(display(&evaluator, "b"), "[1, 2, 3]");
+    assert_eq!(display(&evaluator, "c"), "[]");
+    assert_eq!(display(&evaluator, "d"), "[]");
+}
+
+#[test]
+fn take_and_drop_with_negative_count_are_errors() {
+    for source in [
+        "a: take([1, 2, 3], -1)",
+        "a: drop([1, 2, 3], -1)",
+        "n: -1\na: take([1, 2, 3], n)",
+        "a: drop([1, 2, 3], -1i32)",
+    ] {
+        let evaluator = evaluate(source);
+        assert!(
+            matches!(
+                errors(&evaluator)[..],
+                [EvaluationErrorKind::NegativeCount(-1)]
+            ),
+            "`{source}` reported {:?}",
+            errors(&evaluator)
+        );
+    }
+}
+
+#[test]
+fn fill_repeats_a_value() {
+    let evaluator = evaluate_ok("a: fill(7, 3)\nb: fill(\"x\", 0)");
+    assert_eq!(display(&evaluator, "a"), "[7, 7, 7]");
+    assert_eq!(display(&evaluator, "b"), "[]");
+}
+
+#[test]
+fn generate_calls_the_function_with_each_index() {
+    let evaluator = evaluate_ok("sq: (i32 x) -> (i32 r), r = x * x\na: generate(4i32, sq)");
+    assert_eq!(display(&evaluator, "a"), "[0, 1, 4, 9]");
+}
+
+#[test]
+fn negative_counts_are_errors() {
+    for source in [
+        "a: fill(0, -1)",
+        "sq: (i32 x) -> (i32 r), r = x * x\na: generate(-1, sq)",
+        "n: -1\na: fill(0, n)",
+        "a: fill(0, -1i32)",
+    ] {
+        let evaluator = evaluate(source);
+        assert!(
+            matches!(
+                errors(&evaluator)[..],
+                [EvaluationErrorKind::NegativeCount(-1)]
+            ),
+            "`{source}` reported {:?}",
+            errors(&evaluator)
+        );
+    }
+}
+
+#[test]
+fn counts_over_the_iteration_limit_are_errors() {
+    for source in [
+        "a: fill(0, 100000000000)",
+        "sq: (i32 x) -> (i32 r), r = x * x\na: generate(101, sq)",
+        "a: take([1, 2], 18446744073709551615u64)",
+    ] {
+        let evaluator = evaluate_with(source, |evaluator| evaluator.set_max_iterations(100));
+        assert!(
+            matches!(
+                errors(&evaluator)[..],
+                [EvaluationErrorKind::IterationLimitExceeded(100)]
+            ),
+            "`{source}` reported {:?}",
+            errors(&evaluator)
+        );
+    }
+}
+
+#[test]
+fn builtins_over_too_many_elements_are_errors() {
+    for source in [
+        "a: enumerate(0..100000000000)",
+        "a: zip(0..101, 0..100000000000)",
+        "a: drop(0..100000000000, 3)",
+        "add: (i32 a, i32 b) -> (i32 r), r = a + b\na: reduce(0i32..101i32, 0, add)",
+        "a: sum(0..100000000000)",
+        "a: product(1..=101)",
+        "a: sorted(0..100000000000)",
+        "a: reverse(0..101)",
+    ] {
+        let evaluator = evaluate_with(source, |evaluator| evaluator.set_max_iterations(100));
+        assert!(
+            matches!(
+                errors(&evaluator)[..],
+                [EvaluationErrorKind::IterationLimitExceeded(100)]
+            ),
+            "`{source}` reported {:?}",
+            errors(&evaluator)
+        );
+    }
+
+    // Only the elements that are produced count
+    let evaluator = evaluate_with(
+        "a: zip(0..3, 0..100000000000)\nb: drop(0..103, 3)",
+        |evaluator| evaluator.set_max_iterations(100),
+    );
+    assert!(!evaluator.has_errors(), "{:?}", errors(&evaluator));
+    assert_eq!(display(&evaluator, "a"), "[0, 0, 1, 1, 2, 2]");
+}
+
+#[test]
+fn parse_int_and_parse_float_are_optional() {
+    let evaluator = evaluate_ok(
+        "a: parse_int(\" 42 \")\nb: parse_int(\"4x\")\nc: parse_float(\"1.5\")\nd: parse_float(\"\")\n\
+         e: parse_int(\"-1\")\nf: e == -1",
+    );
+    assert_eq!(display(&evaluator, "a"), "42");
+    assert_eq!(display(&evaluator, "b"), "null");
+    assert_eq!(display(&evaluator, "c"), "1.5");
+    assert_eq!(display(&evaluator, "d"), "null");
+    // Untyped integers are signed, also when they're optional
+    assert_eq!(display(&evaluator, "e"), "-1");
+    assert_eq!(display(&evaluator, "f"), "true");
+
+    let optional_integer = Type::Optional(Box::new(Type::CoercibleInteger));
+    let optional_float = Type::Optional(Box::new(Type::CoercibleFloat));
+    assert_eq!(export(&evaluator, "a").ty, optional_integer);
+    assert_eq!(export(&evaluator, "b").ty, optional_integer);
+    assert_eq!(export(&evaluator, "c").ty, optional_float);
+    assert_eq!(export(&evaluator, "d").ty, optional_float);
+}
+
+#[test]
+fn swap_exchanges_bindings_fields_and_elements() {
+    let evaluator = evaluate_ok(
+        "x: 1i32\ny: 2i32\nswap(x, y)\n\
+         P: (i32 a, i32 b)\np: P(3i32, 4i32)\nswap(p.a, p.b)\n\
+         v: [5i32, 6i32, 7i32]\nswap(v[0], v[2])\nswap(v[1], x)\n\
+         m: [[1, 2], [3, 4]]\nswap(m[0][1], m[1][0])",
+    );
+    assert_eq!(display(&evaluator, "x"), "6");
+    assert_eq!(display(&evaluator, "y"), "1");
+    assert_eq!(display(&evaluator, "p"), "{ a: 4, b: 3 }");
+    assert_eq!(display(&evaluator, "v"), "[7, 2, 5]");
+    assert_eq!(display(&evaluator, "m"), "[[1, 3], [2, 4]]");
+}
+
+#[test]
+fn swap_that_fails_leaves_both_places_as_they_were() {
+    let evaluator = evaluate(
+        "v: [1i32, 2i32]\nswap(v[0], v[0..1])\nswap(v[1], v[2])\nswap(v[0], 3)\n\
+         w: [9i32]\nswap(w, v[0..1])",
+    );
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(..),
+            EvaluationErrorKind::IndexOutOfBounds(..),
+            EvaluationErrorKind::NotAPlace,
+            EvaluationErrorKind::InvalidIndex(..),
+        ]
+    ));
+    assert_eq!(display(&evaluator, "v"), "[1, 2]");
+    assert_eq!(display(&evaluator, "w"), "[9]");
+}
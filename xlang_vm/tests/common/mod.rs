@@ -0,0 +1,110 @@
+#![allow(dead_code)]
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use xlang_core::Module;
+use xlang_util::Rf;
+use xlang_vm::{
+    const_value::ConstValue,
+    error::EvaluationErrorKind,
+    evaluator::Evaluator,
+    pass::CodePass,
+    scope::{Scope, ScopeValue},
+};
+
+/// Parses and evaluates `source` as a module. Panics if it doesn't parse.
+pub fn evaluate(source: &str) -> Evaluator {
+    evaluate_with(source, |_| {})
+}
+
+/// Like `evaluate`, with `configure` called on the evaluator before the module runs
+pub fn evaluate_with(source: &str, configure: impl FnOnce(&Evaluator)) -> Evaluator {
+    let evaluator = evaluator(source);
+    configure(&evaluator);
+    evaluator.evaluate();
+    evaluator
+}
+
+/// An evaluator for `source` that hasn't run yet. Panics if it doesn't parse.
+pub fn evaluator(source: &str) -> Evaluator {
+    let (module, errors) = Module::parse_str(source, "test");
+    assert!(
+        errors.is_empty(),
+        "`{source}` failed to parse: {:?}",
+        errors.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+    );
+
+    let module = Arc::new(module);
+    let symbol_tree = Rf::new(Scope::new(ScopeValue::Root, 0));
+    let state = CodePass::new(symbol_tree, module.clone(), 0).run();
+    Evaluator::new(module, state.scope)
+}
+
+/// The value of the top-level binding `name`. Panics if there's none.
+pub fn export(evaluator: &Evaluator, name: &str) -> ConstValue {
+    evaluator
+        .exports(true)
+        .get(name)
+        .cloned()
+        .unwrap_or_else(|| panic!("no binding named `{name}`"))
+}
+
+/// The top-level binding `name` displayed as the language would display it
+pub fn display(evaluator: &Evaluator, name: &str) -> String {
+    let float_precision = evaluator.float_precision();
+    export(evaluator, name).to_display_string(float_precision)
+}
+
+/// The kinds of the errors reported, in the order they were reported
+pub fn errors(evaluator: &Evaluator) -> Vec<EvaluationErrorKind> {
+    evaluator
+        .errors()
+        .into_iter()
+        .map(|error| error.kind)
+        .collect()
+}
+
+/// Asserts that `source` evaluates without errors, returning the evaluator
+pub fn evaluate_ok(source: &str) -> Evaluator {
+    let evaluator = evaluate(source);
+    assert!(
+        !evaluator.has_errors(),
+        "`{source}` reported {:?}",
+        errors(&evaluator)
+    );
+    evaluator
+}
+
+/// An output sink for `Evaluator::set_output` that keeps what's written to it
+#[derive(Clone, Default)]
+pub struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Evaluates `source` with its output captured, returning the evaluator and what was written
+pub fn evaluate_output(source: &str, configure: impl FnOnce(&Evaluator)) -> (Evaluator, String) {
+    let output = Output::default();
+    let evaluator = evaluate_with(source, |evaluator| {
+        evaluator.set_output(output.clone());
+        configure(evaluator);
+    });
+    (evaluator, output.contents())
+}
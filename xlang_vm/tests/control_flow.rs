@@ -0,0 +1,351 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, evaluate_with, export};
+use xlang_vm::{
+    const_value::Type,
+    error::{EvaluationErrorKind, TypeHint},
+};
+
+#[test]
+fn if_let_binds_only_a_present_optional() {
+    let evaluator = evaluate(
+        "Point: (i32 x, i32 y)\nTagged: (Point? p)\npt: Point(5i32, 6i32)\n\
+         a: if let Some(q) = Tagged(pt).p { q.x }\nb: if let Some(q) = Tagged(null).p { q.x }\n\
+         c: if let Some(q) = Tagged(pt).p { z: q.y, z + q.x }\nd: q",
+    );
+    assert_eq!(display(&evaluator, "a"), "5");
+    assert_eq!(display(&evaluator, "b"), "()");
+    assert_eq!(display(&evaluator, "c"), "11");
+    // The binding doesn't outlive the branch
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::SymbolNotFound(..)]
+    ));
+}
+
+#[test]
+fn enumerate_binds_the_index_and_element() {
+    let evaluator = evaluate(
+        "arr: [\"a\", \"b\", \"c\"]\nout: \"\"\nfor (i, x) in enumerate(arr) { out = \"{out}{i}={x};\" }\n\
+         bad: \"\"\nfor (i, x, y) in enumerate(arr) { bad = \"bad\" }",
+    );
+    assert_eq!(display(&evaluator, "out"), "0=a;1=b;2=c;");
+    assert_eq!(display(&evaluator, "bad"), "");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::PatternMismatch(..)]
+    ));
+}
+
+#[test]
+fn with_blocks_close_the_resource_on_exit() {
+    let evaluator = evaluate(
+        "log: \"\"\nFile: (i32 id)\nclose: (File f) -> (i32 r), log = \"{log} closed({f.id})\", r = 0\n\
+         with File(1i32) as r { defer log = \"{log} inner\", log = \"{log} using({r.id})\" }\n\
+         with File(2i32) as r { log = \"{log} bad\", x: r.id + \"s\" }",
+    );
+    // Closed after the block's own deferred statements, and even when the block fails
+    assert_eq!(
+        display(&evaluator, "log"),
+        " using(1) inner closed(1) bad closed(2)"
+    );
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Operand)]
+    ));
+}
+
+#[test]
+fn with_blocks_need_a_close_method() {
+    let evaluator = evaluate("with 5 as n { 1 }");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::MissingCleanup(..)]
+    ));
+}
+
+#[test]
+fn match_arms_bind_the_scrutinee() {
+    let evaluator = evaluate(
+        "a: match 21 { n => n * 2 }\nb: match (1, 2) { (x, y) => y - x }\n\
+         Tagged: (i32? v)\nc: match Tagged(4i32).v { n: i32 => n + 1i32, other => \"null\" }\n\
+         d: match Tagged(null).v { n: i32 => n + 1i32, other => \"null\" }\ne: n",
+    );
+    assert_eq!(display(&evaluator, "a"), "42");
+    assert_eq!(display(&evaluator, "b"), "1");
+    assert_eq!(display(&evaluator, "c"), "5");
+    assert_eq!(display(&evaluator, "d"), "null");
+    // The binding is scoped to its arm
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::SymbolNotFound(..)]
+    ));
+}
+
+#[test]
+fn typed_match_arms_narrow_a_union() {
+    let evaluator = evaluate_ok(
+        "P: (i32 x)\nQ: (string s)\nc: 1 < 2\n\
+         v: if c { P(1i32) } else { Q(\"a\") }\nr: match v { p: P => \"p{p.x}\", q: Q => q.s }\n\
+         w: if !c { P(1i32) } else { Q(\"a\") }\ns: match w { p: P => \"p{p.x}\", q: Q => q.s }\n\
+         t: match 5u8 { x: i32 => \"int {x}\", x: u8 => \"byte {x}\" }",
+    );
+    assert_eq!(display(&evaluator, "r"), "p1");
+    assert_eq!(display(&evaluator, "s"), "a");
+    assert_eq!(display(&evaluator, "t"), "byte 5");
+}
+
+#[test]
+fn typed_match_arms_without_a_match_are_errors() {
+    let evaluator = evaluate("a: match 7u8 { x: i32 => x }");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::NonExhaustiveMatch(..)]
+    ));
+}
+
+#[test]
+fn match_type_dispatches_on_the_runtime_type() {
+    let evaluator = evaluate_ok(
+        "Point: (i32 x, i32 y)\nOther: (i32 x)\np: Point(1i32, 2i32)\n\
+         a: match type(5) { Float => \"float\", Integer => \"integer\", _ => \"other\" }\n\
+         b: match type(p) { Other => \"other\", Point => \"point {p.y}\", _ => \"?\" }\n\
+         c: match type(1.5) { Integer => \"integer\", _ => \"fallback\" }\n\
+         d: match type(5u8) { i32 => \"i32\", u8 => \"u8\" }",
+    );
+    assert_eq!(display(&evaluator, "a"), "integer");
+    // Records match by name
+    assert_eq!(display(&evaluator, "b"), "point 2");
+    assert_eq!(display(&evaluator, "c"), "fallback");
+    assert_eq!(display(&evaluator, "d"), "u8");
+}
+
+#[test]
+fn match_type_without_a_matching_arm_is_an_error() {
+    let evaluator = evaluate("a: match type(\"s\") { Integer => \"integer\" }");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::NonExhaustiveMatch(_, Type::String)]
+    ));
+}
+
+#[test]
+fn if_elif_else_yields_the_first_true_branch() {
+    let evaluator = evaluate_ok(
+        "pick: (string s) -> (string out), \
+         out = if s == \"a\" { \"first\" } elif s == \"b\" { \"second\" } else { \"third\" }\n\
+         a: pick(\"a\")\nb: pick(\"b\")\nc: pick(\"c\")",
+    );
+    assert_eq!(display(&evaluator, "a"), "{ out: first }");
+    assert_eq!(display(&evaluator, "b"), "{ out: second }");
+    assert_eq!(display(&evaluator, "c"), "{ out: third }");
+}
+
+#[test]
+fn conditions_after_the_true_one_are_not_evaluated() {
+    let evaluator = evaluate_ok(
+        "log: \"\"\ncheck: (i32 n) -> (i32 r), log = \"{log} check{n}\", r = n\n\
+         x: if 1 == 2 { 1 } elif check(2i32).r == 2 { 2 } elif check(3i32).r == 3 { 3 }",
+    );
+    assert_eq!(display(&evaluator, "x"), "2");
+    assert_eq!(display(&evaluator, "log"), " check2");
+}
+
+#[test]
+fn non_bool_conditions_are_errors() {
+    let evaluator = evaluate("x: if 5 { 1 } else { 2 }");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::TypeMismatch(
+            Type::CoercibleInteger,
+            Type::Boolean,
+            TypeHint::Condition
+        )]
+    ));
+}
+
+#[test]
+fn repeat_runs_the_body_a_fixed_number_of_times() {
+    let evaluator = evaluate(
+        "total: 0i32\nrepeat 5 { step: 2i32, total = total + step }\n\
+         n: 3u8\ncount: 0i32\nrepeat n { count = count + 1i32 }\nrepeat 0 { count = 100i32 }\nx: step",
+    );
+    assert_eq!(display(&evaluator, "total"), "10");
+    assert_eq!(display(&evaluator, "count"), "3");
+    // Each iteration has its own scope
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::SymbolNotFound(..)]
+    ));
+}
+
+#[test]
+fn repeat_counts_must_be_non_negative_integers() {
+    let evaluator =
+        evaluate("count: 0i32\nrepeat -1i32 { count = 100i32 }\nrepeat \"x\" { count = 100i32 }");
+    assert_eq!(display(&evaluator, "count"), "0");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::NegativeCount(-1),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+        ]
+    ));
+}
+
+#[test]
+fn while_loops_re_evaluate_the_condition() {
+    let evaluator = evaluate_ok("i: 0i32\nwhile i < 10i32 { i = i + 1i32 }");
+    assert_eq!(display(&evaluator, "i"), "10");
+}
+
+#[test]
+fn while_loops_stop_at_the_iteration_limit() {
+    let evaluator = evaluate_with("j: 0i32\nwhile true { j = j + 1i32 }", |evaluator| {
+        evaluator.set_max_iterations(100)
+    });
+    assert_eq!(display(&evaluator, "j"), "100");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::IterationLimitExceeded(100)]
+    ));
+}
+
+#[test]
+fn for_loops_over_too_many_elements_are_errors() {
+    let evaluator = evaluate_with(
+        "j: 0i32\nfor i in 0..100000000000 { j = j + 1i32 }\nfor i in 0..=99 { j = j + 1i32 }",
+        |evaluator| evaluator.set_max_iterations(100),
+    );
+    // The loop over too many elements doesn't run at all
+    assert_eq!(display(&evaluator, "j"), "100");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::IterationLimitExceeded(100)]
+    ));
+}
+
+#[test]
+fn while_conditions_must_be_bool() {
+    let evaluator = evaluate("j: 0i32\nwhile 1i32 { j = 1i32 }");
+    assert_eq!(display(&evaluator, "j"), "0");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Condition)]
+    ));
+}
+
+#[test]
+fn combined_match_arms_match_any_alternative() {
+    let evaluator = evaluate_ok(
+        "f: (i32 v) -> (i32 r), r = match v { 1 | 2 | 3 => 10i32, 4 => 20i32, x => 0i32 }\n\
+         a: f(1i32).r\nb: f(2i32).r\nc: f(3i32).r\nd: f(4i32).r\ne: f(9i32).r\n\
+         s: match \"b\" { \"a\" | \"b\" => 1, other => 2 }",
+    );
+    for (name, expected) in [
+        ("a", "10"),
+        ("b", "10"),
+        ("c", "10"),
+        ("d", "20"),
+        ("e", "0"),
+        ("s", "1"),
+    ] {
+        assert_eq!(display(&evaluator, name), expected, "{name}");
+    }
+}
+
+#[test]
+fn combined_match_arms_of_mixed_types_are_errors() {
+    let evaluator = evaluate("x: match 5i32 { 1 | \"x\" => 1, y => 2 }");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::TypeMismatch(
+            Type::String,
+            _,
+            TypeHint::Pattern
+        )]
+    ));
+}
+
+#[test]
+fn match_compares_literal_patterns() {
+    let evaluator = evaluate_ok(
+        "a: match 2 { 1 => \"one\", 2 => \"two\", _ => \"many\" }\n\
+         c: match 1.5 { 1.5 => 1, _ => 0 }\nd: match false { true => 1, false => 0 }\n\
+         s: match \"b\" { \"a\" => 1, \"b\" => 2 }",
+    );
+    assert_eq!(display(&evaluator, "a"), "two");
+    assert_eq!(display(&evaluator, "c"), "1");
+    assert_eq!(display(&evaluator, "d"), "0");
+    assert_eq!(display(&evaluator, "s"), "2");
+}
+
+#[test]
+fn match_falls_through_to_the_wildcard() {
+    let evaluator = evaluate_ok(
+        "b: match 7 { 1 => \"one\", _ => \"many\" }\nd: match false { true => 1, _ => 0 }",
+    );
+    assert_eq!(display(&evaluator, "b"), "many");
+    assert_eq!(display(&evaluator, "d"), "0");
+}
+
+#[test]
+fn match_without_a_matching_arm_is_an_error() {
+    let evaluator =
+        evaluate("k: match \"z\" { \"a\" => 1, \"b\" => 2 }\nf: match 9i32 { 1i32 => 1 }");
+    assert_eq!(display(&evaluator, "k"), "()");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::NonExhaustiveMatch(k, Type::String),
+            EvaluationErrorKind::NonExhaustiveMatch(f, Type::Integer { width: 32, signed: true }),
+        ] if k == "z" && f == "9"
+    ));
+}
+
+const RECORDS: &str = "A: (i32 x)\nB: (i32 y)\n";
+
+#[test]
+fn branches_yielding_different_records_have_a_union_type() {
+    for branch in [
+        "v: if c { A(1i32) } else { B(2i32) }",
+        "v: match c { true => A(1i32), false => B(2i32) }",
+    ] {
+        let evaluator = evaluate_ok(&format!("{RECORDS}c: false\n{branch}"));
+        let value = export(&evaluator, "v");
+        let Type::Union(types) = &value.ty else {
+            panic!("`{branch}` has type {}", value.ty);
+        };
+        assert_eq!(types.len(), 2);
+        assert_eq!(value.ty.to_string(), "(i32 x) | (i32 y)");
+        assert_eq!(display(&evaluator, "v"), "{ y: 2 }");
+        assert_eq!(value.narrow().ty, types[1]);
+    }
+}
+
+#[test]
+fn branches_yielding_the_same_record_keep_its_type() {
+    let evaluator = evaluate_ok(&format!(
+        "{RECORDS}c: true\nv: if c {{ A(1i32) }} else {{ A(2i32) }}"
+    ));
+    assert!(matches!(
+        export(&evaluator, "v").ty,
+        Type::RecordInstance { .. }
+    ));
+}
+
+#[test]
+fn union_values_are_used_as_the_record_they_are() {
+    let evaluator = evaluate_ok(&format!(
+        "{RECORDS}c: false\nv: if c {{ A(1i32) }} else {{ B(2i32) }}\n\
+         k: match type(v) {{ A => 1, B => 2 }}\n\
+         y: v.y\n\
+         f: (B b) -> (i32 r), r = b.y\n\
+         z: f(v)\n\
+         s: [v, B(3i32)]"
+    ));
+    assert_eq!(display(&evaluator, "k"), "2");
+    assert_eq!(display(&evaluator, "y"), "2");
+    assert_eq!(display(&evaluator, "z"), "{ r: 2 }");
+    assert_eq!(display(&evaluator, "s"), "[{ y: 2 }, { y: 3 }]");
+}
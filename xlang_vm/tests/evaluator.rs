@@ -0,0 +1,111 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, evaluator, Output};
+use xlang_vm::error::{EvaluationErrorKind, TypeHint};
+
+#[test]
+fn evaluate_checked_returns_the_values() {
+    let evaluator = evaluator("a: 1 + 2\na * 2");
+    let values = evaluator.evaluate_checked().expect("no errors");
+    assert_eq!(
+        values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>(),
+        ["()", "6"]
+    );
+}
+
+#[test]
+fn evaluate_checked_drains_the_errors() {
+    let evaluator = evaluator("a: 1 + \"s\"");
+    let Err(errors) = evaluator.evaluate_checked() else {
+        panic!("`evaluate_checked` succeeded");
+    };
+    assert!(matches!(
+        errors[..],
+        [ref error] if matches!(error.kind, EvaluationErrorKind::TypeMismatch(..))
+    ));
+    assert!(!evaluator.has_errors());
+}
+
+#[test]
+fn errors_are_read_and_taken_through_the_evaluator() {
+    let evaluator = evaluate("a: 1 + \"s\"");
+    assert!(evaluator.has_errors());
+    let errors = evaluator.errors();
+    assert!(matches!(
+        errors[..],
+        [ref error] if matches!(error.kind, EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Operand))
+    ));
+    // Reading them leaves them in place, taking them doesn't
+    assert_eq!(evaluator.errors().len(), 1);
+    assert_eq!(evaluator.take_errors().len(), 1);
+    assert!(!evaluator.has_errors());
+    assert!(evaluator.errors().is_empty());
+}
+
+#[test]
+fn unknown_identifiers_are_reported_once() {
+    for source in [
+        "a: nope + 1",
+        "a: -nope",
+        "a: nope.x",
+        "a: nope(1)",
+        "a: nope == 1",
+        "a: if nope { 1 } else { 2 }",
+        "a: nope[0]",
+        "a: for x in nope { x }",
+        "a: zip(nope, [1])",
+    ] {
+        let errors = errors(&evaluate(source));
+        assert!(
+            matches!(&errors[..], [EvaluationErrorKind::SymbolNotFound(name)] if name == "nope"),
+            "`{source}` reported {errors:?}"
+        );
+    }
+}
+
+#[test]
+fn statements_can_be_stepped_through() {
+    let output = Output::default();
+    let evaluator = evaluator("a: 1\nb: a + 1\ndefer print(\"done\")\nc: b * 10");
+    evaluator.set_output(output.clone());
+    let mut statements = evaluator.statements();
+
+    statements.next().unwrap();
+    assert_eq!(display(&evaluator, "a"), "1");
+    // Later statements haven't been evaluated yet
+    assert_eq!(display(&evaluator, "b"), "()");
+    statements.next().unwrap();
+    assert_eq!(display(&evaluator, "b"), "2");
+    assert_eq!(display(&evaluator, "c"), "()");
+    statements.next().unwrap();
+    statements.next().unwrap();
+    assert_eq!(display(&evaluator, "c"), "20");
+
+    // Deferred expressions run once the last statement has been evaluated
+    assert_eq!(output.contents(), "");
+    assert!(statements.next().is_none());
+    assert_eq!(output.contents(), "done");
+}
+
+#[test]
+fn repeated_lookups_see_scope_changes() {
+    let evaluator = evaluate_ok(
+        "x: 1i32\ntotal: 0i32\nrepeat 1000 { total = total + x }\n\
+         f: (i32 x) -> (i32 r), r = x * 10i32\na: f(5i32)\nb: x\n\
+         get: () -> (i32 r), r = x\nbefore: get().r\nx = 5i32\nchanged: get().r\n\
+         inner: 0i32\nfor i in 0..2i32 { inner = inner + x, y: x, x: 10i32, inner = inner + x + y }\n\
+         last: x",
+    );
+    assert_eq!(display(&evaluator, "total"), "1000");
+    // Parameters shadow the binding only inside the call
+    assert_eq!(display(&evaluator, "a"), "{ r: 50 }");
+    assert_eq!(display(&evaluator, "b"), "1");
+    assert_eq!(display(&evaluator, "before"), "1");
+    assert_eq!(display(&evaluator, "changed"), "5");
+    // 5 + (10 + 5), then 10 + (10 + 10) once the loop body has reassigned `x`
+    assert_eq!(display(&evaluator, "inner"), "50");
+    assert_eq!(display(&evaluator, "last"), "10");
+}
@@ -0,0 +1,375 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, evaluate_with};
+use xlang_vm::{
+    const_value::Type,
+    error::{EvaluationError, EvaluationErrorKind, Impurity, TypeHint},
+    evaluator::PropertyCalls,
+};
+
+#[test]
+fn method_calls_pass_the_receiver_first() {
+    let evaluator = evaluate_ok(
+        "P: (i32 x, i32 y)\nadd: (P p, i32 n) -> (i32 r), r = p.x + n\nq: P(1, 2)\na: q.add(3).r",
+    );
+    assert_eq!(display(&evaluator, "a"), "4");
+}
+
+#[test]
+fn method_calls_check_the_arguments_after_the_receiver() {
+    let evaluator = evaluate(
+        "P: (i32 x)\nadd: (P p, i32 n) -> (i32 r), r = p.x + n\nq: P(1)\na: q.add(3, 4)\nb: q.add(\"a\")",
+    );
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::ArgCountMismatch(3, 2),
+            EvaluationErrorKind::TypeMismatch(..),
+        ]
+    ));
+}
+
+#[test]
+fn pipelines_pass_the_left_side_as_the_first_argument() {
+    let evaluator = evaluate_ok(
+        "double: (i32 a) -> (i32 b), b = a * 2\nadd: (i32 a, i32 c) -> (i32 b), b = a + c\n\
+         x: (5 |> double).b\ny: (5 |> add(3)).b",
+    );
+    assert_eq!(display(&evaluator, "x"), "10");
+    assert_eq!(display(&evaluator, "y"), "8");
+}
+
+#[test]
+fn pipelines_into_a_non_function_are_errors() {
+    let evaluator = evaluate("n: 4\nw: 5 |> n");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::NotCallable(_)]
+    ));
+}
+
+#[test]
+fn pure_functions_may_call_other_pure_functions() {
+    let evaluator = evaluate_ok(
+        "sq: pure (i32 a) -> (i32 b), b = a * a\n\
+         quad: pure (i32 a) -> (i32 b), c: sq(a).b, b = sq(c).b\nx: quad(2).b",
+    );
+    assert_eq!(display(&evaluator, "x"), "16");
+}
+
+#[test]
+fn impure_pure_functions_are_errors() {
+    let evaluator = evaluate(
+        "use std.io\ncount: 0\nnoisy: (i32 a) -> (i32 b), b = a\n\
+         bad: pure (i32 a) -> (i32 b), count = a, print(\"x\"), b = noisy(a).b",
+    );
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::ImpureFunction(f1, Impurity::OuterAssignment(count)),
+            EvaluationErrorKind::ImpureFunction(f2, Impurity::ImpureCall(print)),
+            EvaluationErrorKind::ImpureFunction(f3, Impurity::ImpureCall(noisy)),
+        ] if [f1, f2, f3].iter().all(|f| *f == "bad")
+            && count == "count"
+            && print == "print"
+            && noisy == "noisy"
+    ));
+}
+
+#[test]
+fn errors_in_calls_capture_the_call_stack() {
+    let evaluator = evaluate(
+        "c: (i32 x) -> (i32 r), r = x + \"s\"\nb: (i32 x) -> (i32 r), r = c(x).r\n\
+         a: (i32 x) -> (i32 r), r = b(x).r\nd: a(1)\ne: c(2)",
+    );
+    let frames = |error: &EvaluationError| {
+        error
+            .backtrace
+            .iter()
+            .flatten()
+            .map(|frame| (frame.name.clone(), frame.range.start.line_num))
+            .collect::<Vec<_>>()
+    };
+    let errors = evaluator.errors();
+    assert_eq!(errors.len(), 2);
+    // Outermost first, each frame at its call site
+    assert_eq!(
+        frames(&errors[0]),
+        [
+            ("a".to_string(), 3),
+            ("b".to_string(), 2),
+            ("c".to_string(), 1)
+        ]
+    );
+    // The stack is unwound after the first error
+    assert_eq!(frames(&errors[1]), [("c".to_string(), 4)]);
+}
+
+#[test]
+fn failed_preconditions_are_errors() {
+    let evaluator = evaluate(
+        "div: (i32 a, i32 b) -> (i32 out) requires \"{b}\" != \"0\", out = a / b\n\
+         a: div(10i32, 2i32).out\nb: div(10i32, 0i32)\nc: (i32 n) -> (i32 out) requires n, out = n\nd: c(1i32)",
+    );
+    assert_eq!(display(&evaluator, "a"), "5");
+    let errors = evaluator.errors();
+    assert!(matches!(
+        errors.iter().map(|error| &error.kind).collect::<Vec<_>>()[..],
+        [
+            EvaluationErrorKind::PreconditionFailed,
+            EvaluationErrorKind::TypeMismatch(..),
+        ]
+    ));
+    // Reported at the condition rather than the call
+    assert_eq!(errors[0].range.start.line_num, 0);
+    assert_eq!(errors[0].range.start.position, 42);
+}
+
+#[test]
+fn deferred_statements_run_last_in_reverse_order() {
+    let evaluator = evaluate_ok(
+        "log: \"start\"\n\
+         f: (i32 a) -> (i32 r), defer log = \"{log} first\", defer log = \"{log} second\", log = \"{log} body\", r = a\n\
+         x: f(3i32).r",
+    );
+    assert_eq!(display(&evaluator, "x"), "3");
+    assert_eq!(display(&evaluator, "log"), "start body second first");
+}
+
+#[test]
+fn deferred_statements_run_on_early_return_and_error() {
+    let evaluator = evaluate(
+        "log: \"start\"\n\
+         h: (i32 a) -> (i32 r), defer log = \"{log} returned\", r = a, return, log = \"{log} skipped\"\n\
+         x: h(2i32).r\n\
+         g: (i32 a) -> (i32 r), defer log = \"{log} failed\", r = a + \"oops\"\ny: g(1i32)",
+    );
+    assert_eq!(display(&evaluator, "x"), "2");
+    assert_eq!(display(&evaluator, "log"), "start returned failed");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Operand)]
+    ));
+}
+
+#[test]
+fn assignments_through_returned_references_change_the_original() {
+    let evaluator = evaluate_ok(
+        "Point: (i32 x, i32 y)\np: Point(1i32, 2i32)\nf: (i32 a) -> (&i32 r), r = &p.x\n\
+         g: (i32 v) -> (i32 out), *f(0i32).r = v, out = p.x\na: g(5i32).out\n\
+         n: 3i32\nh: (&i32 r, i32 v) -> (i32 out), *r = v, out = v\nb: h(&n, 4i32).out",
+    );
+    assert_eq!(display(&evaluator, "a"), "5");
+    assert_eq!(display(&evaluator, "p"), "{ x: 5, y: 2 }");
+    assert_eq!(display(&evaluator, "b"), "4");
+    assert_eq!(display(&evaluator, "n"), "4");
+}
+
+#[test]
+fn assignments_through_references_are_checked() {
+    let evaluator = evaluate(
+        "n: 3i32\nh: (&i32 r) -> (i32 out), *r = \"x\", out = 1i32\nb: h(&n).out\n\
+         const N = 3i32\ng: (&i32 r, i32 v) -> (i32 out), *r = v, out = v\nc: g(&N, 4i32).out",
+    );
+    assert_eq!(display(&evaluator, "n"), "3");
+    assert_eq!(display(&evaluator, "N"), "3");
+    // Referencing the constant is the only error, not also the argument it leaves empty
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(Type::String, _, TypeHint::Variable),
+            EvaluationErrorKind::AssignToImmutable(name),
+        ] if name == "N"
+    ));
+}
+
+#[test]
+fn record_parameters_can_be_destructured() {
+    let evaluator = evaluate_ok(
+        "Point: (i32 x, i32 y)\nsum: (Point {x, y}) -> (i32 r), r = x + y\n\
+         pd: pure (Point {x, y}) -> (i32 r), r = x - y\np: Point(3i32, 4i32)\n\
+         a: sum(p).r\nb: pd(Point(9i32, 4i32)).r",
+    );
+    assert_eq!(display(&evaluator, "a"), "7");
+    assert_eq!(display(&evaluator, "b"), "5");
+}
+
+#[test]
+fn destructured_parameters_need_a_record_with_the_fields() {
+    let evaluator = evaluate(
+        "Point: (i32 x, i32 y)\nthree: (Point {x, z}) -> (i32 r), r = x\nodd: (i32 {x}) -> (i32 r), r = x\n\
+         sum: (Point {x, y}) -> (i32 r), r = x + y\na: three(Point(3i32, 4i32))\nb: odd(5i32)\nc: sum(5i32)",
+    );
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::MissingField(z, _),
+            EvaluationErrorKind::ExpectedRecord(..),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+        ] if z == "z"
+    ));
+}
+
+#[test]
+fn functions_can_call_themselves() {
+    let evaluator = evaluate_ok(
+        "fact: (i32 n) -> (i32 r), r = if n <= 1i32 { 1i32 } else { n * fact(n - 1i32).r }\n\
+         a: fact(5i32).r\nb: fact(5i32).r == 120i32\nc: fact(1i32).r\nafter: 3i32",
+    );
+    assert_eq!(display(&evaluator, "a"), "120");
+    assert_eq!(display(&evaluator, "b"), "true");
+    assert_eq!(display(&evaluator, "c"), "1");
+    // Declarations after the calls still land in the module scope
+    assert!(evaluator.exports(false).contains_key("after"));
+}
+
+#[test]
+fn return_unwinds_nested_blocks() {
+    let evaluator = common::evaluator(
+        "find: (i32 limit) -> (i32 r), r = 0i32, i: 0i32, \
+         if limit > 0i32 { while true { i = i + 1i32, if i == limit { return i * 10i32 } } }, r = 99i32\n\
+         a: find(4i32).r\nb: find(0i32).r\nearly: (i32 v) -> (i32 r), r = v, return, r = 0i32\nd: early(6i32).r",
+    );
+    let depth = || evaluator.state.read().unwrap().scope.current_scopes().len();
+    let before = depth();
+    evaluator.evaluate();
+    assert!(!evaluator.has_errors(), "{:?}", errors(&evaluator));
+    assert_eq!(display(&evaluator, "a"), "40");
+    assert_eq!(display(&evaluator, "b"), "99");
+    assert_eq!(display(&evaluator, "d"), "6");
+    // Every scope entered on the way to the `return` was popped
+    assert_eq!(depth(), before);
+}
+
+#[test]
+fn return_outside_a_function_is_an_error() {
+    let evaluator = evaluate("return 5");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::ReturnOutsideFunction]
+    ));
+}
+
+#[test]
+fn return_values_must_match_their_declared_type() {
+    let evaluator = evaluate(
+        "wide: (i32 v) -> (i8 r), r = 300i32\na: wide(1i32)\nok: (i32 v) -> (i64 r), r = 5\nb: ok(1i32).r\n\
+         s: (i32 v) -> (i32 r), r = \"x\"\nc: s(1i32)",
+    );
+    // Untyped literals are coerced to the declared type
+    assert_eq!(display(&evaluator, "b"), "5");
+    let errors = evaluator.errors();
+    assert!(matches!(
+        errors.iter().map(|error| &error.kind).collect::<Vec<_>>()[..],
+        [
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::ReturnParameter),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::ReturnParameter),
+        ]
+    ));
+    // Reported at the call
+    assert_eq!(errors[0].range.start.line_num, 1);
+    assert_eq!(errors[1].range.start.line_num, 5);
+}
+
+const PROPERTIES: &str =
+    "R: (i32 v, i32 double)\ndouble: (R r) -> (i32 d), d = r.v * 2i32\ns: \"hello\"\n\
+                          r: R(3i32, 7i32)\na: s.len\nb: s.upper\nc: r.double";
+
+#[test]
+fn zero_argument_methods_are_called_as_properties_when_enabled() {
+    let evaluator = evaluate_with(PROPERTIES, |evaluator| {
+        evaluator.set_property_calls(PropertyCalls::MembersFirst)
+    });
+    assert!(!evaluator.has_errors(), "{:?}", errors(&evaluator));
+    assert_eq!(display(&evaluator, "a"), "5");
+    assert_eq!(display(&evaluator, "b"), "HELLO");
+    // The member wins over the method of the same name
+    assert_eq!(display(&evaluator, "c"), "7");
+
+    let evaluator = evaluate_with(PROPERTIES, |evaluator| {
+        evaluator.set_property_calls(PropertyCalls::MethodsFirst)
+    });
+    assert!(!evaluator.has_errors(), "{:?}", errors(&evaluator));
+    assert_eq!(display(&evaluator, "c"), "{ d: 6 }");
+}
+
+#[test]
+fn zero_argument_methods_are_not_properties_by_default() {
+    let evaluator = evaluate(PROPERTIES);
+    assert_eq!(display(&evaluator, "c"), "7");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::SymbolNotFound(..),
+            EvaluationErrorKind::SymbolNotFound(..),
+        ]
+    ));
+}
+
+#[test]
+fn calls_with_the_wrong_number_of_arguments_are_errors() {
+    let evaluator = evaluate(
+        "f: (i32 a, i32 b) -> (i32 r), r = a + b\nx: f(1i32)\ny: f(1i32, 2i32, 3i32)\n\
+         z: f(1i32, 2i32)\nw: f(1i32).r",
+    );
+    assert_eq!(display(&evaluator, "z"), "{ r: 3 }");
+    assert_eq!(display(&evaluator, "x"), "()");
+    assert_eq!(display(&evaluator, "y"), "()");
+    // The body isn't evaluated, so nothing else is reported
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::ArgCountMismatch(1, 2),
+            EvaluationErrorKind::ArgCountMismatch(3, 2),
+            EvaluationErrorKind::ArgCountMismatch(1, 2),
+        ]
+    ));
+}
+
+#[test]
+fn unbounded_recursion_is_reported_without_overflowing() {
+    let evaluator = evaluate("f: (i32 n) -> (i32 r), r = f(n).r\na: f(1i32)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::RecursionLimitExceeded(256)]
+    ));
+}
+
+#[test]
+fn recursion_up_to_the_call_depth_limit() {
+    let evaluator = evaluate_ok(
+        "count: (i32 n) -> (i32 r), r = if n == 0i32 { 0i32 } else { count(n - 1i32).r + 1i32 }\na: count(250i32).r",
+    );
+    assert_eq!(display(&evaluator, "a"), "250");
+}
+
+#[test]
+fn call_depth_limit_is_configurable() {
+    let evaluator = evaluate_with(
+        "f: (i32 n) -> (i32 r), r = f(n).r\na: f(1i32)",
+        |evaluator| evaluator.set_max_call_depth(1000),
+    );
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::RecursionLimitExceeded(1000)]
+    ));
+}
+
+#[test]
+fn bool_and_string_parameters_and_return_values() {
+    let evaluator = evaluate_ok(
+        "even: (i32 n) -> (bool r), r = n % 2i32 == 0i32\ngreet: (string name) -> (string r), r = \"hi {name}\"\na: even(4i32).r\nb: even(3i32).r\nc: greet(\"x\").r",
+    );
+    assert_eq!(display(&evaluator, "a"), "true");
+    assert_eq!(display(&evaluator, "b"), "false");
+    assert_eq!(display(&evaluator, "c"), "hi x");
+}
+
+#[test]
+fn bool_parameters_reject_other_types() {
+    let evaluator = evaluate("not: (bool b) -> (bool r), r = !b\na: not(1)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::TypeMismatch(..)]
+    ));
+}
@@ -0,0 +1,348 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, export};
+use xlang_vm::{
+    const_value::Type,
+    error::{EvaluationErrorKind, TypeHint},
+};
+
+#[test]
+fn and_binds_tighter_than_or() {
+    for a in [false, true] {
+        for b in [false, true] {
+            for c in [false, true] {
+                let source = format!(
+                    "a: {a}\nb: {b}\nc: {c}\n\
+                     x: a || b && c\ny: (a || b) && c\nz: a && b || c\nw: a && (b || c)"
+                );
+                let evaluator = evaluate_ok(&source);
+                assert_eq!(
+                    display(&evaluator, "x"),
+                    (a || b && c).to_string(),
+                    "{source}"
+                );
+                assert_eq!(
+                    display(&evaluator, "y"),
+                    ((a || b) && c).to_string(),
+                    "{source}"
+                );
+                assert_eq!(
+                    display(&evaluator, "z"),
+                    (a && b || c).to_string(),
+                    "{source}"
+                );
+                assert_eq!(
+                    display(&evaluator, "w"),
+                    (a && (b || c)).to_string(),
+                    "{source}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn mixed_integer_and_float_exponents_are_floats() {
+    let evaluator =
+        evaluate_ok("a: 2 ** 2.0\nb: 2.0 ** 2\ng: (i32 x) -> (f64 r), r = x ** 0.5\nc: g(9).r");
+    assert_eq!(display(&evaluator, "a"), "4");
+    assert_eq!(display(&evaluator, "b"), "4");
+    assert_eq!(display(&evaluator, "c"), "3");
+    assert_eq!(export(&evaluator, "a").ty, Type::CoercibleFloat);
+    assert_eq!(export(&evaluator, "b").ty, Type::CoercibleFloat);
+    assert_eq!(export(&evaluator, "c").ty, Type::Float { width: 64 });
+}
+
+#[test]
+fn null_conditional_access_short_circuits_on_null() {
+    let evaluator = evaluate_ok(
+        "Point: (i32 x, i32 y)\nTagged: (Point? p)\nget: (Point? p) -> (i32? x), x = p?.x\n\
+         pt: Point(5, 2)\na: pt?.y\nb: get(pt).x\nc: get(null).x\nd: Tagged(pt).p?.x\ne: Tagged(null).p?.x",
+    );
+    // Through a value that isn't optional it's a plain member access
+    assert_eq!(display(&evaluator, "a"), "2");
+    assert_eq!(
+        export(&evaluator, "a").ty,
+        Type::Integer {
+            width: 32,
+            signed: true
+        }
+    );
+    assert_eq!(display(&evaluator, "b"), "5");
+    assert_eq!(display(&evaluator, "c"), "null");
+    assert_eq!(display(&evaluator, "d"), "5");
+    assert_eq!(display(&evaluator, "e"), "null");
+}
+
+#[test]
+fn bitwise_operators_on_booleans_are_logical() {
+    let evaluator = evaluate_ok(
+        "t: true\nf: false\na: t & f\nb: t | f\nc: t ^ t\nd: t ^ f\ne: 6 & 3\ng: 6 | 3\nh: 6 ^ 3",
+    );
+    for (name, expected) in [("a", "false"), ("b", "true"), ("c", "false"), ("d", "true")] {
+        assert_eq!(display(&evaluator, name), expected, "{name}");
+        assert_eq!(export(&evaluator, name).ty, Type::Boolean);
+    }
+    for (name, expected) in [("e", "2"), ("g", "7"), ("h", "5")] {
+        assert_eq!(display(&evaluator, name), expected, "{name}");
+        assert_eq!(export(&evaluator, name).ty, Type::CoercibleInteger);
+    }
+}
+
+#[test]
+fn bitwise_operators_on_a_boolean_and_an_integer_are_errors() {
+    let evaluator = evaluate("a: true & 1");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::BinExpMismatch { .. }]
+    ));
+}
+
+#[test]
+fn member_access_on_an_optional_record() {
+    let evaluator = evaluate(
+        "Point: (i32 x, i32 y)\nTagged: (Point? p)\nsome: Tagged(Point(5, 2)).p\nnone: Tagged(null).p\n\
+         a: some.x\nb: none?.x\nc: none.x",
+    );
+    assert_eq!(display(&evaluator, "a"), "5");
+    assert_eq!(display(&evaluator, "b"), "null");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::NullAccess(..)]
+    ));
+}
+
+#[test]
+fn exponentiation_is_right_associative() {
+    let evaluator = evaluate_ok(
+        "a: 2 ** 3 ** 2\nb: (2 ** 3) ** 2\nc: 2 ** 2 ** 3 ** 1\nd: 2 * 3 ** 2\ne: 2 ** 3 * 2",
+    );
+    assert_eq!(display(&evaluator, "a"), "512");
+    assert_eq!(display(&evaluator, "b"), "64");
+    assert_eq!(display(&evaluator, "c"), "256");
+    assert_eq!(display(&evaluator, "d"), "18");
+    assert_eq!(display(&evaluator, "e"), "16");
+}
+
+#[test]
+fn optionals_compare_by_their_contents() {
+    let evaluator = evaluate_ok(
+        "Opt: (i32? v)\na: Opt(null).v\nb: Opt(null).v\nc: Opt(3i32).v\nd: Opt(5i32).v\ne: Opt(3i32).v\n\
+         nn: a == b\nnn2: a != b\nnp: a == c\nnp2: a < c\nnp3: c > a\n\
+         pp: c < d\npp2: c == e\npp3: c != d\npp4: c >= e\npv: c == 3i32",
+    );
+    for (name, expected) in [
+        ("nn", "true"),
+        ("nn2", "false"),
+        ("np", "false"),
+        // Null sorts before any value
+        ("np2", "true"),
+        ("np3", "true"),
+        ("pp", "true"),
+        ("pp2", "true"),
+        ("pp3", "true"),
+        ("pp4", "true"),
+        ("pv", "true"),
+    ] {
+        assert_eq!(display(&evaluator, name), expected, "{name}");
+    }
+}
+
+#[test]
+fn optionals_of_incomparable_types_are_errors() {
+    let evaluator = evaluate("Opt: (i32? v)\nc: Opt(3i32).v\nx: c == \"x\"");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::IncomparableTypes(..)]
+    ));
+}
+
+#[test]
+fn integer_division_by_zero_is_an_error() {
+    let evaluator = evaluate("a: 5 / 0");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::DivisionByZero]
+    ));
+
+    let evaluator = evaluate("a: 5i32 / 0\nb: 5 % 0\nc: 5i32 % 0i32");
+    assert_eq!(errors(&evaluator).len(), 3);
+    assert!(errors(&evaluator)
+        .iter()
+        .all(|error| matches!(error, EvaluationErrorKind::DivisionByZero)));
+}
+
+#[test]
+fn float_division_by_zero_follows_ieee() {
+    let evaluator = evaluate_ok("a: 5.0 / 0.0\nb: 1.0 % 0.0\nc: 6 / 3");
+    assert_eq!(display(&evaluator, "a"), "inf");
+    assert_eq!(display(&evaluator, "b"), "NaN");
+    assert_eq!(display(&evaluator, "c"), "2");
+}
+
+#[test]
+fn logical_operators_short_circuit() {
+    let evaluator = evaluate_ok(
+        "log: \"\"\nmark: () -> (i32 r), log = \"{log}x\", r = 1i32\n\
+         c: false && mark().r == 1i32\nlc: log\nd: true || mark().r == 1i32\nld: log\n\
+         e: true && mark().r == 1i32\nle: log",
+    );
+    assert_eq!(display(&evaluator, "c"), "false");
+    assert_eq!(display(&evaluator, "lc"), "");
+    assert_eq!(display(&evaluator, "d"), "true");
+    assert_eq!(display(&evaluator, "ld"), "");
+    // The right side runs when the left doesn't decide the result
+    assert_eq!(display(&evaluator, "e"), "true");
+    assert_eq!(display(&evaluator, "le"), "x");
+}
+
+#[test]
+fn logical_operators_need_booleans() {
+    let evaluator = evaluate("g: 1 && true\ni: !5\nj: false || 5");
+    let errors = evaluator.errors();
+    assert!(errors.iter().all(|error| matches!(
+        error.kind,
+        EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Operand)
+    )));
+    // Each points at the offending operand
+    let columns = errors
+        .iter()
+        .map(|error| (error.range.start.line_num, error.range.start.position))
+        .collect::<Vec<_>>();
+    assert_eq!(columns, [(0, 3), (1, 4), (2, 12)]);
+}
+
+#[test]
+fn prefix_and_postfix_increment_and_decrement() {
+    let evaluator = evaluate_ok(
+        "x: 5i32\na: x++\nb: x\nc: ++x\nd: x--\ne: --x\nf: x\n\
+         P: (i32 v)\np: P(1i32)\np.v++\npv: p.v\ni: 0\nwhile i < 3 { i++ }",
+    );
+    // Postfix yields the old value and prefix the new one
+    for (name, expected) in [
+        ("a", "5"),
+        ("b", "6"),
+        ("c", "7"),
+        ("d", "7"),
+        ("e", "5"),
+        ("f", "5"),
+    ] {
+        assert_eq!(display(&evaluator, name), expected, "{name}");
+    }
+    assert_eq!(display(&evaluator, "pv"), "2");
+    assert_eq!(display(&evaluator, "i"), "3");
+}
+
+#[test]
+fn increments_check_overflow_and_their_operand() {
+    let evaluator = evaluate("m: 127i8\ng: m++\nn: 0u8\nh: n--\nfl: 1.5\nfl++\n5++");
+    assert_eq!(display(&evaluator, "m"), "127");
+    assert_eq!(display(&evaluator, "n"), "0");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IntegerOverflow(a, _),
+            EvaluationErrorKind::IntegerOverflow(b, _),
+            EvaluationErrorKind::UnaryExpMismatch(..),
+            EvaluationErrorKind::NotAPlace,
+        ] if a == "128" && b == "-1"
+    ));
+}
+
+#[test]
+fn negation_keeps_the_width_of_sized_integers() {
+    let evaluator = evaluate_ok("a: -5\nb: -3.14\nc: -5i8\nh: +5u8");
+    assert_eq!(display(&evaluator, "a"), "-5");
+    assert_eq!(display(&evaluator, "b"), "-3.14");
+    assert_eq!(export(&evaluator, "b").ty, Type::CoercibleFloat);
+    assert_eq!(display(&evaluator, "c"), "-5");
+    assert_eq!(
+        export(&evaluator, "c").ty,
+        Type::Integer {
+            width: 8,
+            signed: true
+        }
+    );
+    assert_eq!(display(&evaluator, "h"), "5");
+}
+
+#[test]
+fn negating_unsigned_integers_is_an_error() {
+    let evaluator = evaluate("d: 5u8\ne: -d\nf: -(1u32)\ng: -(-128i8)");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::UnaryExpMismatch(..),
+            EvaluationErrorKind::UnaryExpMismatch(..),
+            EvaluationErrorKind::IntegerOverflow(value, _),
+        ] if value == "128"
+    ));
+}
+
+#[test]
+fn arithmetic_overflow_is_checked_against_the_width() {
+    let evaluator = evaluate(
+        "a: 200u8 * 200u8\nb: 100u8 + 100u8\nc: 0u8 - 1u8\nd: 127i8 + 1\nf: 9223372036854775807 + 1\n\
+         g: 2 ** 62\nh: 2 ** 63",
+    );
+    assert_eq!(display(&evaluator, "b"), "200");
+    assert_eq!(display(&evaluator, "g"), "4611686018427387904");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IntegerOverflow(a, Type::Integer { width: 8, signed: false }),
+            EvaluationErrorKind::IntegerOverflow(c, Type::Integer { width: 8, signed: false }),
+            EvaluationErrorKind::IntegerOverflow(d, Type::Integer { width: 8, signed: true }),
+            // Untyped integers overflow at the bounds of an i64
+            EvaluationErrorKind::IntegerOverflow(f, Type::CoercibleInteger),
+            EvaluationErrorKind::IntegerOverflow(h, Type::CoercibleInteger),
+        ] if a == "40000" && c == "-1" && d == "128"
+            && f == "9223372036854775808" && h == "9223372036854775808"
+    ));
+}
+
+#[test]
+fn unary_minus_and_plus() {
+    let evaluator = evaluate_ok("a: -3\nb: 2 * -3\nc: -2 ** 2\nd: +4\ne: -(1 + 2)\nf: -1.5");
+    assert_eq!(display(&evaluator, "a"), "-3");
+    assert_eq!(display(&evaluator, "b"), "-6");
+    assert_eq!(display(&evaluator, "c"), "-4");
+    assert_eq!(display(&evaluator, "d"), "4");
+    assert_eq!(display(&evaluator, "e"), "-3");
+    assert_eq!(display(&evaluator, "f"), "-1.5");
+}
+
+#[test]
+fn negative_untyped_integers_keep_their_sign() {
+    let evaluator = evaluate_ok("a: (-2) ** 2.0\nb: (-8) ** 0.0\nc: -1 < 0");
+    assert_eq!(display(&evaluator, "a"), "4");
+    assert_eq!(display(&evaluator, "b"), "1");
+    assert_eq!(display(&evaluator, "c"), "true");
+}
+
+#[test]
+fn negative_untyped_counts_are_errors() {
+    let evaluator = evaluate("total: 0\nrepeat -1 { total = total + 1 }");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::NegativeCount(-1)]
+    ));
+
+    let evaluator = evaluate("a: parse_int_radix(\"ff\", -16)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::InvalidRadix(-16)]
+    ));
+}
+
+#[test]
+fn boolean_literals_and_logical_operators() {
+    let evaluator = evaluate_ok(
+        "a: true && false\nb: true || false\nc: !true\nd: false || !false && true\ne: !(1 < 2)",
+    );
+    assert_eq!(display(&evaluator, "a"), "false");
+    assert_eq!(display(&evaluator, "b"), "true");
+    assert_eq!(display(&evaluator, "c"), "false");
+    assert_eq!(display(&evaluator, "d"), "true");
+    assert_eq!(display(&evaluator, "e"), "false");
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, evaluate_output, evaluate_with};
+use xlang_vm::{const_value::Type, error::EvaluationErrorKind};
+
+#[test]
+fn float_precision_applies_to_display_and_interpolation() {
+    let source = "pi: 3.14159\ns: \"pi is {pi}\"\nt: to_string(pi)";
+    let evaluator = evaluate_with(source, |evaluator| evaluator.set_float_precision(Some(2)));
+    assert_eq!(display(&evaluator, "pi"), "3.14");
+    assert_eq!(display(&evaluator, "s"), "pi is 3.14");
+    assert_eq!(display(&evaluator, "t"), "3.14");
+
+    // Floats print as short as they can be while still round-tripping by default
+    let evaluator = evaluate_ok(source);
+    assert_eq!(evaluator.float_precision(), None);
+    assert_eq!(display(&evaluator, "s"), "pi is 3.14159");
+}
+
+#[test]
+fn format_specifiers_pad_and_round() {
+    let evaluator = evaluate_ok(
+        "x: 7i32\npi: 3.14159\ns: \"hi\"\na: \"[{x:04}]\"\nb: \"[{pi:.2}]\"\nc: \"[{pi:8.3}]\"\n\
+         d: \"[{x:<5}|{x:^5}|{x:>5}]\"\nf: \"[{s:>4}]\"",
+    );
+    assert_eq!(display(&evaluator, "a"), "[0007]");
+    assert_eq!(display(&evaluator, "b"), "[3.14]");
+    assert_eq!(display(&evaluator, "c"), "[   3.142]");
+    assert_eq!(display(&evaluator, "d"), "[7    |  7  |    7]");
+    assert_eq!(display(&evaluator, "f"), "[  hi]");
+}
+
+#[test]
+fn format_specifiers_must_suit_the_value() {
+    let evaluator = evaluate("s: \"hi\"\nn: 42u8\na: \"[{s:04}]\"\nb: \"[{n:.2}]\"");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::InvalidFormatSpec(_, Type::String),
+            EvaluationErrorKind::InvalidFormatSpec(..),
+        ]
+    ));
+}
+
+#[test]
+fn print_and_println_write_to_the_output() {
+    let (_, output) = evaluate_output("print(1, \"a\")\nprintln(true)\nprintln()", |_| {});
+    assert_eq!(output, "1 atrue\n\n");
+}
+
+#[test]
+fn print_uses_the_float_precision() {
+    let (_, output) = evaluate_output("x: 3.14159\nprintln(x)\nprint(x, 2.0)", |evaluator| {
+        evaluator.set_float_precision(Some(2))
+    });
+    assert_eq!(output, "3.14\n3.14 2.00");
+}
+
+#[test]
+fn debug_writes_the_tree_of_a_value_to_the_output() {
+    let (evaluator, output) = evaluate_output("debug([1, 2])", |_| {});
+    assert!(!evaluator.has_errors());
+    assert!(output.starts_with("Const Value\n"), "{output}");
+    assert!(
+        output.contains("Integer: 1") && output.contains("Integer: 2"),
+        "{output}"
+    );
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, evaluate_with, evaluator, export};
+use xlang_core::token::Range;
+use xlang_vm::{
+    const_value::{ConstValue, Type},
+    error::{EvaluationErrorKind, TypeHint},
+    evaluator::{Evaluator, StringMode},
+};
+
+#[test]
+fn strings_compare_lexicographically() {
+    let evaluator = evaluate_ok(
+        "a: \"abc\" < \"abd\"\nb: \"b\" > \"abc\"\nc: \"ab\" < \"abc\"\nd: \"é\" > \"z\"\n\
+         e: \"abc\" <= \"abc\"\nf: \"abc\" >= \"abd\"",
+    );
+    for (name, expected) in [
+        ("a", "true"),
+        ("b", "true"),
+        ("c", "true"),
+        ("d", "true"),
+        ("e", "true"),
+        ("f", "false"),
+    ] {
+        assert_eq!(display(&evaluator, name), expected, "{name}");
+    }
+}
+
+#[test]
+fn strings_are_equal_by_content() {
+    let evaluator =
+        evaluate_ok("s: \"ab\"\nt: \"{s}c\"\na: t == \"abc\"\nb: t != \"abc\"\nc: s == \"abc\"");
+    assert_eq!(display(&evaluator, "a"), "true");
+    assert_eq!(display(&evaluator, "b"), "false");
+    assert_eq!(display(&evaluator, "c"), "false");
+}
+
+#[test]
+fn strings_compared_with_other_types_are_errors() {
+    let evaluator = evaluate("a: \"1\" < 1");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::IncomparableTypes(
+            Type::String,
+            Type::CoercibleInteger
+        )]
+    ));
+}
+
+#[test]
+fn string_methods() {
+    let evaluator = evaluate_ok(
+        "s: \"Hello World\"\na: s.upper()\nb: s.lower()\nc: s.len()\nd: s.contains(\"World\")\n\
+         e: s.contains(\"xyz\")\nf: \"a,b,c\".split(\",\")",
+    );
+    assert_eq!(display(&evaluator, "a"), "HELLO WORLD");
+    assert_eq!(display(&evaluator, "b"), "hello world");
+    assert_eq!(display(&evaluator, "c"), "11");
+    assert_eq!(display(&evaluator, "d"), "true");
+    assert_eq!(display(&evaluator, "e"), "false");
+    assert_eq!(display(&evaluator, "f"), "[a, b, c]");
+    assert_eq!(
+        export(&evaluator, "f").ty,
+        Type::Array(Box::new(Type::String), None)
+    );
+}
+
+#[test]
+fn string_methods_check_their_arguments_and_receiver() {
+    let evaluator = evaluate("s: \"abc\"\na: s.contains(5)\nb: s.upper(1)\nx: 5i32\nc: x.upper()");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+            EvaluationErrorKind::ArgCountMismatch(1, 0),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+        ]
+    ));
+}
+
+#[test]
+fn strings_concatenate_with_plus() {
+    let evaluator = evaluate_ok("a: \"ab\" + \"cd\"\nb: a + \"\"\nc: \"x\" == \"x\"");
+    assert_eq!(display(&evaluator, "a"), "abcd");
+    assert_eq!(export(&evaluator, "a").ty, Type::String);
+    assert_eq!(display(&evaluator, "b"), "abcd");
+    assert_eq!(display(&evaluator, "c"), "true");
+}
+
+#[test]
+fn adding_a_string_to_something_else_is_an_error() {
+    let evaluator = evaluate("b: \"ab\" + 1\nc: 1 + \"ab\"");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Operand),
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Operand),
+        ]
+    ));
+}
+
+#[test]
+fn join_concatenates_strings_with_a_separator() {
+    let evaluator = evaluate_ok(
+        "a: join([\"x\", \"y\", \"z\"], \", \")\nb: join([], \"-\")\n\
+         c: join(\"a b c\".split(\" \"), \"+\")",
+    );
+    assert_eq!(display(&evaluator, "a"), "x, y, z");
+    assert_eq!(display(&evaluator, "b"), "");
+    assert_eq!(display(&evaluator, "c"), "a+b+c");
+}
+
+#[test]
+fn join_does_not_stringify_elements() {
+    let evaluator =
+        evaluate("d: join([1, 2], \",\")\ne: join([\"q\"], 3)\nf: join(\"abc\", \",\")");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(
+                Type::Array(..),
+                Type::Array(..),
+                TypeHint::Parameter
+            ),
+            EvaluationErrorKind::TypeMismatch(_, Type::String, TypeHint::Parameter),
+            EvaluationErrorKind::TypeMismatch(Type::String, Type::Array(..), TypeHint::Parameter),
+        ]
+    ));
+}
+
+/// `value[position]` of a string built in Rust rather than lexed
+fn index(evaluator: &Evaluator, string: &str, position: u64) -> String {
+    let value = ConstValue::string(string.to_string());
+    let position = ConstValue::cinteger(position);
+    evaluator
+        .evaluate_index(value, position, false, Range::default())
+        .to_string()
+}
+
+#[test]
+fn non_ascii_source_is_lexed() {
+    let evaluator = evaluate_ok("é: \"héllo wörld 👋\"\nn: é.len()\nc: é[1]\n");
+    assert_eq!(display(&evaluator, "é"), "héllo wörld 👋");
+    assert_eq!(display(&evaluator, "n"), "13");
+    assert_eq!(display(&evaluator, "c"), "é");
+}
+
+#[test]
+fn char_mode_counts_unicode_scalars() {
+    let evaluator = evaluator("0");
+    assert_eq!(StringMode::Chars.len("héllo"), 5);
+    assert_eq!(StringMode::Chars.len("e\u{301}"), 2);
+    assert_eq!(index(&evaluator, "héllo", 1), "é");
+    assert_eq!(index(&evaluator, "héllo", 2), "l");
+    assert!(errors(&evaluator).is_empty());
+}
+
+#[test]
+fn byte_mode_counts_utf8_bytes() {
+    let evaluator = evaluator("0");
+    evaluator.set_string_mode(StringMode::Bytes);
+    assert_eq!(StringMode::Bytes.len("héllo"), 6);
+    assert_eq!(index(&evaluator, "héllo", 0), "h");
+    assert_eq!(index(&evaluator, "héllo", 3), "l");
+    assert!(errors(&evaluator).is_empty());
+
+    // `é` is the two bytes at 1 and 2, so the byte at 1 ends in the middle of it
+    assert_eq!(index(&evaluator, "héllo", 1), "()");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::NotCharBoundary(2)]
+    ));
+}
+
+#[test]
+fn string_mode_applies_to_len_and_indexing_in_source() {
+    let source = "s: \"añb\"\nn: s.len()\nc: s[1..3]";
+    let chars = evaluate_ok(source);
+    assert_eq!(display(&chars, "n"), "3");
+    assert_eq!(display(&chars, "c"), "ñb");
+
+    let bytes = evaluate_with(source, |evaluator| {
+        evaluator.set_string_mode(StringMode::Bytes)
+    });
+    assert_eq!(display(&bytes, "n"), "4");
+    assert_eq!(display(&bytes, "c"), "ñ");
+}
+
+#[cfg(feature = "graphemes")]
+#[test]
+fn grapheme_mode_counts_grapheme_clusters() {
+    let evaluator = evaluator("0");
+    evaluator.set_string_mode(StringMode::Graphemes);
+    // `e` followed by a combining acute accent, and a waving hand with a skin tone modifier
+    let string = "e\u{301}x👋🏽";
+    assert_eq!(StringMode::Graphemes.len(string), 3);
+    assert_eq!(index(&evaluator, string, 0), "e\u{301}");
+    assert_eq!(index(&evaluator, string, 2), "👋🏽");
+    assert!(errors(&evaluator).is_empty());
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use std::hash::{DefaultHasher, Hasher};
+
+use common::{display, errors, evaluate, evaluate_ok, export};
+use xlang_vm::{const_value::Type, error::EvaluationErrorKind};
+
+#[test]
+fn nested_tuples_are_indexed_through_each_level() {
+    let evaluator = evaluate_ok("t: ((1, 2), (3, 4))\na: t.0.1\nb: t.1.0\nc: t.1");
+    assert_eq!(display(&evaluator, "a"), "2");
+    assert_eq!(display(&evaluator, "b"), "3");
+    assert_eq!(
+        export(&evaluator, "c").ty,
+        Type::Tuple(vec![Type::CoercibleInteger, Type::CoercibleInteger])
+    );
+    assert_eq!(
+        export(&evaluator, "t").ty,
+        Type::Tuple(vec![
+            Type::Tuple(vec![Type::CoercibleInteger, Type::CoercibleInteger]),
+            Type::Tuple(vec![Type::CoercibleInteger, Type::CoercibleInteger]),
+        ])
+    );
+}
+
+#[test]
+fn out_of_range_and_too_deep_tuple_indices_are_errors() {
+    let evaluator = evaluate("t: ((1, 2), (3, 4))\na: t.2\nb: t.0.1.0");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::InvalidTupleIndex(2, Type::Tuple(_)),
+            EvaluationErrorKind::InvalidTupleIndex(0, Type::CoercibleInteger),
+        ]
+    ));
+}
+
+#[test]
+fn commas_build_tuples_and_semicolons_sequence() {
+    let evaluator = evaluate_ok("x: 1\nt: (x = 5, x + 1)\ny: x\ns: (x = 7; x + 1)\nz: x");
+    // Both forms evaluate each element in order for its side effects
+    assert_eq!(display(&evaluator, "t"), "5, 6");
+    assert_eq!(
+        export(&evaluator, "t").ty,
+        Type::Tuple(vec![Type::CoercibleInteger, Type::CoercibleInteger])
+    );
+    assert_eq!(display(&evaluator, "y"), "5");
+    // but a sequence yields only its last value
+    assert_eq!(display(&evaluator, "s"), "8");
+    assert_eq!(export(&evaluator, "s").ty, Type::CoercibleInteger);
+    assert_eq!(display(&evaluator, "z"), "7");
+}
+
+#[test]
+fn labeled_tuples_are_accessed_by_index_or_label() {
+    let evaluator = evaluate_ok(
+        "p: (x: 1i32, y: 2i32)\na: p.0\nb: p.y\ns: (only: 5i32)\nf: s.only\nt: (x: 1i32, 2i32)\ng: t.1\n\
+         h: \"{p.x + p.1}\"",
+    );
+    assert_eq!(display(&evaluator, "p"), "x: 1, y: 2");
+    assert_eq!(display(&evaluator, "a"), "1");
+    assert_eq!(display(&evaluator, "b"), "2");
+    assert_eq!(display(&evaluator, "f"), "5");
+    // Labels may be left off some elements
+    assert_eq!(display(&evaluator, "g"), "2");
+    assert_eq!(display(&evaluator, "h"), "3");
+}
+
+#[test]
+fn undefined_tuple_labels_are_errors() {
+    let evaluator = evaluate("p: (x: 1i32, y: 2i32)\nc: p.z\nq: (3i32, 4i32)\ne: q.w");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::InvalidTupleLabel(z, Type::Tuple(_)),
+            EvaluationErrorKind::InvalidTupleLabel(w, Type::Tuple(_)),
+        ] if z == "z" && w == "w"
+    ));
+}
+
+#[test]
+fn tuples_compare_element_by_element() {
+    let evaluator = evaluate_ok(
+        "a: (1, 2)\nb: (1, 3)\nc: (1, 2, 3)\na1: a == (1, 2)\na2: a == b\na3: a != b\na4: a < b\na5: b > a\n\
+         a6: c <= (1, 2, 3)\na7: a == c\na8: a != c\nb1: (true, \"a\") == (true, \"a\")\n\
+         b3: (\"a\", (1, 2)) < (\"a\", (1, 3))\nb4: max(a, b)",
+    );
+    for (name, expected) in [
+        ("a1", "true"),
+        ("a2", "false"),
+        ("a3", "true"),
+        ("a4", "true"),
+        ("a5", "true"),
+        ("a6", "true"),
+        // Tuples of different arity are never equal
+        ("a7", "false"),
+        ("a8", "true"),
+        ("b1", "true"),
+        ("b3", "true"),
+        ("b4", "1, 3"),
+    ] {
+        assert_eq!(display(&evaluator, name), expected, "{name}");
+    }
+}
+
+#[test]
+fn tuples_of_different_arity_or_unordered_elements_are_incomparable() {
+    let evaluator = evaluate("a: (1, 2) < (1, 2, 3)\nb: (true, 1) < (false, 2)");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IncomparableTypes(..),
+            EvaluationErrorKind::IncomparableTypes(..),
+        ]
+    ));
+}
+
+#[test]
+fn equal_tuples_hash_the_same() {
+    let evaluator = evaluate_ok("a: (1, 2.0)\nb: (1.0, 2)\nc: (2, 1)");
+    let hash = |name| {
+        let mut hasher = DefaultHasher::new();
+        export(&evaluator, name).hash_value(&mut hasher);
+        hasher.finish()
+    };
+    assert!(export(&evaluator, "a").eq_value(&export(&evaluator, "b")));
+    assert_eq!(hash("a"), hash("b"));
+    assert_ne!(hash("a"), hash("c"));
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use common::{display, errors, evaluate, evaluate_ok, evaluate_with, export};
+use xlang_vm::{
+    const_value::Type,
+    error::{EvaluationErrorKind, TypeHint},
+};
+
+#[test]
+fn suffixed_literals_are_sized() {
+    let evaluator = evaluate_ok("a: 5i32\nb: 255u8\nc: 3.14f32\nd: 2f64\ne: 127i8\nf: 5");
+    let integer = |width, signed| Type::Integer { width, signed };
+    assert_eq!(export(&evaluator, "a").ty, integer(32, true));
+    assert_eq!(export(&evaluator, "b").ty, integer(8, false));
+    assert_eq!(export(&evaluator, "c").ty, Type::Float { width: 32 });
+    assert_eq!(export(&evaluator, "d").ty, Type::Float { width: 64 });
+    assert_eq!(export(&evaluator, "e").ty, integer(8, true));
+    assert_eq!(export(&evaluator, "f").ty, Type::CoercibleInteger);
+    assert_eq!(display(&evaluator, "b"), "255");
+    assert_eq!(display(&evaluator, "c"), "3.14");
+}
+
+#[test]
+fn out_of_range_suffixed_literals_overflow() {
+    let evaluator = evaluate("a: 300u8\nb: 128i8");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IntegerOverflow(a, Type::Integer { width: 8, signed: false }),
+            EvaluationErrorKind::IntegerOverflow(b, Type::Integer { width: 8, signed: true }),
+        ] if a == "300" && b == "128"
+    ));
+}
+
+#[test]
+fn sized_literals_resolve_to_the_smallest_fitting_type() {
+    let evaluator = evaluate_with(
+        "a: 5\nb: 127\nc: 128\nd: 300\ne: 70000\nf: 3000000000\ng: 10000000000000000000\nh: 5u8",
+        |evaluator| evaluator.set_sized_literals(true),
+    );
+    assert!(!evaluator.has_errors(), "{:?}", errors(&evaluator));
+    let integer = |width, signed| Type::Integer { width, signed };
+    for (name, ty) in [
+        ("a", integer(8, true)),
+        ("b", integer(8, true)),
+        ("c", integer(16, true)),
+        ("d", integer(16, true)),
+        ("e", integer(32, true)),
+        ("f", integer(64, true)),
+        // Only fits unsigned
+        ("g", integer(64, false)),
+        // Already sized
+        ("h", integer(8, false)),
+    ] {
+        assert_eq!(export(&evaluator, name).ty, ty, "{name}");
+    }
+}
+
+#[test]
+fn sized_literals_overflow_their_type() {
+    let evaluator = evaluate_with("a: 5\nb: a + 200", |evaluator| {
+        evaluator.set_sized_literals(true)
+    });
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::IntegerOverflow(
+            _,
+            Type::Integer {
+                width: 8,
+                signed: true
+            }
+        )]
+    ));
+}
+
+#[test]
+fn sized_literals_are_off_by_default() {
+    let evaluator = evaluate_ok("a: 5\nb: a + 200");
+    assert_eq!(export(&evaluator, "a").ty, Type::CoercibleInteger);
+    assert_eq!(display(&evaluator, "b"), "205");
+
+    // Once sized, arithmetic is checked against the narrow type
+    let evaluator = evaluate_with("a: 5\nb: a + 200", |evaluator| {
+        evaluator.set_sized_literals(true)
+    });
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::IntegerOverflow(..)]
+    ));
+}
+
+#[test]
+fn bitcast_reinterprets_and_cast_preserves_the_value() {
+    let evaluator = evaluate_ok(
+        "a: bitcast(-1, u32)\nc: bitcast(-1i32, u32)\ng: bitcast(4294967295u32, i32)\nm: bitcast(255u8, i8)\n\
+         f: cast(200u16, u8)\ni: cast(-5i8, i64)",
+    );
+    let integer = |width, signed| Type::Integer { width, signed };
+    assert_eq!(display(&evaluator, "a"), "4294967295");
+    assert_eq!(export(&evaluator, "a").ty, integer(32, false));
+    assert_eq!(display(&evaluator, "c"), "4294967295");
+    assert_eq!(display(&evaluator, "g"), "-1");
+    assert_eq!(export(&evaluator, "g").ty, integer(32, true));
+    assert_eq!(display(&evaluator, "m"), "-1");
+    assert_eq!(display(&evaluator, "f"), "200");
+    assert_eq!(display(&evaluator, "i"), "-5");
+    assert_eq!(export(&evaluator, "i").ty, integer(64, true));
+}
+
+#[test]
+fn casts_that_lose_the_value_are_errors() {
+    let evaluator = evaluate(
+        "b: cast(-1, u32)\nd: cast(-1i32, u32)\ne: cast(300u16, u8)\nh: bitcast(1u8, i32)\nk: cast(1, f32)",
+    );
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::IntegerOverflow(b, Type::Integer { width: 32, signed: false }),
+            EvaluationErrorKind::IntegerOverflow(d, Type::Integer { width: 32, signed: false }),
+            EvaluationErrorKind::IntegerOverflow(e, Type::Integer { width: 8, signed: false }),
+            // Bitcasts need the same width
+            EvaluationErrorKind::TypeMismatch(_, _, TypeHint::Parameter),
+            EvaluationErrorKind::ExpectedIntegerType,
+        ] if b == "-1" && d == "-1" && e == "300"
+    ));
+}
+
+#[test]
+fn mixed_width_integers_widen() {
+    let evaluator = evaluate_ok(
+        "a: 1i32 + 2i64\nb: 200u8 + 1000u16\nd: -5i32 + 3u8\ne: 4000000000u32 + -1i32\ng: 3i16 == 3u8",
+    );
+    let integer = |width, signed| Type::Integer { width, signed };
+    assert_eq!(display(&evaluator, "a"), "3");
+    assert_eq!(export(&evaluator, "a").ty, integer(64, true));
+    assert_eq!(display(&evaluator, "b"), "1200");
+    assert_eq!(export(&evaluator, "b").ty, integer(16, false));
+    // Signed and unsigned widen to a signed type that holds both
+    assert_eq!(display(&evaluator, "d"), "-2");
+    assert_eq!(export(&evaluator, "d").ty, integer(32, true));
+    assert_eq!(display(&evaluator, "e"), "3999999999");
+    assert_eq!(export(&evaluator, "e").ty, integer(64, true));
+    assert_eq!(display(&evaluator, "g"), "true");
+}
+
+#[test]
+fn integers_without_a_common_type_are_errors() {
+    let evaluator = evaluate("c: 1u64 + 1i64");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::BinExpMismatch { .. }]
+    ));
+}
+
+#[test]
+fn typed_parameters_resolve_on_every_call() {
+    let evaluator = evaluate_ok(
+        "P: (i32 x)\nf: (P p) -> (i32 out), out = p.x\n\
+         total: 0i32\nfor i in 0..100 { total = total + f(P(2i32)).out }",
+    );
+    assert_eq!(display(&evaluator, "total"), "200");
+}
+
+#[test]
+fn redeclared_type_names_resolve_to_the_new_record() {
+    let evaluator = evaluate_ok(
+        "make: (i32 v) -> (i32 out), P: (i32 y), B: (P p), out = B(P(v)).p.y\n\
+         a: make(3i32)\nb: make(4i32)\n\
+         Point: (i32 x)\nf: (Point p) -> (i32 out), out = p.x\nc: f(Point(7i32))\n\
+         Point: (i32 x, i32 z)\ng: (Point p) -> (i32 out), out = p.z\nd: g(Point(1i32, 9i32))",
+    );
+    assert_eq!(display(&evaluator, "a"), "{ out: 3 }");
+    assert_eq!(display(&evaluator, "b"), "{ out: 4 }");
+    assert_eq!(display(&evaluator, "c"), "{ out: 7 }");
+    assert_eq!(display(&evaluator, "d"), "{ out: 9 }");
+}
@@ -0,0 +1,391 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{display, errors, evaluate, evaluate_ok, export};
+use linked_hash_map::LinkedHashMap;
+use xlang_vm::{
+    const_value::{ConstValue, ConstValueKind, OwnedData, OwnedDataError, Type},
+    error::{EvaluationErrorKind, Impurity, TypeHint},
+};
+
+fn record(name: &str, members: impl IntoIterator<Item = (&'static str, OwnedData)>) -> OwnedData {
+    OwnedData::Record {
+        name: Some(name.to_string()),
+        members: LinkedHashMap::from_iter(
+            members
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value)),
+        ),
+    }
+}
+
+fn i32(value: u64) -> OwnedData {
+    OwnedData::Integer {
+        value,
+        width: Some(32),
+        signed: true,
+    }
+}
+
+#[test]
+fn nested_records_convert_to_owned_data() {
+    let evaluator = evaluate_ok(
+        "P: (i32 x, string s)\nO: (P inner, [i32] xs, f64 y)\n\
+         o: O(P(1i32, \"a\"), [1i32, 2i32], 1.5)\nn: -1",
+    );
+    let expected = record(
+        "O",
+        [
+            (
+                "inner",
+                record("P", [("x", i32(1)), ("s", OwnedData::String("a".into()))]),
+            ),
+            ("xs", OwnedData::Array(vec![i32(1), i32(2)])),
+            (
+                "y",
+                OwnedData::Float {
+                    value: 1.5,
+                    width: Some(64),
+                },
+            ),
+        ],
+    );
+    assert_eq!(export(&evaluator, "o").to_owned_data(), Ok(expected));
+    assert_eq!(
+        export(&evaluator, "n").to_owned_data(),
+        Ok(OwnedData::Integer {
+            value: -1i64 as u64,
+            width: None,
+            signed: true
+        })
+    );
+}
+
+#[test]
+fn functions_are_not_owned_data() {
+    let evaluator = evaluate_ok("f: (i32 x) -> (i32 r), r = x\nt: (1, f)");
+    assert_eq!(
+        export(&evaluator, "f").to_owned_data(),
+        Err(OwnedDataError::Function)
+    );
+    // Also when they're inside another value
+    assert_eq!(
+        export(&evaluator, "t").to_owned_data(),
+        Err(OwnedDataError::Function)
+    );
+}
+
+fn elements(value: ConstValue) -> Vec<String> {
+    value
+        .into_elements()
+        .expect("value isn't iterable")
+        .map(|element| element.to_string())
+        .collect()
+}
+
+#[test]
+fn arrays_and_ranges_iterate_their_elements() {
+    let evaluator = evaluate_ok("a: [3, 1, 2]\nr: 2..5\ni: 2..=5\ne: 0..0\ns: \"abc\"");
+    assert_eq!(elements(export(&evaluator, "a")), ["3", "1", "2"]);
+    assert_eq!(elements(export(&evaluator, "r")), ["2", "3", "4"]);
+    assert_eq!(elements(export(&evaluator, "i")), ["2", "3", "4", "5"]);
+    assert!(elements(export(&evaluator, "e")).is_empty());
+
+    let mut range = export(&evaluator, "r").into_elements().unwrap();
+    assert_eq!(range.size_hint(), (3, Some(3)));
+    assert!(range.all(|element| element.ty == Type::CoercibleInteger));
+    assert!(export(&evaluator, "s").into_elements().is_none());
+}
+
+#[test]
+fn ranges_with_negative_bounds_are_signed() {
+    let evaluator = evaluate_ok(
+        "r: -2..2\ni: -3i8..=-1i8\nn: 0\nfor x in -2..2 { n = n + 1 }\nt: 0\nfor x in r { t = t + x }",
+    );
+    assert_eq!(display(&evaluator, "r"), "-2..2");
+    assert_eq!(display(&evaluator, "i"), "-3..=-1");
+    assert_eq!(elements(export(&evaluator, "r")), ["-2", "-1", "0", "1"]);
+    assert_eq!(elements(export(&evaluator, "i")), ["-3", "-2", "-1"]);
+    assert_eq!(display(&evaluator, "n"), "4");
+    assert_eq!(display(&evaluator, "t"), "-2");
+
+    // Untyped bounds have to fit the type of the other bound
+    let evaluator = evaluate("x: 3u8\nr: -1..x");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::IntegerOverflow(n, Type::Integer { width: 8, signed: false })]
+            if n == "-1"
+    ));
+}
+
+#[test]
+fn record_literal_fields_see_earlier_fields() {
+    let evaluator =
+        evaluate_ok("r: { a: 1, b: a + 1 }\nb: r.b\na: 10\ns: { a: 1, b: a }\nt: { a: a + 1 }");
+    assert_eq!(display(&evaluator, "r"), "{ a: 1, b: 2 }");
+    assert_eq!(display(&evaluator, "b"), "2");
+    // The field shadows the outer binding of the same name
+    assert_eq!(display(&evaluator, "s"), "{ a: 1, b: 1 }");
+    // Until it's declared, the field's own name is the outer binding
+    assert_eq!(display(&evaluator, "t"), "{ a: 11 }");
+}
+
+#[test]
+fn record_literal_forward_references_are_errors() {
+    let evaluator = evaluate("q: { c: d, d: 2 }");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::ForwardReference(field)] if field == "d"
+    ));
+}
+
+#[test]
+fn uninitialized_record_return_parameters_default_each_member() {
+    let evaluator = evaluate(
+        "Point: (i32 x, i32 y)\nPair: (Point a, f32 w)\nmk: (i32 v) -> (Pair p), v\np: mk(1).p",
+    );
+    assert_eq!(display(&evaluator, "p"), "{ a: { x: 0, y: 0 }, w: 0 }");
+}
+
+#[test]
+fn function_types_have_no_default() {
+    let function = Type::Function {
+        parameters: LinkedHashMap::new(),
+        return_parameters: LinkedHashMap::new(),
+    };
+    assert!(ConstValue::default_for(&function).is_none());
+    assert!(ConstValue::default_for(&Type::Tuple(vec![Type::Boolean, function.clone()])).is_none());
+    // An optional function defaults to null
+    let optional = Type::Optional(Box::new(function));
+    assert_eq!(
+        ConstValue::default_for(&optional).map(|value| value.to_string()),
+        Some("null".to_string())
+    );
+}
+
+#[test]
+fn exports_returns_top_level_bindings_in_order() {
+    let evaluator = evaluate_ok(
+        "Point: (i32 x, i32 y)\na: 1\nb: \"two\"\nc: (1, 2.5)\nf: (i32 x) -> (i32 y), y = x\na = 5",
+    );
+    let values = evaluator.exports(false);
+    assert_eq!(values.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+    // The final state, after the module has run
+    assert_eq!(values["a"].to_string(), "5");
+    assert_eq!(values["b"].to_string(), "two");
+    assert_eq!(values["c"].to_string(), "1, 2.5");
+
+    let values = evaluator.exports(true);
+    assert_eq!(values.keys().collect::<Vec<_>>(), ["a", "b", "c", "f"]);
+}
+
+#[test]
+fn constants_are_folded_once_and_immutable() {
+    let evaluator = evaluate(
+        "sq: pure (i32 a) -> (i32 b), b = a * a\nconst N = sq(4i32).b + 1i32\nN = 5i32\n\
+         P: (i32 x, i32 y)\nconst O = P(1i32, 2i32)\nO.x = 3i32",
+    );
+    assert_eq!(display(&evaluator, "N"), "17");
+    assert_eq!(display(&evaluator, "O"), "{ x: 1, y: 2 }");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::AssignToImmutable(n),
+            EvaluationErrorKind::AssignToImmutable(o),
+        ] if n == "N" && o == "O"
+    ));
+}
+
+#[test]
+fn constants_with_impure_initializers_are_errors() {
+    let evaluator = evaluate("use std.io\nconst BAD = print(\"hi\")");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::NonConstInitializer(name, Impurity::ImpureCall(call))]
+            if name == "BAD" && call == "print"
+    ));
+}
+
+#[test]
+fn records_convert_between_compatible_types() {
+    let evaluator = evaluate_ok(
+        "Point: (i32 x, i32 y)\nPoint3: (i32 x, i32 y, i32 z)\np: Point(1i32, 2i32)\n\
+         up: convert(p, Point3)\ndown: convert(Point3(4i32, 5i32, 6i32), Point)",
+    );
+    // Fields missing from the source are defaulted, and extra ones are dropped
+    assert_eq!(display(&evaluator, "up"), "{ x: 1, y: 2, z: 0 }");
+    assert_eq!(display(&evaluator, "down"), "{ x: 4, y: 5 }");
+}
+
+#[test]
+fn records_with_incompatible_fields_do_not_convert() {
+    let evaluator = evaluate(
+        "Point: (i32 x, i32 y)\nNamed: (u8 x, f32 w)\np: Point(1i32, 2i32)\n\
+         bad: convert(p, Named)\nnr: convert(5, Point)",
+    );
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::TypeMismatch(..),
+            EvaluationErrorKind::ExpectedRecord(..),
+        ]
+    ));
+}
+
+#[test]
+fn frozen_records_are_deeply_immutable() {
+    let evaluator = evaluate(
+        "Inner: (i32 v)\nOuter: (Inner inner, i32 n)\no: Outer(Inner(1i32), 2i32)\nf: freeze(o)\n\
+         f.inner.v = 5i32\nf.n = 3i32\nrv: f.inner.v\nrn: f.n\no.inner.v = 9i32\nfi: f.inner\nfi.v = 7i32",
+    );
+    // Reads still work, and the original isn't frozen
+    assert_eq!(display(&evaluator, "rv"), "1");
+    assert_eq!(display(&evaluator, "rn"), "2");
+    assert_eq!(display(&evaluator, "o"), "{ inner: { v: 9 }, n: 2 }");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::ImmutableMember(a),
+            EvaluationErrorKind::ImmutableMember(b),
+            EvaluationErrorKind::ImmutableMember(c),
+        ] if a == "f.inner.v" && b == "f.n" && c == "fi.v"
+    ));
+}
+
+#[test]
+fn frozen_bindings_can_be_reassigned() {
+    let evaluator = evaluate(
+        "Inner: (i32 v)\nOuter: (Inner inner, i32 n)\nf: freeze(Outer(Inner(1i32), 2i32))\n\
+         f = Outer(Inner(0i32), 0i32)\nf.n = 4i32\nx: freeze(5)",
+    );
+    assert_eq!(display(&evaluator, "f"), "{ inner: { v: 0 }, n: 4 }");
+    assert!(matches!(
+        errors(&evaluator)[..],
+        [EvaluationErrorKind::ExpectedRecord(..)]
+    ));
+}
+
+#[test]
+fn clones_are_independent_of_the_original() {
+    let evaluator = evaluate_ok(
+        "I: (i32 v)\nO: (I inner, i32 n)\na: O(I(1i32), 2i32)\nb: clone(a)\nb.inner.v = 9i32\n\
+         f: (i32 x) -> (i32 r), r = x + 1i32\ng: clone(f)\nh: g(1i32).r\nt: clone((1, [2, 3]))",
+    );
+    assert_eq!(display(&evaluator, "a"), "{ inner: { v: 1 }, n: 2 }");
+    assert_eq!(display(&evaluator, "b"), "{ inner: { v: 9 }, n: 2 }");
+    // A cloned function shares its body
+    assert_eq!(display(&evaluator, "h"), "2");
+    assert_eq!(display(&evaluator, "t"), "1, [2, 3]");
+}
+
+#[test]
+fn record_defaults_fill_missing_fields() {
+    let evaluator = evaluate_ok(
+        "P: (i32 x, i32 y = 10i32, [i32] zs = [1i32, 2i32])\na: P(1i32)\nb: P(2i32, 3i32)\n\
+         a.zs[0] = 100i32\nc: P(3i32)",
+    );
+    assert_eq!(display(&evaluator, "a"), "{ x: 1, y: 10, zs: [100, 2] }");
+    assert_eq!(display(&evaluator, "b"), "{ x: 2, y: 3, zs: [1, 2] }");
+    // Each instance gets its own default
+    assert_eq!(display(&evaluator, "c"), "{ x: 3, y: 10, zs: [1, 2] }");
+}
+
+#[test]
+fn record_defaults_are_evaluated_on_instantiation() {
+    let evaluator = evaluate_ok("n: 7i32\nC: (i32 v = n * 2i32)\na: C()\nn = 8i32\nb: C()");
+    assert_eq!(display(&evaluator, "a"), "{ v: 14 }");
+    assert_eq!(display(&evaluator, "b"), "{ v: 16 }");
+}
+
+#[test]
+fn record_defaults_must_fit_their_type() {
+    let evaluator = evaluate("B: (u8 v = 300)\nb: B()\nD: (i8 v = -5)\nd: D()");
+    assert_eq!(display(&evaluator, "d"), "{ v: -5 }");
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [EvaluationErrorKind::IntegerOverflow(value, Type::Integer { width: 8, signed: false })]
+            if value == "300"
+    ));
+}
+
+#[test]
+fn record_instantiation_fills_omitted_fields() {
+    let evaluator = evaluate_ok(
+        "P: (i32 x, i32 y = 5i32, [i32] zs)\na: P { x: 1i32, y: 2i32, zs: [1i32] }\n\
+         b: P { x: 3i32 }\nc: P {}\nd: (P { x: 9i32 }).x",
+    );
+    assert_eq!(display(&evaluator, "a"), "{ x: 1, y: 2, zs: [1] }");
+    // Omitted fields take their default, or the default for their type
+    assert_eq!(display(&evaluator, "b"), "{ x: 3, y: 5, zs: [] }");
+    assert_eq!(display(&evaluator, "c"), "{ x: 0, y: 5, zs: [] }");
+    assert_eq!(display(&evaluator, "d"), "9");
+}
+
+#[test]
+fn record_instantiation_checks_fields() {
+    let evaluator = evaluate(
+        "P: (i32 x, i32 y = 5i32)\nc: P { x: 1i32, w: 2 }\nd: P { x: \"s\" }\ne: Q { x: 1 }",
+    );
+    assert!(matches!(
+        &errors(&evaluator)[..],
+        [
+            EvaluationErrorKind::UnknownField(field, record),
+            EvaluationErrorKind::TypeMismatch(Type::String, _, TypeHint::Record),
+            EvaluationErrorKind::SymbolNotFound(..),
+        ] if field == "w" && record == "P"
+    ));
+}
+
+fn members(value: &ConstValue) -> &Arc<LinkedHashMap<String, ConstValue>> {
+    let ConstValueKind::RecordInstance { members, .. } = &value.kind else {
+        panic!("`{value}` isn't a record instance");
+    };
+    members
+}
+
+fn array(value: &ConstValue) -> &Arc<Vec<ConstValue>> {
+    let ConstValueKind::Array(values) = &value.kind else {
+        panic!("`{value}` isn't an array");
+    };
+    values
+}
+
+#[test]
+fn copies_share_storage_until_written() {
+    let evaluator = evaluate_ok(
+        "P: ([i32] xs, i32 n)\na: P(sorted(0i32..1000i32), 1i32)\nb: a\nc: a\n\
+         total: 0i32\nrepeat 100 { t: a, total = total + t.xs[999] }\nm: [[1, 2], [3, 4]]\nn: m",
+    );
+    let (a, b, c) = (
+        export(&evaluator, "a"),
+        export(&evaluator, "b"),
+        export(&evaluator, "c"),
+    );
+    assert!(Arc::ptr_eq(members(&a), members(&b)));
+    assert!(Arc::ptr_eq(members(&a), members(&c)));
+    assert_eq!(display(&evaluator, "total"), "99900");
+    let (m, n) = (export(&evaluator, "m"), export(&evaluator, "n"));
+    assert!(Arc::ptr_eq(array(&m), array(&n)));
+}
+
+#[test]
+fn writes_copy_only_what_they_change() {
+    let evaluator = evaluate_ok(
+        "P: ([i32] xs, i32 n)\na: P(sorted(0i32..1000i32), 1i32)\nb: a\nb.n = 5i32\n\
+         m: [[1, 2], [3, 4]]\nn: m\nn[1][0] = 7",
+    );
+    let (a, b) = (export(&evaluator, "a"), export(&evaluator, "b"));
+    assert!(!Arc::ptr_eq(members(&a), members(&b)));
+    // The array member wasn't written to, so it's still shared
+    assert!(Arc::ptr_eq(
+        array(&members(&a)["xs"]),
+        array(&members(&b)["xs"])
+    ));
+    assert_eq!(display(&evaluator, "m"), "[[1, 2], [3, 4]]");
+    assert_eq!(display(&evaluator, "n"), "[[1, 2], [7, 4]]");
+    // Only the row that was written to is copied
+    let (m, n) = (export(&evaluator, "m"), export(&evaluator, "n"));
+    assert!(Arc::ptr_eq(array(&array(&m)[0]), array(&array(&n)[0])));
+    assert!(!Arc::ptr_eq(array(&array(&m)[1]), array(&array(&n)[1])));
+}